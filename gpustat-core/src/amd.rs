@@ -0,0 +1,145 @@
+//! AMD GPUs (Instinct MI, Radeon) through the amdgpu driver's sysfs files
+
+use crate::backend::GpuBackend;
+use crate::GpuStatError;
+use crate::drm::{self, Card};
+use crate::{host_name, GpuProcessInfo, GpuStat, GpuStatCollection, QueryOptions, MB};
+use chrono::Utc;
+use std::path::Path;
+
+const AMD_VENDOR_ID: &str = "0x1002";
+
+/// Stats of every amdgpu card, read from /sys/class/drm
+#[derive(Debug, Default)]
+pub struct AmdBackend;
+
+impl AmdBackend {
+    /// Whether any AMD GPU is present
+    pub fn detect() -> bool {
+        !drm::cards(AMD_VENDOR_ID).is_empty()
+    }
+}
+
+impl GpuBackend for AmdBackend {
+    fn query(&self, gpu_ids: Option<&[u32]>, _query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        let cards = drm::cards(AMD_VENDOR_ID);
+        if cards.is_empty() {
+            return Err("no AMD GPU found".into());
+        }
+        let clients = drm::clients("amdgpu");
+        let gpus = cards
+            .iter()
+            .enumerate()
+            .map(|(i, card)| (i as u32, card))
+            .filter(|(i, _)| gpu_ids.is_none_or(|ids| ids.contains(i)))
+            .map(|(i, card)| {
+                let procs = drm::memory_by_pid(&clients, &card.pdev, &["drm-memory-vram"]);
+                query_card(i, card, &procs)
+            })
+            .collect();
+        Ok(GpuStatCollection {
+            hostname: host_name(),
+            query_time: Utc::now(),
+            driver_version: drm::read_str(Path::new("/sys/module/amdgpu/version")),
+            cuda_version: None,
+            nvml_version: None,
+            sample_period: None,
+            query_latency: None,
+            temperature_unit: Default::default(),
+            memory_unit: Default::default(),
+            gpus,
+            users: Vec::new(),
+            by_process: Vec::new(),
+        })
+    }
+}
+
+fn query_card(index: u32, card: &Card, procs: &[(u32, u64)]) -> GpuStat {
+    let dev = &card.device;
+    let hwmon = drm::hwmon(dev);
+    let hwmon = hwmon.as_deref();
+    let memory_used_bytes = drm::read_u64(&dev.join("mem_info_vram_used")).unwrap_or(0);
+    let memory_total_bytes = drm::read_u64(&dev.join("mem_info_vram_total")).unwrap_or(0);
+    let (clock_sm, clock_sm_max) = dpm_clock(&dev.join("pp_dpm_sclk"));
+    let (clock_mem, clock_mem_max) = dpm_clock(&dev.join("pp_dpm_mclk"));
+    let (pcie_gen, pcie_gen_max, pcie_width, pcie_width_max) = drm::pcie_link(dev);
+    let memory_total = Some(memory_total_bytes).filter(|&t| t > 0);
+    GpuStat {
+        index,
+        name: drm::read_str(&dev.join("product_name"))
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| format!("AMD GPU {}", drm::read_str(&dev.join("device")).unwrap_or_default())),
+        uuid: drm::read_str(&dev.join("unique_id")).unwrap_or_else(|| card.pdev.clone()),
+        temperature: hwmon.and_then(|h| drm::temperature(h, "edge").or_else(|| drm::temperature(h, "junction"))),
+        temperature_memory: hwmon.and_then(|h| drm::temperature(h, "mem")),
+        fan_speed: hwmon.and_then(fan_percent),
+        fan_speeds: hwmon.and_then(fan_percent).into_iter().collect(),
+        utilization: drm::read_u64(&dev.join("gpu_busy_percent")).map(|u| u as u32),
+        power_draw: hwmon.and_then(|h| drm::power(h, "power1_average").or_else(|| drm::power(h, "power1_input"))),
+        power_limit: hwmon.and_then(|h| drm::power(h, "power1_cap")),
+        power_limit_default: hwmon.and_then(|h| drm::power(h, "power1_cap_default")),
+        power_limit_max: hwmon.and_then(|h| drm::power(h, "power1_cap_max")),
+        clock_sm,
+        clock_sm_max,
+        clock_graphics: clock_sm,
+        clock_graphics_max: clock_sm_max,
+        clock_mem,
+        clock_mem_max,
+        pcie_gen,
+        pcie_gen_max,
+        pcie_width,
+        pcie_width_max,
+        memory_used: memory_used_bytes / MB,
+        memory_total: memory_total_bytes / MB,
+        memory_free: memory_total_bytes.saturating_sub(memory_used_bytes) / MB,
+        memory_used_bytes,
+        memory_total_bytes,
+        processes: Some(
+            procs
+                .iter()
+                .map(|&(pid, bytes)| GpuProcessInfo::lookup(pid, Some(bytes), memory_total))
+                .collect(),
+        ),
+        available: true,
+        ..Default::default()
+    }
+}
+
+/// Current (marked `*`) and highest level of a `pp_dpm_*` clock table, in MHz:
+/// "0: 500Mhz\n1: 1500Mhz *\n"
+fn dpm_clock(path: &Path) -> (Option<u32>, Option<u32>) {
+    let Some(table) = drm::read_str(path) else {
+        return (None, None);
+    };
+    let mhz = |line: &str| -> Option<u32> {
+        let value = line.split_whitespace().nth(1)?;
+        value.to_ascii_lowercase().strip_suffix("mhz")?.parse().ok()
+    };
+    let current = table.lines().find(|l| l.trim_end().ends_with('*')).and_then(mhz);
+    let max = table.lines().filter_map(mhz).max();
+    (current, max)
+}
+
+/// Fan duty cycle in % from the 0-255 PWM value
+fn fan_percent(hwmon: &Path) -> Option<u32> {
+    drm::read_u64(&hwmon.join("pwm1")).map(|pwm| (pwm * 100 / 255) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dpm_table() {
+        let path = std::env::temp_dir().join(format!("gpustat-pp_dpm_sclk-{}", std::process::id()));
+        std::fs::write(&path, "0: 500Mhz\n1: 1500Mhz *\n2: 2100Mhz\n").unwrap();
+        let busy = dpm_clock(&path);
+        std::fs::write(&path, "0: 96Mhz\n1: 456MHz\n").unwrap();
+        let unmarked = dpm_clock(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(busy, (Some(1500), Some(2100)));
+        assert_eq!(unmarked, (None, Some(456)));
+        assert_eq!(dpm_clock(&path), (None, None));
+    }
+}
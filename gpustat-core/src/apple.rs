@@ -0,0 +1,229 @@
+//! Apple Silicon GPUs through the IOKit registry (macOS)
+//!
+//! The AGX accelerator publishes a `PerformanceStatistics` dictionary with the
+//! device utilization and the unified memory the GPU has in use, and one user
+//! client per process holding a GPU context.
+
+use crate::backend::GpuBackend;
+use crate::GpuStatError;
+use crate::{host_name, GpuProcessInfo, GpuStat, GpuStatCollection, QueryOptions, MB};
+use chrono::Utc;
+use core_foundation_sys::base::{kCFAllocatorDefault, CFAllocatorRef, CFGetTypeID, CFRelease, CFTypeRef};
+use core_foundation_sys::dictionary::{CFDictionaryGetTypeID, CFDictionaryGetValue, CFDictionaryRef, CFMutableDictionaryRef};
+use core_foundation_sys::number::{kCFNumberSInt64Type, CFNumberGetTypeID, CFNumberGetValue, CFNumberRef};
+use core_foundation_sys::string::{
+    kCFStringEncodingUTF8, CFStringCreateWithCString, CFStringGetCString, CFStringGetTypeID, CFStringRef,
+};
+use std::ffi::{c_char, c_void, CStr, CString};
+use sysinfo::{MemoryRefreshKind, RefreshKind, System};
+
+type KernReturn = i32;
+type IoObject = u32;
+/// `kIOMainPortDefault`
+const MAIN_PORT_DEFAULT: u32 = 0;
+const KERN_SUCCESS: KernReturn = 0;
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IOServiceMatching(name: *const c_char) -> CFMutableDictionaryRef;
+    fn IOServiceGetMatchingServices(main_port: u32, matching: CFDictionaryRef, iterator: *mut IoObject) -> KernReturn;
+    fn IOIteratorNext(iterator: IoObject) -> IoObject;
+    fn IORegistryEntryCreateCFProperties(
+        entry: IoObject,
+        properties: *mut CFMutableDictionaryRef,
+        allocator: CFAllocatorRef,
+        options: u32,
+    ) -> KernReturn;
+    fn IORegistryEntryGetChildIterator(entry: IoObject, plane: *const c_char, iterator: *mut IoObject) -> KernReturn;
+    fn IORegistryEntryGetRegistryEntryID(entry: IoObject, id: *mut u64) -> KernReturn;
+    fn IOObjectRelease(object: IoObject) -> KernReturn;
+}
+
+/// An IOKit object released on drop
+struct Object(IoObject);
+
+impl Drop for Object {
+    fn drop(&mut self) {
+        unsafe { IOObjectRelease(self.0) };
+    }
+}
+
+impl Object {
+    fn iter(iterator: IoObject) -> impl Iterator<Item = Object> {
+        let iterator = Object(iterator);
+        std::iter::from_fn(move || match unsafe { IOIteratorNext(iterator.0) } {
+            0 => None,
+            obj => Some(Object(obj)),
+        })
+    }
+
+    fn children(&self) -> impl Iterator<Item = Object> {
+        let mut iterator = 0;
+        let plane = CString::new("IOService").unwrap();
+        let ok = unsafe { IORegistryEntryGetChildIterator(self.0, plane.as_ptr(), &mut iterator) } == KERN_SUCCESS;
+        ok.then(|| Object::iter(iterator)).into_iter().flatten()
+    }
+
+    fn registry_id(&self) -> Option<u64> {
+        let mut id = 0;
+        (unsafe { IORegistryEntryGetRegistryEntryID(self.0, &mut id) } == KERN_SUCCESS).then_some(id)
+    }
+
+    fn properties(&self) -> Option<Dict> {
+        let mut props: CFMutableDictionaryRef = std::ptr::null_mut();
+        let ret = unsafe { IORegistryEntryCreateCFProperties(self.0, &mut props, kCFAllocatorDefault, 0) };
+        (ret == KERN_SUCCESS && !props.is_null()).then(|| Dict(props as CFDictionaryRef, true))
+    }
+}
+
+/// A CFDictionary; released on drop when owned
+struct Dict(CFDictionaryRef, bool);
+
+impl Drop for Dict {
+    fn drop(&mut self) {
+        if self.1 {
+            unsafe { CFRelease(self.0 as CFTypeRef) };
+        }
+    }
+}
+
+impl Dict {
+    fn get(&self, key: &str) -> Option<CFTypeRef> {
+        let key = CString::new(key).ok()?;
+        unsafe {
+            let key = CFStringCreateWithCString(kCFAllocatorDefault, key.as_ptr(), kCFStringEncodingUTF8);
+            if key.is_null() {
+                return None;
+            }
+            let value = CFDictionaryGetValue(self.0, key as *const c_void);
+            CFRelease(key as CFTypeRef);
+            (!value.is_null()).then_some(value)
+        }
+    }
+
+    fn dict(&self, key: &str) -> Option<Dict> {
+        let value = self.get(key)?;
+        (unsafe { CFGetTypeID(value) } == unsafe { CFDictionaryGetTypeID() }).then(|| Dict(value as CFDictionaryRef, false))
+    }
+
+    fn number(&self, key: &str) -> Option<i64> {
+        let value = self.get(key)?;
+        if unsafe { CFGetTypeID(value) } != unsafe { CFNumberGetTypeID() } {
+            return None;
+        }
+        let mut n: i64 = 0;
+        let ok = unsafe { CFNumberGetValue(value as CFNumberRef, kCFNumberSInt64Type, &mut n as *mut i64 as *mut c_void) };
+        ok.then_some(n)
+    }
+
+    fn string(&self, key: &str) -> Option<String> {
+        let value = self.get(key)?;
+        if unsafe { CFGetTypeID(value) } != unsafe { CFStringGetTypeID() } {
+            return None;
+        }
+        let mut buf = [0 as c_char; 256];
+        let ok = unsafe { CFStringGetCString(value as CFStringRef, buf.as_mut_ptr(), buf.len() as _, kCFStringEncodingUTF8) };
+        (ok != 0).then(|| unsafe { CStr::from_ptr(buf.as_ptr()) }.to_string_lossy().into_owned())
+    }
+}
+
+/// Every IOAccelerator service (one per GPU)
+fn accelerators() -> Vec<Object> {
+    let name = CString::new("IOAccelerator").unwrap();
+    let mut iterator = 0;
+    unsafe {
+        // IOServiceGetMatchingServices consumes the matching dictionary
+        let matching = IOServiceMatching(name.as_ptr());
+        if matching.is_null() || IOServiceGetMatchingServices(MAIN_PORT_DEFAULT, matching, &mut iterator) != KERN_SUCCESS {
+            return Vec::new();
+        }
+    }
+    Object::iter(iterator).collect()
+}
+
+/// Stats of the integrated GPU of Apple Silicon Macs
+#[derive(Debug, Default)]
+pub struct AppleBackend;
+
+impl AppleBackend {
+    /// Whether IOKit lists a GPU with performance statistics
+    pub fn detect() -> bool {
+        accelerators().iter().any(|a| a.properties().is_some_and(|p| p.dict("PerformanceStatistics").is_some()))
+    }
+}
+
+impl GpuBackend for AppleBackend {
+    fn query(&self, gpu_ids: Option<&[u32]>, _query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        let accelerators: Vec<Object> = accelerators()
+            .into_iter()
+            .filter(|a| a.properties().is_some_and(|p| p.dict("PerformanceStatistics").is_some()))
+            .collect();
+        if accelerators.is_empty() {
+            return Err("no Apple GPU found".into());
+        }
+        // Unified memory: the GPU shares system RAM
+        let ram = System::new_with_specifics(RefreshKind::nothing().with_memory(MemoryRefreshKind::nothing().with_ram()))
+            .total_memory();
+        let gpus = accelerators
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| gpu_ids.is_none_or(|ids| ids.contains(&(*i as u32))))
+            .filter_map(|(i, a)| query_gpu(i as u32, a, ram))
+            .collect();
+        Ok(GpuStatCollection {
+            hostname: host_name(),
+            query_time: Utc::now(),
+            driver_version: None,
+            cuda_version: None,
+            nvml_version: None,
+            sample_period: None,
+            query_latency: None,
+            temperature_unit: Default::default(),
+            memory_unit: Default::default(),
+            gpus,
+            users: Vec::new(),
+            by_process: Vec::new(),
+        })
+    }
+}
+
+fn query_gpu(index: u32, accelerator: &Object, ram: u64) -> Option<GpuStat> {
+    let props = accelerator.properties()?;
+    let perf = props.dict("PerformanceStatistics")?;
+    let used = perf.number("In use system memory").map_or(0, |b| b.max(0) as u64);
+    let cores = props.number("gpu-core-count");
+    let model = props.string("model").unwrap_or_else(|| "Apple GPU".to_string());
+    let name = match cores {
+        Some(n) => format!("{} ({}-core)", model, n),
+        None => model,
+    };
+    Some(GpuStat {
+        index,
+        uuid: accelerator.registry_id().map(|id| format!("AGX-{:x}", id)).unwrap_or_default(),
+        name,
+        utilization: perf.number("Device Utilization %").map(|u| u.clamp(0, 100) as u32),
+        memory_used: used / MB,
+        memory_total: ram / MB,
+        memory_free: ram.saturating_sub(used) / MB,
+        memory_used_bytes: used,
+        memory_total_bytes: ram,
+        processes: Some(processes(accelerator)),
+        available: true,
+        ..Default::default()
+    })
+}
+
+/// Processes with a GPU user client; the registry has no per-process memory
+fn processes(accelerator: &Object) -> Vec<GpuProcessInfo> {
+    let mut pids: Vec<u32> = accelerator
+        .children()
+        .filter_map(|c| {
+            // e.g. "pid 1234, WindowServer"
+            let creator = c.properties()?.string("IOUserClientCreator")?;
+            creator.strip_prefix("pid ")?.split(',').next()?.trim().parse().ok()
+        })
+        .collect();
+    pids.sort_unstable();
+    pids.dedup();
+    pids.into_iter().map(|pid| GpuProcessInfo::lookup(pid, None, None)).collect()
+}
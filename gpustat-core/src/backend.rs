@@ -0,0 +1,187 @@
+//! Sources of GPU stats: NVML on real hardware, or a fixed snapshot for demos and tests
+
+use crate::{AmdBackend, GpuStatCollection, GpuStatError, IntelBackend, JetsonBackend, QueryOptions};
+use nvml_wrapper_sys::bindings::nvmlDevice_t;
+use nvml_wrapper::{Device, Nvml};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Anything that can produce a [`GpuStatCollection`]
+pub trait GpuBackend {
+    /// Query all GPUs, or only those in `gpu_ids`, with their processes
+    fn query(&self, gpu_ids: Option<&[u32]>, query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError>;
+}
+
+/// Live stats from the NVIDIA driver; NVML is initialized on the first query
+/// and kept, with its device handles, for the following ones
+#[derive(Debug, Default)]
+pub struct NvmlBackend {
+    session: Mutex<Option<Session>>,
+}
+
+#[derive(Debug)]
+struct Session {
+    nvml: Nvml,
+    /// Handle of each device by index, or None if it failed to open (retried
+    /// every query so the placeholder shows the current error)
+    devices: Vec<Option<nvmlDevice_t>>,
+}
+
+impl Session {
+    /// Re-open the devices when their number changed (hot-plug, GPU fallen off the bus)
+    fn refresh(&mut self) -> Result<(), GpuStatError> {
+        let count = self.nvml.device_count()?;
+        if count as usize != self.devices.len() {
+            // SAFETY: the raw handle is only turned back into a `Device` by `device`,
+            // borrowing the same `self.nvml` it came from
+            self.devices =
+                (0..count).map(|i| self.nvml.device_by_index(i).ok().map(|d| unsafe { d.handle() })).collect();
+        }
+        Ok(())
+    }
+
+    fn device(&self, index: u32) -> Result<Device<'_>, nvml_wrapper::error::NvmlError> {
+        match self.devices.get(index as usize).copied().flatten() {
+            // SAFETY: the handle came from `self.nvml`, which is still initialized (the
+            // whole session is dropped on re-init), and `refresh` rebuilds the list
+            // whenever the device count changes, so it refers to a device NVML enumerated
+            Some(handle) => Ok(unsafe { Device::new(handle, &self.nvml) }),
+            None => self.nvml.device_by_index(index),
+        }
+    }
+}
+
+impl GpuBackend for NvmlBackend {
+    fn query(&self, gpu_ids: Option<&[u32]>, query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        let mut guard = self.session.lock().unwrap_or_else(|e| e.into_inner());
+        let session = match guard.as_mut() {
+            Some(session) => session,
+            None => guard.insert(Session { nvml: Nvml::init().map_err(GpuStatError::Init)?, devices: Vec::new() }),
+        };
+        if let Err(e) = session.refresh() {
+            tracing::debug!(error = %e, "device enumeration failed, re-initializing NVML");
+            // e.g. the driver was reloaded: start over with a fresh NVML next time
+            *guard = None;
+            return Err(e);
+        }
+        let indices: Vec<u32> = match gpu_ids {
+            Some(ids) => ids.to_vec(),
+            None => (0..session.devices.len() as u32).collect(),
+        };
+        let devices = indices.iter().map(|&i| (i, session.device(i))).collect();
+        Ok(GpuStatCollection::from_devices(&session.nvml, devices, query))
+    }
+}
+
+/// Every vendor found on this machine, GPUs numbered across vendors in order
+pub struct AutoBackend {
+    backends: Vec<Box<dyn GpuBackend>>,
+}
+
+impl AutoBackend {
+    /// NVML always (its error explains a missing driver best), through DCGM when
+    /// built with it and nv-hostengine is running, then each other vendor whose
+    /// devices are present
+    pub fn detect() -> Self {
+        let mut backends: Vec<Box<dyn GpuBackend>> = vec![Box::new(NvmlBackend::default())];
+        #[cfg(feature = "dcgm")]
+        match crate::DcgmBackend::connect(None) {
+            Ok(dcgm) => backends[0] = Box::new(dcgm),
+            Err(e) => tracing::debug!(error = %e, "DCGM not available, using NVML"),
+        }
+        if AmdBackend::detect() {
+            backends.push(Box::new(AmdBackend));
+        }
+        if IntelBackend::detect() {
+            backends.push(Box::new(IntelBackend::default()));
+        }
+        if JetsonBackend::detect() {
+            backends.push(Box::new(JetsonBackend));
+        }
+        #[cfg(target_os = "macos")]
+        if crate::AppleBackend::detect() {
+            backends.push(Box::new(crate::AppleBackend));
+        }
+        AutoBackend { backends }
+    }
+}
+
+impl GpuBackend for AutoBackend {
+    fn query(&self, gpu_ids: Option<&[u32]>, query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        if let [backend] = &self.backends[..] {
+            return backend.query(gpu_ids, query);
+        }
+        // A vendor without devices or driver is skipped as long as another one works
+        let started = std::time::Instant::now();
+        let mut merged: Option<GpuStatCollection> = None;
+        let mut first_error = None;
+        for backend in &self.backends {
+            match backend.query(None, query) {
+                Ok(mut stats) => match &mut merged {
+                    None => merged = Some(stats),
+                    Some(m) => {
+                        let offset = m.gpus.len() as u32;
+                        for gpu in &mut stats.gpus {
+                            gpu.index += offset;
+                        }
+                        m.gpus.append(&mut stats.gpus);
+                    }
+                },
+                Err(e) => {
+                    tracing::debug!(error = %e, "backend query failed");
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        let Some(mut stats) = merged else {
+            return Err(first_error.unwrap_or_else(|| "no GPU found".into()));
+        };
+        if let Some(ids) = gpu_ids {
+            stats.gpus.retain(|g| ids.contains(&g.index));
+        }
+        stats.query_latency = Some(started.elapsed().as_secs_f64());
+        Ok(stats)
+    }
+}
+
+/// Deterministic backend replaying a snapshot, as written by `gpustat --json`
+#[derive(Debug, Clone)]
+pub struct MockBackend {
+    stats: GpuStatCollection,
+}
+
+/// Built-in snapshot used when no fixture file is given
+const DEFAULT_FIXTURE: &str = include_str!("../fixtures/mock.json");
+
+impl MockBackend {
+    pub fn new(stats: GpuStatCollection) -> Self {
+        MockBackend { stats }
+    }
+
+    /// Parse a JSON snapshot
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json).map(Self::new)
+    }
+
+    /// Load a JSON snapshot from `path`
+    pub fn load(path: &Path) -> Result<Self, GpuStatError> {
+        Ok(Self::from_json(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+impl Default for MockBackend {
+    /// Two busy GPUs shared by a couple of users
+    fn default() -> Self {
+        Self::from_json(DEFAULT_FIXTURE).expect("built-in mock fixture is valid")
+    }
+}
+
+impl GpuBackend for MockBackend {
+    fn query(&self, gpu_ids: Option<&[u32]>, _query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        let mut stats = self.stats.clone();
+        if let Some(ids) = gpu_ids {
+            stats.gpus.retain(|g| ids.contains(&g.index));
+        }
+        Ok(stats)
+    }
+}
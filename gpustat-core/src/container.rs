@@ -0,0 +1,169 @@
+//! Container / Kubernetes pod detection from a process's cgroup
+//!
+//! The cgroup path carries the runtime and container ID (and the pod UID under
+//! Kubernetes). Pod name and namespace are not in it, so they are taken from
+//! the process environment (`HOSTNAME`, `POD_NAMESPACE`) or the mounted service
+//! account, which needs the same user or root to read.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Container {
+    /// "docker", "containerd", "crio" or "podman"
+    pub runtime: String,
+    /// Short (12 character) container ID
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pod: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pod_uid: Option<String>,
+}
+
+impl Container {
+    /// Short label for the process list: pod name, or the container ID
+    pub fn label(&self) -> String {
+        match &self.pod {
+            Some(pod) => format!("pod:{}", pod),
+            None => format!("ctr:{}", self.id),
+        }
+    }
+}
+
+const SHORT_ID: usize = 12;
+
+/// Runtime and full container ID from one cgroup path component, e.g.
+/// "docker-<id>.scope", "cri-containerd-<id>.scope", "crio-<id>.scope" or a bare "<id>"
+fn parse_component(part: &str, parent: &str) -> Option<(&'static str, String)> {
+    let part = part.strip_suffix(".scope").unwrap_or(part);
+    let (runtime, id) = if let Some(id) = part.strip_prefix("docker-") {
+        ("docker", id)
+    } else if let Some(id) = part.strip_prefix("cri-containerd-") {
+        ("containerd", id)
+    } else if let Some(id) = part.strip_prefix("crio-") {
+        ("crio", id)
+    } else if let Some(id) = part.strip_prefix("libpod-") {
+        ("podman", id)
+    } else if parent == "docker" {
+        ("docker", part)
+    } else if parent.starts_with("pod") {
+        // cgroupfs driver: /kubepods/<qos>/pod<uid>/<id>, runtime unknown
+        ("containerd", part)
+    } else {
+        return None;
+    };
+    (id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit())).then(|| (runtime, id.to_string()))
+}
+
+/// Pod UID from "kubepods-burstable-pod<uid>.slice" (systemd, '_' for '-') or "pod<uid>" (cgroupfs)
+fn parse_pod_uid(part: &str) -> Option<String> {
+    let part = part.strip_suffix(".slice").unwrap_or(part);
+    let uid = part.rsplit_once("-pod").map(|(_, u)| u).or_else(|| part.strip_prefix("pod"))?;
+    (uid.len() >= 32).then(|| uid.replace('_', "-"))
+}
+
+/// Container of the process with `cgroup` as its /proc/<pid>/cgroup content
+fn from_cgroup(cgroup: &str) -> Option<Container> {
+    for line in cgroup.lines() {
+        // "hierarchy-ID:controllers:path"; cgroup v2 has a single "0::path" line
+        let Some(path) = line.splitn(3, ':').nth(2) else { continue };
+        let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+        for (i, part) in parts.iter().enumerate().rev() {
+            let parent = if i > 0 { parts[i - 1] } else { "" };
+            if let Some((runtime, id)) = parse_component(part, parent) {
+                let pod_uid = if path.contains("kubepods") {
+                    parts[..i].iter().rev().find_map(|p| parse_pod_uid(p))
+                } else {
+                    None
+                };
+                return Some(Container {
+                    runtime: runtime.to_string(),
+                    id: id[..SHORT_ID].to_string(),
+                    pod: None,
+                    namespace: None,
+                    pod_uid,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Detect the container `pid` runs in, if any
+#[cfg(target_os = "linux")]
+pub fn detect(pid: u32) -> Option<Container> {
+    let cgroup = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    let mut container = from_cgroup(&cgroup)?;
+    if container.pod_uid.is_some() {
+        let env = std::fs::read(format!("/proc/{}/environ", pid)).unwrap_or_default();
+        let var = |name: &str| {
+            env.split(|&b| b == 0)
+                .filter_map(|kv| std::str::from_utf8(kv).ok())
+                .find_map(|kv| kv.strip_prefix(name)?.strip_prefix('=').map(str::to_string))
+        };
+        // Kubernetes sets the pod hostname to the pod name
+        container.pod = var("HOSTNAME");
+        container.namespace = var("POD_NAMESPACE").or_else(|| {
+            std::fs::read_to_string(format!(
+                "/proc/{}/root/var/run/secrets/kubernetes.io/serviceaccount/namespace",
+                pid
+            ))
+            .ok()
+            .map(|ns| ns.trim().to_string())
+        });
+    }
+    Some(container)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn detect(_pid: u32) -> Option<Container> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "4f1c2d9e8b7a6f5e4d3c2b1a0f9e8d7c6b5a4f3e2d1c0b9a8f7e6d5c4b3a2f1e";
+
+    #[test]
+    fn docker_v2() {
+        let c = from_cgroup(&format!("0::/system.slice/docker-{}.scope\n", ID)).unwrap();
+        assert_eq!((c.runtime.as_str(), c.id.as_str()), ("docker", &ID[..SHORT_ID]));
+        assert_eq!(c.pod_uid, None);
+    }
+
+    #[test]
+    fn docker_cgroupfs_v1() {
+        let cgroup = format!("12:pids:/docker/{}\n11:memory:/docker/{}\n", ID, ID);
+        let c = from_cgroup(&cgroup).unwrap();
+        assert_eq!((c.runtime.as_str(), c.id.as_str()), ("docker", &ID[..SHORT_ID]));
+    }
+
+    #[test]
+    fn kubernetes_systemd() {
+        let cgroup = format!(
+            "0::/kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod1a2b3c4d_5e6f_7a8b_9c0d_1e2f3a4b5c6d.slice/cri-containerd-{}.scope\n",
+            ID
+        );
+        let c = from_cgroup(&cgroup).unwrap();
+        assert_eq!(c.runtime, "containerd");
+        assert_eq!(c.pod_uid.as_deref(), Some("1a2b3c4d-5e6f-7a8b-9c0d-1e2f3a4b5c6d"));
+    }
+
+    #[test]
+    fn kubernetes_cgroupfs() {
+        let cgroup = format!("0::/kubepods/besteffort/pod1a2b3c4d-5e6f-7a8b-9c0d-1e2f3a4b5c6d/{}\n", ID);
+        let c = from_cgroup(&cgroup).unwrap();
+        assert_eq!(c.runtime, "containerd");
+        assert_eq!(c.pod_uid.as_deref(), Some("1a2b3c4d-5e6f-7a8b-9c0d-1e2f3a4b5c6d"));
+    }
+
+    #[test]
+    fn not_a_container() {
+        assert!(from_cgroup("0::/user.slice/user-1000.slice/session-3.scope\n").is_none());
+        assert!(from_cgroup("0::/system.slice/docker-abc.scope\n").is_none());
+        assert!(from_cgroup("").is_none());
+    }
+}
@@ -0,0 +1,161 @@
+//! DCGM profiling metrics from a running nv-hostengine (`dcgm` feature)
+//!
+//! libdcgm is loaded at runtime, so the binary still starts on machines without
+//! DCGM. Everything else comes from NVML as usual; DCGM only adds the
+//! profiling counters NVML doesn't expose.
+
+use crate::backend::GpuBackend;
+use crate::GpuStatError;
+use crate::{GpuStatCollection, NvmlBackend, ProfilingMetrics, QueryOptions};
+use libloading::Library;
+use std::ffi::{c_char, CString};
+
+type DcgmReturn = i32;
+type Handle = usize;
+
+const DCGM_ST_OK: DcgmReturn = 0;
+const DCGM_GROUP_ALL_GPUS: usize = 0x7fff_ffff;
+const DCGM_FT_DOUBLE: u16 = b'd' as u16;
+const LIBRARY_NAMES: [&str; 3] = ["libdcgm.so.4", "libdcgm.so.3", "libdcgm.so"];
+
+/// DCGM_FI_PROF_SM_ACTIVE, _SM_OCCUPANCY, _PIPE_TENSOR_ACTIVE, _DRAM_ACTIVE
+const FIELDS: [u16; 4] = [1002, 1003, 1004, 1005];
+/// Sample every second and keep a minute, enough for any refresh interval
+const UPDATE_FREQ_US: i64 = 1_000_000;
+const MAX_KEEP_AGE_S: f64 = 60.0;
+
+const DCGM_MAX_BLOB_LENGTH: usize = 4096;
+
+/// `dcgmFieldValue_v1`
+#[repr(C)]
+struct FieldValue {
+    version: u32,
+    field_id: u16,
+    field_type: u16,
+    status: i32,
+    ts: i64,
+    value: [u8; DCGM_MAX_BLOB_LENGTH],
+}
+
+impl FieldValue {
+    const VERSION: u32 = std::mem::size_of::<FieldValue>() as u32 | (1 << 24);
+
+    fn new() -> Self {
+        FieldValue { version: Self::VERSION, field_id: 0, field_type: 0, status: 0, ts: 0, value: [0; DCGM_MAX_BLOB_LENGTH] }
+    }
+
+    /// The value as a fraction turned into %, unless DCGM has none yet (or it's blank)
+    fn percent(&self) -> Option<f64> {
+        if self.status != DCGM_ST_OK || self.field_type != DCGM_FT_DOUBLE {
+            return None;
+        }
+        let v = f64::from_ne_bytes(self.value[..8].try_into().ok()?);
+        // Blank values are huge sentinels (DCGM_FP64_BLANK = 140737488355328.0)
+        (0.0..=1.0).contains(&v).then_some(v * 100.0)
+    }
+}
+
+/// NVML stats plus DCGM profiling counters, over a hostengine connection
+pub struct DcgmBackend {
+    nvml: NvmlBackend,
+    lib: Library,
+    handle: Handle,
+    field_group: usize,
+}
+
+impl DcgmBackend {
+    /// Connect to nv-hostengine at `host` (default: localhost) and start watching
+    /// the profiling fields on all GPUs
+    pub fn connect(host: Option<&str>) -> Result<Self, GpuStatError> {
+        let lib = LIBRARY_NAMES
+            .iter()
+            .find_map(|name| unsafe { Library::new(name) }.ok())
+            .ok_or("libdcgm not found")?;
+        let host = CString::new(host.unwrap_or("127.0.0.1")).map_err(|_| "invalid DCGM host name")?;
+        // Field group names are global in the hostengine
+        let group_name = CString::new(format!("gpustat-{}", std::process::id())).map_err(|e| e.to_string())?;
+        unsafe {
+            let init = lib.get::<unsafe extern "C" fn() -> DcgmReturn>(b"dcgmInit\0").map_err(symbol_error)?;
+            let connect = lib.get::<unsafe extern "C" fn(*const c_char, *mut Handle) -> DcgmReturn>(b"dcgmConnect\0").map_err(symbol_error)?;
+            let group_create = lib.get::<unsafe extern "C" fn(Handle, i32, *mut u16, *const c_char, *mut usize) -> DcgmReturn>(
+                b"dcgmFieldGroupCreate\0",
+            )
+            .map_err(symbol_error)?;
+            // Plain fn pointers, so `lib` can move into the backend below
+            let watch =
+                *lib.get::<unsafe extern "C" fn(Handle, usize, usize, i64, f64, i32) -> DcgmReturn>(b"dcgmWatchFields\0").map_err(symbol_error)?;
+
+            check(init(), "dcgmInit")?;
+            let mut handle = 0;
+            check(connect(host.as_ptr(), &mut handle), "dcgmConnect")?;
+            let mut fields = FIELDS;
+            let mut field_group = 0;
+            check(
+                group_create(handle, fields.len() as i32, fields.as_mut_ptr(), group_name.as_ptr(), &mut field_group),
+                "dcgmFieldGroupCreate",
+            )?;
+            let backend = DcgmBackend { nvml: NvmlBackend::default(), lib, handle, field_group };
+            check(watch(handle, DCGM_GROUP_ALL_GPUS, field_group, UPDATE_FREQ_US, MAX_KEEP_AGE_S, 0), "dcgmWatchFields")?;
+            Ok(backend)
+        }
+    }
+
+    /// Latest profiling values of GPU `index` (DCGM GPU IDs follow NVML indices)
+    fn profiling(&self, index: u32) -> Option<ProfilingMetrics> {
+        let mut fields = FIELDS;
+        let mut values: Vec<FieldValue> = FIELDS.iter().map(|_| FieldValue::new()).collect();
+        unsafe {
+            let latest = self
+                .lib
+                .get::<unsafe extern "C" fn(Handle, i32, *mut u16, u32, *mut FieldValue) -> DcgmReturn>(
+                    b"dcgmGetLatestValuesForFields\0",
+                )
+                .ok()?;
+            let ret = latest(self.handle, index as i32, fields.as_mut_ptr(), fields.len() as u32, values.as_mut_ptr());
+            if ret != DCGM_ST_OK {
+                return None;
+            }
+        }
+        let [sm_active, sm_occupancy, tensor_active, dram_active] = [0, 1, 2, 3].map(|i| values[i].percent());
+        let metrics = ProfilingMetrics { sm_active, sm_occupancy, tensor_active, dram_active };
+        [sm_active, sm_occupancy, tensor_active, dram_active].iter().any(Option::is_some).then_some(metrics)
+    }
+}
+
+fn symbol_error(e: libloading::Error) -> GpuStatError {
+    GpuStatError::Backend(format!("libdcgm: {}", e))
+}
+
+fn check(ret: DcgmReturn, call: &str) -> Result<(), GpuStatError> {
+    if ret == DCGM_ST_OK {
+        Ok(())
+    } else {
+        Err(format!("{} failed (DCGM error {})", call, ret).into())
+    }
+}
+
+impl Drop for DcgmBackend {
+    fn drop(&mut self) {
+        unsafe {
+            if let Ok(destroy) = self.lib.get::<unsafe extern "C" fn(Handle, usize) -> DcgmReturn>(b"dcgmFieldGroupDestroy\0") {
+                destroy(self.handle, self.field_group);
+            }
+            if let Ok(disconnect) = self.lib.get::<unsafe extern "C" fn(Handle) -> DcgmReturn>(b"dcgmDisconnect\0") {
+                disconnect(self.handle);
+            }
+            if let Ok(shutdown) = self.lib.get::<unsafe extern "C" fn() -> DcgmReturn>(b"dcgmShutdown\0") {
+                shutdown();
+            }
+        }
+    }
+}
+
+impl GpuBackend for DcgmBackend {
+    fn query(&self, gpu_ids: Option<&[u32]>, query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        let mut stats = self.nvml.query(gpu_ids, query)?;
+        for gpu in stats.gpus.iter_mut().filter(|g| g.available) {
+            gpu.profiling = self.profiling(gpu.index);
+        }
+        Ok(stats)
+    }
+}
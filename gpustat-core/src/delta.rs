@@ -0,0 +1,133 @@
+//! Changes between two snapshots of the same host
+//!
+//! GPUs are matched by UUID (falling back to the index for backends without
+//! one), processes by PID. Rates are per second of the sample period.
+
+use crate::{GpuStat, GpuStatCollection};
+use serde::{Deserialize, Serialize};
+
+/// What changed between a previous snapshot and this one
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GpuStatDelta {
+    /// Seconds between the two snapshots
+    pub period: f64,
+    /// GPUs present in both snapshots, in the order of the newer one
+    pub gpus: Vec<GpuDelta>,
+    /// Indices of GPUs that appeared since the previous snapshot
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added_gpus: Vec<u32>,
+    /// Indices (in the previous snapshot) of GPUs that went away
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_gpus: Vec<u32>,
+}
+
+/// Change of one GPU over the period
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GpuDelta {
+    pub index: u32,
+    pub uuid: String,
+    /// Change in used memory (bytes; negative when freed)
+    pub memory_growth: i64,
+    /// `memory_growth` per second
+    pub memory_rate: Option<f64>,
+    /// Change in utilization (percentage points)
+    pub utilization_change: Option<i64>,
+    /// Energy consumed over the period (J), from the energy counter
+    pub energy_used: Option<f64>,
+    /// % of the period clocks were held back by the power / thermal limit
+    pub violation_power_pct: Option<f64>,
+    pub violation_thermal_pct: Option<f64>,
+    /// PCIe throughput at the newer sample (bytes/s)
+    pub pcie_tx_rate: Option<u64>,
+    pub pcie_rx_rate: Option<u64>,
+    /// PIDs that started using the GPU
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub new_processes: Vec<u32>,
+    /// PIDs that no longer use the GPU
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_processes: Vec<u32>,
+}
+
+impl GpuStatCollection {
+    /// Changes since `prev`, an earlier snapshot of the same host
+    pub fn diff(&self, prev: &GpuStatCollection) -> GpuStatDelta {
+        let period = self.period_since(prev);
+        let mut delta = GpuStatDelta { period, ..Default::default() };
+        for gpu in &self.gpus {
+            match prev.gpus.iter().find(|p| gpu.same_gpu(p)) {
+                Some(before) => delta.gpus.push(gpu.diff(before, period)),
+                None => delta.added_gpus.push(gpu.index),
+            }
+        }
+        delta.removed_gpus =
+            prev.gpus.iter().filter(|p| !self.gpus.iter().any(|g| g.same_gpu(p))).map(|p| p.index).collect();
+        delta
+    }
+
+    /// Seconds since `prev`: the sample period if known, else the query time difference
+    pub(crate) fn period_since(&self, prev: &GpuStatCollection) -> f64 {
+        self.sample_period
+            .unwrap_or_else(|| (self.query_time - prev.query_time).num_milliseconds() as f64 / 1000.0)
+    }
+}
+
+/// Growth of a counter that only resets with the driver; a decrease is no data
+fn counter_delta(before: Option<u64>, after: Option<u64>) -> Option<u64> {
+    match (before, after) {
+        (Some(b), Some(a)) if a >= b => Some(a - b),
+        _ => None,
+    }
+}
+
+impl GpuStat {
+    /// Whether `other` is this GPU in another snapshot
+    pub(crate) fn same_gpu(&self, other: &GpuStat) -> bool {
+        if self.uuid.is_empty() {
+            self.index == other.index
+        } else {
+            self.uuid == other.uuid
+        }
+    }
+
+    /// Changes since `prev` (the same GPU, `period` seconds earlier)
+    pub fn diff(&self, prev: &GpuStat, period: f64) -> GpuDelta {
+        let memory_growth = self.memory_used_bytes as i64 - prev.memory_used_bytes as i64;
+        let pids = |g: &GpuStat| -> Vec<u32> { g.processes.iter().flatten().map(|p| p.pid).collect() };
+        let (now, before) = (pids(self), pids(prev));
+        // Violation counters are in ns
+        let violation = |b, a| {
+            let ns = counter_delta(b, a)?;
+            (period > 0.0).then(|| (ns as f64 / (period * 1e9) * 100.0).min(100.0))
+        };
+        GpuDelta {
+            index: self.index,
+            uuid: self.uuid.clone(),
+            memory_growth,
+            memory_rate: (period > 0.0).then(|| memory_growth as f64 / period),
+            utilization_change: self.utilization.zip(prev.utilization).map(|(a, b)| a as i64 - b as i64),
+            energy_used: counter_delta(prev.energy_total, self.energy_total).map(|mj| mj as f64 / 1000.0),
+            violation_power_pct: violation(prev.violation_power, self.violation_power),
+            violation_thermal_pct: violation(prev.violation_thermal, self.violation_thermal),
+            // NVML reports PCIe throughput as a rate already (KB/s)
+            pcie_tx_rate: self.pcie_tx.map(|kb| kb as u64 * 1024),
+            pcie_rx_rate: self.pcie_rx.map(|kb| kb as u64 * 1024),
+            new_processes: now.iter().filter(|p| !before.contains(p)).copied().collect(),
+            removed_processes: before.iter().filter(|p| !now.contains(p)).copied().collect(),
+        }
+    }
+}
+
+impl GpuStatDelta {
+    /// Whether the GPU set, any process list, utilization by at least
+    /// `utilization` points or memory by at least `memory` bytes changed
+    pub fn is_active(&self, utilization: u32, memory: u64) -> bool {
+        !self.added_gpus.is_empty()
+            || !self.removed_gpus.is_empty()
+            || self.gpus.iter().any(|g| {
+                g.utilization_change.unwrap_or(0).unsigned_abs() >= utilization as u64
+                    || g.memory_growth.unsigned_abs() >= memory
+                    || !g.new_processes.is_empty()
+                    || !g.removed_processes.is_empty()
+            })
+    }
+}
@@ -0,0 +1,221 @@
+//! Linux DRM sysfs and fdinfo helpers shared by the non-NVIDIA backends
+//!
+//! Cards are found under /sys/class/drm/cardN/device, sensors under its hwmon
+//! directory. Per-process memory and engine time come from the DRM client stats
+//! every driver prints into /proc/<pid>/fdinfo (`drm-driver`, `drm-pdev`,
+//! `drm-memory-*`, `drm-engine-*`).
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A DRM card of one vendor
+pub(crate) struct Card {
+    /// The PCI device directory, e.g. /sys/class/drm/card0/device
+    pub device: PathBuf,
+    /// PCI address, e.g. "0000:03:00.0"
+    pub pdev: String,
+    /// Kernel driver bound to the card, e.g. "amdgpu", "i915" or "xe"
+    pub driver: String,
+}
+
+/// Cards whose PCI vendor ID is `vendor` (e.g. "0x1002"), in card order
+pub(crate) fn cards(vendor: &str) -> Vec<Card> {
+    let Ok(entries) = fs::read_dir("/sys/class/drm") else {
+        return Vec::new();
+    };
+    let mut cards: Vec<(u32, Card)> = entries
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            // cardN only; connectors are cardN-DP-1 etc.
+            let n: u32 = name.strip_prefix("card")?.parse().ok()?;
+            let device = e.path().join("device");
+            if read_str(&device.join("vendor"))? != vendor {
+                return None;
+            }
+            let pdev = fs::canonicalize(&device).ok()?.file_name()?.to_string_lossy().to_string();
+            let driver = fs::read_link(device.join("driver")).ok()?.file_name()?.to_string_lossy().to_string();
+            Some((n, Card { device, pdev, driver }))
+        })
+        .collect();
+    cards.sort_by_key(|(n, _)| *n);
+    cards.into_iter().map(|(_, c)| c).collect()
+}
+
+pub(crate) fn read_str(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+pub(crate) fn read_u64(path: &Path) -> Option<u64> {
+    read_str(path)?.parse().ok()
+}
+
+/// The card's hwmon directory (temperatures, power, fans)
+pub(crate) fn hwmon(device: &Path) -> Option<PathBuf> {
+    fs::read_dir(device.join("hwmon")).ok()?.flatten().map(|e| e.path()).next()
+}
+
+/// hwmon temperature (°C) of the sensor labelled `label`, e.g. "edge" or "mem"
+pub(crate) fn temperature(hwmon: &Path, label: &str) -> Option<u32> {
+    (1..=8).find_map(|i| {
+        if read_str(&hwmon.join(format!("temp{}_label", i)))? != label {
+            return None;
+        }
+        read_u64(&hwmon.join(format!("temp{}_input", i))).map(|m| (m / 1000) as u32)
+    })
+}
+
+/// First hwmon temperature (°C), for drivers that don't label their sensors
+pub(crate) fn first_temperature(hwmon: &Path) -> Option<u32> {
+    (1..=8).find_map(|i| read_u64(&hwmon.join(format!("temp{}_input", i))).map(|m| (m / 1000) as u32))
+}
+
+/// hwmon power value in W from a µW file, e.g. "power1_average"
+pub(crate) fn power(hwmon: &Path, file: &str) -> Option<u32> {
+    read_u64(&hwmon.join(file)).map(|uw| (uw / 1_000_000) as u32)
+}
+
+/// Current and maximum PCIe (generation, width)
+pub(crate) fn pcie_link(device: &Path) -> (Option<u32>, Option<u32>, Option<u32>, Option<u32>) {
+    // e.g. "16.0 GT/s PCIe"
+    let gen = |file: &str| {
+        let speed: f64 = read_str(&device.join(file))?.split_whitespace().next()?.parse().ok()?;
+        [2.5, 5.0, 8.0, 16.0, 32.0, 64.0]
+            .iter()
+            .position(|&s| (speed - s).abs() < 0.1)
+            .map(|i| i as u32 + 1)
+    };
+    let width = |file: &str| read_u64(&device.join(file)).map(|w| w as u32);
+    (
+        gen("current_link_speed"),
+        gen("max_link_speed"),
+        width("current_link_width"),
+        width("max_link_width"),
+    )
+}
+
+/// An open DRM file of a process, from /proc/<pid>/fdinfo
+pub(crate) struct Client {
+    pub pid: u32,
+    /// PCI address of the card, matching [`Card::pdev`]
+    pub pdev: String,
+    fields: HashMap<String, String>,
+}
+
+impl Client {
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).map(String::as_str)
+    }
+
+    /// Sum of size fields, e.g. "drm-memory-vram: 1234 KiB", in bytes
+    pub fn bytes(&self, keys: &[&str]) -> u64 {
+        keys.iter().filter_map(|k| self.field(k)).filter_map(parse_size).sum()
+    }
+
+    /// Number of engines of `class` (for busy time summed over all of them)
+    pub fn capacity(&self, class: &str) -> u64 {
+        self.field(&format!("drm-engine-capacity-{}", class)).and_then(|c| c.parse().ok()).unwrap_or(1)
+    }
+
+    /// Busy time of each engine class in ns, e.g. "drm-engine-render: 1234 ns"
+    pub fn engine_ns(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.fields.iter().filter_map(|(k, v)| {
+            let class = k.strip_prefix("drm-engine-")?;
+            if class.starts_with("capacity-") {
+                return None;
+            }
+            Some((class, v.strip_suffix("ns")?.trim().parse().ok()?))
+        })
+    }
+
+    /// Busy and elapsed GPU cycles of each engine class, for drivers (xe) that
+    /// count cycles instead of time: "drm-cycles-rcs" / "drm-total-cycles-rcs"
+    pub fn engine_cycles(&self) -> impl Iterator<Item = (&str, u64, u64)> {
+        self.fields.iter().filter_map(|(k, v)| {
+            let class = k.strip_prefix("drm-cycles-")?;
+            let total = self.field(&format!("drm-total-cycles-{}", class))?.parse().ok()?;
+            Some((class, v.parse().ok()?, total))
+        })
+    }
+}
+
+/// Every DRM client of `driver` (e.g. "amdgpu", "i915", "xe") on this machine;
+/// a client shared by several fds of a process is listed once
+pub(crate) fn clients(driver: &str) -> Vec<Client> {
+    let mut clients = Vec::new();
+    let Ok(procs) = fs::read_dir("/proc") else {
+        return clients;
+    };
+    for proc_entry in procs.flatten() {
+        let Some(pid) = proc_entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) else {
+            continue;
+        };
+        let Ok(fds) = fs::read_dir(proc_entry.path().join("fdinfo")) else {
+            continue;
+        };
+        let mut seen = HashSet::new();
+        for fd in fds.flatten() {
+            let Ok(info) = fs::read_to_string(fd.path()) else {
+                continue;
+            };
+            let fields: HashMap<String, String> = info
+                .lines()
+                .filter_map(|l| l.split_once(':'))
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .collect();
+            if fields.get("drm-driver").map(String::as_str) != Some(driver) {
+                continue;
+            }
+            let (Some(pdev), Some(id)) = (fields.get("drm-pdev"), fields.get("drm-client-id")) else {
+                continue;
+            };
+            if !seen.insert((pdev.clone(), id.clone())) {
+                continue;
+            }
+            clients.push(Client { pid, pdev: pdev.clone(), fields });
+        }
+    }
+    clients
+}
+
+/// Device memory (bytes) each PID holds on the card at `pdev`, by PID
+pub(crate) fn memory_by_pid(clients: &[Client], pdev: &str, keys: &[&str]) -> Vec<(u32, u64)> {
+    let mut pids: HashMap<u32, u64> = HashMap::new();
+    for c in clients.iter().filter(|c| c.pdev == pdev) {
+        *pids.entry(c.pid).or_default() += c.bytes(keys);
+    }
+    let mut pids: Vec<(u32, u64)> = pids.into_iter().collect();
+    pids.sort_unstable();
+    pids
+}
+
+/// fdinfo size such as "1234 KiB" (bare numbers are bytes)
+fn parse_size(s: &str) -> Option<u64> {
+    let mut parts = s.split_whitespace();
+    let n: u64 = parts.next()?.parse().ok()?;
+    let scale = match parts.next() {
+        None => 1,
+        Some("KiB") => 1 << 10,
+        Some("MiB") => 1 << 20,
+        Some("GiB") => 1 << 30,
+        Some(_) => return None,
+    };
+    Some(n * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fdinfo_sizes() {
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("12 KiB"), Some(12 << 10));
+        assert_eq!(parse_size("3 MiB"), Some(3 << 20));
+        assert_eq!(parse_size("2 GiB"), Some(2 << 30));
+        assert_eq!(parse_size("2 TiB"), None);
+        assert_eq!(parse_size("KiB"), None);
+        assert_eq!(parse_size(""), None);
+    }
+}
@@ -0,0 +1,157 @@
+//! What can go wrong in a query, from a missing driver down to one GPU
+//!
+//! A query as a whole fails with a [`GpuStatError`]; a GPU that can't be read
+//! is still listed, unavailable, with a [`DeviceError`] saying why.
+
+use nvml_wrapper::error::NvmlError;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum GpuStatError {
+    /// NVML could not be loaded or initialized (no driver, library mismatch, ...)
+    Init(NvmlError),
+    /// A driver-wide NVML call failed
+    Nvml(NvmlError),
+    /// Querying GPU `index` failed
+    Device { index: u32, source: NvmlError },
+    /// A non-NVML backend has no GPU, or its interface failed
+    Backend(String),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for GpuStatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GpuStatError::Init(e) => write!(f, "NVML initialization failed: {}", e),
+            GpuStatError::Nvml(e) => write!(f, "{}", e),
+            GpuStatError::Device { index, source } => write!(f, "GPU {}: {}", index, source),
+            GpuStatError::Backend(msg) => write!(f, "{}", msg),
+            GpuStatError::Io(e) => write!(f, "{}", e),
+            GpuStatError::Json(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for GpuStatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GpuStatError::Init(e) | GpuStatError::Nvml(e) | GpuStatError::Device { source: e, .. } => Some(e),
+            GpuStatError::Io(e) => Some(e),
+            GpuStatError::Json(e) => Some(e),
+            GpuStatError::Backend(_) => None,
+        }
+    }
+}
+
+impl From<NvmlError> for GpuStatError {
+    fn from(e: NvmlError) -> Self {
+        GpuStatError::Nvml(e)
+    }
+}
+
+impl From<std::io::Error> for GpuStatError {
+    fn from(e: std::io::Error) -> Self {
+        GpuStatError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for GpuStatError {
+    fn from(e: serde_json::Error) -> Self {
+        GpuStatError::Json(e)
+    }
+}
+
+impl From<String> for GpuStatError {
+    fn from(msg: String) -> Self {
+        GpuStatError::Backend(msg)
+    }
+}
+
+impl From<&str> for GpuStatError {
+    fn from(msg: &str) -> Self {
+        GpuStatError::Backend(msg.to_string())
+    }
+}
+
+/// Why a GPU is unavailable, as reported in `--json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceError {
+    pub kind: DeviceErrorKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceErrorKind {
+    /// The GPU fell off the bus or stopped responding
+    GpuLost,
+    /// The GPU needs a reset before it can be used again
+    ResetRequired,
+    NoPermission,
+    NotSupported,
+    Timeout,
+    /// Any other driver error
+    Driver,
+}
+
+impl From<&GpuStatError> for DeviceError {
+    fn from(e: &GpuStatError) -> Self {
+        let kind = match e {
+            GpuStatError::Init(source) | GpuStatError::Nvml(source) | GpuStatError::Device { source, .. } => {
+                match source {
+                    NvmlError::GpuLost => DeviceErrorKind::GpuLost,
+                    NvmlError::ResetRequired => DeviceErrorKind::ResetRequired,
+                    NvmlError::NoPermission => DeviceErrorKind::NoPermission,
+                    NvmlError::NotSupported => DeviceErrorKind::NotSupported,
+                    NvmlError::Timeout => DeviceErrorKind::Timeout,
+                    _ => DeviceErrorKind::Driver,
+                }
+            }
+            GpuStatError::Backend(_) | GpuStatError::Io(_) | GpuStatError::Json(_) => DeviceErrorKind::Driver,
+        };
+        // The GPU is known from context; keep just the cause
+        let message = match e {
+            GpuStatError::Device { source, .. } => source.to_string(),
+            e => e.to_string(),
+        };
+        DeviceError { kind, message }
+    }
+}
+
+thread_local! {
+    /// NVML calls that came back NotSupported on this thread, see [`collect_unsupported`]
+    static UNSUPPORTED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run `f` (one GPU's query), returning its result and the calls in it that
+/// the GPU or driver doesn't support
+pub(crate) fn collect_unsupported<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+    UNSUPPORTED.with_borrow_mut(Vec::clear);
+    let result = f();
+    (result, UNSUPPORTED.take())
+}
+
+/// `.ok()` for optional NVML calls, logging why a value is missing (`--debug`)
+/// and noting calls that aren't supported, to tell those apart from failures
+pub(crate) trait Traced<T> {
+    fn traced(self, call: &str) -> Option<T>;
+}
+
+impl<T> Traced<T> for Result<T, NvmlError> {
+    fn traced(self, call: &str) -> Option<T> {
+        self.map_err(|e| {
+            tracing::debug!(call, error = %e, "NVML call failed");
+            if matches!(e, NvmlError::NotSupported) {
+                UNSUPPORTED.with_borrow_mut(|calls| {
+                    if !calls.iter().any(|c| c == call) {
+                        calls.push(call.to_string());
+                    }
+                });
+            }
+        })
+        .ok()
+    }
+}
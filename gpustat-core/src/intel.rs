@@ -0,0 +1,201 @@
+//! Intel GPUs (Arc, Flex, Data Center GPU Max) through the i915 / xe DRM drivers
+//!
+//! Neither driver reports a utilization or power reading directly: both come from
+//! counters (DRM client engine time, hwmon energy) differenced against the
+//! previous query, so they are only filled from the second sample on.
+
+use crate::backend::GpuBackend;
+use crate::GpuStatError;
+use crate::drm::{self, Card, Client};
+use crate::{host_name, GpuProcessInfo, GpuStat, GpuStatCollection, QueryOptions, MB};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+const INTEL_VENDOR_ID: &str = "0x8086";
+/// fdinfo keys of device-local memory (i915 regions / xe regions)
+const LOCAL_MEMORY_KEYS: [&str; 2] = ["drm-total-local0", "drm-total-vram0"];
+
+/// Marketing names of discrete parts, by PCI device ID
+const DEVICE_NAMES: [(&str, &str); 8] = [
+    ("0x56a0", "Intel Arc A770"),
+    ("0x56a1", "Intel Arc A750"),
+    ("0x56a5", "Intel Arc A380"),
+    ("0x56c0", "Intel Data Center GPU Flex 170"),
+    ("0x56c1", "Intel Data Center GPU Flex 140"),
+    ("0x0bd5", "Intel Data Center GPU Max 1550"),
+    ("0x0bd6", "Intel Data Center GPU Max 1550"),
+    ("0x0bda", "Intel Data Center GPU Max 1100"),
+];
+
+/// Stats of every i915 / xe card, read from sysfs and DRM fdinfo
+#[derive(Debug, Default)]
+pub struct IntelBackend {
+    previous: Mutex<Option<Sample>>,
+}
+
+/// Counters of one query, kept to turn the next query's counters into rates
+#[derive(Debug)]
+struct Sample {
+    at: Instant,
+    cards: HashMap<String, Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    /// Per engine class: busy time (ns) or cycles, total cycles (xe only), engine count
+    engines: HashMap<String, (u64, Option<u64>, u64)>,
+    /// hwmon energy (µJ)
+    energy: Option<u64>,
+}
+
+impl IntelBackend {
+    /// Whether any Intel GPU is present
+    pub fn detect() -> bool {
+        !drm::cards(INTEL_VENDOR_ID).is_empty()
+    }
+}
+
+impl GpuBackend for IntelBackend {
+    fn query(&self, gpu_ids: Option<&[u32]>, _query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        let cards = drm::cards(INTEL_VENDOR_ID);
+        if cards.is_empty() {
+            return Err("no Intel GPU found".into());
+        }
+        let mut clients = drm::clients("i915");
+        clients.extend(drm::clients("xe"));
+
+        let now = Instant::now();
+        let mut previous = self.previous.lock().unwrap_or_else(|e| e.into_inner());
+        let mut sample = Sample { at: now, cards: HashMap::new() };
+        let mut gpus = Vec::new();
+        for (i, card) in cards.iter().enumerate() {
+            let counters = counters(card, &clients);
+            if gpu_ids.is_none_or(|ids| ids.contains(&(i as u32))) {
+                let prev = previous.as_ref().and_then(|p| Some((p.cards.get(&card.pdev)?, now.duration_since(p.at).as_secs_f64())));
+                gpus.push(query_card(i as u32, card, &clients, &counters, prev));
+            }
+            sample.cards.insert(card.pdev.clone(), counters);
+        }
+        *previous = Some(sample);
+
+        Ok(GpuStatCollection {
+            hostname: host_name(),
+            query_time: Utc::now(),
+            driver_version: None,
+            cuda_version: None,
+            nvml_version: None,
+            sample_period: None,
+            query_latency: None,
+            temperature_unit: Default::default(),
+            memory_unit: Default::default(),
+            gpus,
+            users: Vec::new(),
+            by_process: Vec::new(),
+        })
+    }
+}
+
+fn counters(card: &Card, clients: &[Client]) -> Counters {
+    let mut engines: HashMap<String, (u64, Option<u64>, u64)> = HashMap::new();
+    for c in clients.iter().filter(|c| c.pdev == card.pdev) {
+        for (class, ns) in c.engine_ns() {
+            let e = engines.entry(class.to_string()).or_insert((0, None, c.capacity(class)));
+            e.0 += ns;
+        }
+        for (class, cycles, total) in c.engine_cycles() {
+            let e = engines.entry(class.to_string()).or_insert((0, Some(0), c.capacity(class)));
+            e.0 += cycles;
+            // Total cycles is the GPU timestamp, the same for every client
+            e.1 = e.1.max(Some(total));
+        }
+    }
+    let energy = drm::hwmon(&card.device).and_then(|h| drm::read_u64(&h.join("energy1_input")));
+    Counters { engines, energy }
+}
+
+/// Busiest engine class over the last sample period, in %
+fn utilization(now: &Counters, prev: &Counters, seconds: f64) -> Option<u32> {
+    now.engines
+        .iter()
+        .filter_map(|(class, &(busy, total, capacity))| {
+            let (prev_busy, prev_total, _) = prev.engines.get(class)?;
+            let busy = busy.saturating_sub(*prev_busy) as f64;
+            let elapsed = match (total, prev_total) {
+                (Some(t), Some(p)) => t.saturating_sub(*p) as f64,
+                _ => seconds * 1e9,
+            };
+            (elapsed > 0.0).then(|| busy / (elapsed * capacity as f64) * 100.0)
+        })
+        .reduce(f64::max)
+        .map(|u| u.clamp(0.0, 100.0).round() as u32)
+}
+
+fn query_card(index: u32, card: &Card, clients: &[Client], counters: &Counters, prev: Option<(&Counters, f64)>) -> GpuStat {
+    let dev = &card.device;
+    let hwmon = drm::hwmon(dev);
+    let hwmon = hwmon.as_deref();
+    let procs = drm::memory_by_pid(clients, &card.pdev, &LOCAL_MEMORY_KEYS);
+    // Device-local memory: sysfs where the driver has it, else what the clients hold
+    let card_dir = dev.parent().unwrap_or(dev);
+    let memory_total_bytes = drm::read_u64(&card_dir.join("lmem_total_bytes")).unwrap_or(0);
+    let memory_used_bytes = match drm::read_u64(&card_dir.join("lmem_avail_bytes")) {
+        Some(avail) if memory_total_bytes > 0 => memory_total_bytes.saturating_sub(avail),
+        _ => procs.iter().map(|(_, b)| b).sum(),
+    };
+    let memory_total = Some(memory_total_bytes).filter(|&t| t > 0);
+    let (clock, clock_max) = gt_freq(card);
+    let (pcie_gen, pcie_gen_max, pcie_width, pcie_width_max) = drm::pcie_link(dev);
+    let device_id = drm::read_str(&dev.join("device")).unwrap_or_default();
+    GpuStat {
+        index,
+        name: DEVICE_NAMES
+            .iter()
+            .find(|(id, _)| *id == device_id)
+            .map_or_else(|| format!("Intel GPU {}", device_id), |(_, name)| name.to_string()),
+        uuid: card.pdev.clone(),
+        temperature: hwmon.and_then(|h| drm::temperature(h, "pkg").or_else(|| drm::first_temperature(h))),
+        utilization: prev.and_then(|(p, secs)| utilization(counters, p, secs)),
+        power_draw: prev.and_then(|(p, secs)| {
+            let joules = counters.energy?.checked_sub(p.energy?)? as f64 / 1e6;
+            (secs > 0.0).then(|| (joules / secs).round() as u32)
+        }),
+        power_limit: hwmon.and_then(|h| drm::power(h, "power1_max")),
+        energy_total: counters.energy.map(|uj| uj / 1000),
+        clock_sm: clock,
+        clock_sm_max: clock_max,
+        clock_graphics: clock,
+        clock_graphics_max: clock_max,
+        pcie_gen,
+        pcie_gen_max,
+        pcie_width,
+        pcie_width_max,
+        memory_used: memory_used_bytes / MB,
+        memory_total: memory_total_bytes / MB,
+        memory_free: memory_total_bytes.saturating_sub(memory_used_bytes) / MB,
+        memory_used_bytes,
+        memory_total_bytes,
+        processes: Some(
+            procs
+                .iter()
+                .map(|&(pid, bytes)| GpuProcessInfo::lookup(pid, Some(bytes), memory_total))
+                .collect(),
+        ),
+        available: true,
+        ..Default::default()
+    }
+}
+
+/// Actual and maximum GT frequency in MHz (i915 on the card, xe per GT)
+fn gt_freq(card: &Card) -> (Option<u32>, Option<u32>) {
+    let read = |path: &Path| drm::read_u64(path).map(|f| f as u32);
+    if card.driver == "xe" {
+        let freq = card.device.join("tile0/gt0/freq0");
+        (read(&freq.join("act_freq")), read(&freq.join("rp0_freq")))
+    } else {
+        let card_dir = card.device.parent().unwrap_or(&card.device);
+        (read(&card_dir.join("gt_act_freq_mhz")), read(&card_dir.join("gt_RP0_freq_mhz")))
+    }
+}
@@ -0,0 +1,192 @@
+//! Jetson / Tegra integrated GPUs, where NVML is not available
+//!
+//! Reads the same sysfs nodes tegrastats does: GR3D load and devfreq clock,
+//! the GPU thermal zone, the INA3221 power rails and the EMC clock. The GPU
+//! shares system RAM, so memory is the RAM figure tegrastats prints.
+
+use crate::backend::GpuBackend;
+use crate::GpuStatError;
+use crate::drm::{read_str, read_u64};
+use crate::{host_name, GpuProcessInfo, GpuStat, GpuStatCollection, QueryOptions, MB};
+use chrono::Utc;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// GR3D load in per mille, depending on SoC and kernel
+const LOAD_PATHS: [&str; 7] = [
+    "/sys/devices/platform/bus@0/17000000.gpu/load",
+    "/sys/devices/platform/17000000.ga10b/load",
+    "/sys/devices/platform/gpu.0/load",
+    "/sys/devices/17000000.gv11b/load",
+    "/sys/devices/17000000.gp10b/load",
+    "/sys/devices/57000000.gpu/load",
+    "/sys/devices/gpu.0/load",
+];
+/// devfreq node names of the GPU
+const DEVFREQ_NAMES: [&str; 5] = ["gpu", "ga10b", "gv11b", "gp10b", "57000000.gpu"];
+/// EMC clock in Hz (debugfs, root only)
+const EMC_RATE_PATHS: [&str; 2] = ["/sys/kernel/debug/bpmp/debug/clk/emc/rate", "/sys/kernel/debug/clk/emc/clk_rate"];
+/// nvmap allocations per client (debugfs, root only)
+const NVMAP_CLIENTS: &str = "/sys/kernel/debug/nvmap/iovmm/clients";
+
+/// Stats of the integrated GPU of a Jetson module
+#[derive(Debug, Default)]
+pub struct JetsonBackend;
+
+impl JetsonBackend {
+    /// Whether this is a Tegra SoC with a readable GR3D load
+    pub fn detect() -> bool {
+        load_path().is_some()
+    }
+}
+
+fn load_path() -> Option<&'static Path> {
+    LOAD_PATHS.iter().map(Path::new).find(|p| p.exists())
+}
+
+impl GpuBackend for JetsonBackend {
+    fn query(&self, gpu_ids: Option<&[u32]>, _query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        let load = load_path().ok_or("no Tegra GPU found")?;
+        let gpus = if gpu_ids.is_none_or(|ids| ids.contains(&0)) { vec![query_gpu(load)] } else { Vec::new() };
+        Ok(GpuStatCollection {
+            hostname: host_name(),
+            query_time: Utc::now(),
+            // e.g. "# R35 (release), REVISION: 4.1, ..."
+            driver_version: read_str(Path::new("/etc/nv_tegra_release"))
+                .and_then(|r| r.lines().next().map(|l| l.trim_start_matches('#').trim().to_string())),
+            cuda_version: None,
+            nvml_version: None,
+            sample_period: None,
+            query_latency: None,
+            temperature_unit: Default::default(),
+            memory_unit: Default::default(),
+            gpus,
+            users: Vec::new(),
+            by_process: Vec::new(),
+        })
+    }
+}
+
+fn query_gpu(load: &Path) -> GpuStat {
+    let (memory_used_bytes, memory_total_bytes) = ram();
+    let (clock, clock_max) = devfreq().map_or((None, None), |d| {
+        let mhz = |file: &str| read_u64(&d.join(file)).map(|hz| (hz / 1_000_000) as u32);
+        (mhz("cur_freq"), mhz("max_freq"))
+    });
+    let memory_total = Some(memory_total_bytes).filter(|&t| t > 0);
+    GpuStat {
+        index: 0,
+        name: read_str(Path::new("/proc/device-tree/model"))
+            .map(|m| m.trim_end_matches('\0').to_string())
+            .unwrap_or_else(|| "NVIDIA Tegra".to_string()),
+        uuid: read_str(Path::new("/proc/device-tree/serial-number"))
+            .map(|s| s.trim_end_matches('\0').to_string())
+            .unwrap_or_default(),
+        temperature: thermal_zone(&["GPU-therm", "gpu-thermal"]),
+        utilization: read_u64(load).map(|permille| (permille / 10).min(100) as u32),
+        power_draw: gpu_rail_power().map(|mw| (mw / 1000) as u32),
+        clock_sm: clock,
+        clock_sm_max: clock_max,
+        clock_graphics: clock,
+        clock_graphics_max: clock_max,
+        clock_mem: EMC_RATE_PATHS.iter().find_map(|p| read_u64(Path::new(p))).map(|hz| (hz / 1_000_000) as u32),
+        memory_used: memory_used_bytes / MB,
+        memory_total: memory_total_bytes / MB,
+        memory_free: memory_total_bytes.saturating_sub(memory_used_bytes) / MB,
+        memory_used_bytes,
+        memory_total_bytes,
+        processes: nvmap_clients()
+            .map(|clients| clients.into_iter().map(|(pid, bytes)| GpuProcessInfo::lookup(pid, Some(bytes), memory_total)).collect()),
+        available: true,
+        ..Default::default()
+    }
+}
+
+/// Used and total RAM in bytes (MemTotal - MemAvailable, as tegrastats)
+fn ram() -> (u64, u64) {
+    let meminfo = fs::read_to_string("/proc/meminfo").unwrap_or_default();
+    let kib = |key: &str| {
+        meminfo
+            .lines()
+            .find_map(|l| l.strip_prefix(key)?.strip_prefix(':')?.split_whitespace().next()?.parse::<u64>().ok())
+            .unwrap_or(0)
+            * 1024
+    };
+    let total = kib("MemTotal");
+    (total.saturating_sub(kib("MemAvailable")), total)
+}
+
+fn devfreq() -> Option<PathBuf> {
+    fs::read_dir("/sys/class/devfreq").ok()?.flatten().map(|e| e.path()).find(|p| {
+        let name = p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        DEVFREQ_NAMES.iter().any(|n| name.ends_with(n))
+    })
+}
+
+/// Temperature (°C) of the first thermal zone with one of `types`
+fn thermal_zone(types: &[&str]) -> Option<u32> {
+    fs::read_dir("/sys/class/thermal").ok()?.flatten().find_map(|e| {
+        let zone = e.path();
+        let kind = read_str(&zone.join("type"))?;
+        if !types.contains(&kind.as_str()) {
+            return None;
+        }
+        read_u64(&zone.join("temp")).map(|m| (m / 1000) as u32)
+    })
+}
+
+/// Power (mW) of the INA3221 rail feeding the GPU ("VDD_GPU_SOC", "VDD_GPU", ...)
+fn gpu_rail_power() -> Option<u64> {
+    let is_gpu = |label: &str| label.to_ascii_uppercase().contains("GPU");
+    // hwmon interface (JetPack 5+): inN_label / inN_input (mV) / currN_input (mA)
+    for dev in fs::read_dir("/sys/bus/i2c/drivers/ina3221").ok().into_iter().flatten().flatten() {
+        for hwmon in fs::read_dir(dev.path().join("hwmon")).ok().into_iter().flatten().flatten() {
+            let h = hwmon.path();
+            for n in 1..=3 {
+                if read_str(&h.join(format!("in{}_label", n))).is_some_and(|l| is_gpu(&l)) {
+                    let mv = read_u64(&h.join(format!("in{}_input", n)))?;
+                    let ma = read_u64(&h.join(format!("curr{}_input", n)))?;
+                    return Some(mv * ma / 1000);
+                }
+            }
+        }
+    }
+    // iio interface (JetPack 4): rail_name_N / in_power{N}_input (mW)
+    for dev in fs::read_dir("/sys/bus/i2c/drivers/ina3221x").ok().into_iter().flatten().flatten() {
+        for iio in fs::read_dir(dev.path()).ok().into_iter().flatten().flatten() {
+            let d = iio.path();
+            if !d.file_name().is_some_and(|n| n.to_string_lossy().starts_with("iio:device")) {
+                continue;
+            }
+            for n in 0..3 {
+                if read_str(&d.join(format!("rail_name_{}", n))).is_some_and(|l| is_gpu(&l)) {
+                    return read_u64(&d.join(format!("in_power{}_input", n)));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// GPU-mapped memory per PID from nvmap; None when debugfs isn't readable
+fn nvmap_clients() -> Option<Vec<(u32, u64)>> {
+    // CLIENT        PROCESS      PID        SIZE
+    // user          python3      1234       102400K
+    let table = fs::read_to_string(NVMAP_CLIENTS).ok()?;
+    let mut clients: Vec<(u32, u64)> = Vec::new();
+    for line in table.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [_, _, pid, size] = fields[..] else {
+            continue;
+        };
+        let (Ok(pid), Some(kib)) = (pid.parse::<u32>(), size.strip_suffix('K').and_then(|k| k.parse::<u64>().ok())) else {
+            continue;
+        };
+        match clients.iter_mut().find(|(p, _)| *p == pid) {
+            Some((_, bytes)) => *bytes += kib * 1024,
+            None => clients.push((pid, kib * 1024)),
+        }
+    }
+    clients.sort_unstable();
+    Some(clients)
+}
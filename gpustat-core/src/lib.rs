@@ -0,0 +1,1621 @@
+//! Core GPU stat structures and NVML query logic
+//! Ported from Python gpustat (https://github.com/wookayin/gpustat)
+//!
+//! `GpuStatCollection::new_query` gathers every GPU and its processes; all stat
+//! structs serialize with serde, matching `gpustat --json`.
+
+mod amd;
+#[cfg(target_os = "macos")]
+mod apple;
+pub mod backend;
+pub mod container;
+#[cfg(feature = "dcgm")]
+mod dcgm;
+pub mod delta;
+mod drm;
+pub mod error;
+mod intel;
+mod jetson;
+mod mig;
+pub mod procinfo;
+pub mod slurm;
+mod vgpu;
+
+pub use amd::AmdBackend;
+#[cfg(target_os = "macos")]
+pub use apple::AppleBackend;
+pub use backend::{AutoBackend, GpuBackend, MockBackend, NvmlBackend};
+#[cfg(feature = "dcgm")]
+pub use dcgm::DcgmBackend;
+pub use delta::{GpuDelta, GpuStatDelta};
+pub use error::{DeviceError, DeviceErrorKind, GpuStatError};
+use error::Traced;
+pub use intel::IntelBackend;
+pub use jetson::JetsonBackend;
+
+/// Re-exported so embedders can initialize NVML without depending on nvml-wrapper
+pub use nvml_wrapper::Nvml;
+
+use container::Container;
+use procinfo::ProcInfo;
+use slurm::SlurmJob;
+use chrono::{DateTime, Utc};
+use nvml_wrapper::bitmasks::device::ThrottleReasons;
+use nvml_wrapper::enum_wrappers::device::{
+    Clock, GpuVirtualizationMode, PcieUtilCounter, PerformancePolicy, RetirementCause, TemperatureSensor,
+    TemperatureThreshold,
+};
+use nvml_wrapper::enums::device::{SampleValue, UsedGpuMemory};
+use nvml_wrapper::error::NvmlError;
+use nvml_wrapper::struct_wrappers::device::ProcessUtilizationSample;
+use nvml_wrapper::structs::device::FieldId;
+use nvml_wrapper::sys_exports::field_id::*;
+use nvml_wrapper::Device;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+const MB: u64 = 1024 * 1024;
+/// Field values fetched with a single `nvmlDeviceGetFieldValues` per GPU
+const BATCHED_FIELDS: [u32; 16] = [
+    NVML_FI_DEV_MEMORY_TEMP,
+    NVML_FI_DEV_TEMPERATURE_SLOWDOWN_TLIMIT,
+    NVML_FI_DEV_TEMPERATURE_SHUTDOWN_TLIMIT,
+    NVML_FI_DEV_TEMPERATURE_GPU_MAX_TLIMIT,
+    NVML_FI_DEV_TEMPERATURE_MEM_MAX_TLIMIT,
+    NVML_FI_DEV_POWER_CURRENT_LIMIT,
+    NVML_FI_DEV_POWER_DEFAULT_LIMIT,
+    NVML_FI_DEV_POWER_MIN_LIMIT,
+    NVML_FI_DEV_POWER_MAX_LIMIT,
+    NVML_FI_DEV_TOTAL_ENERGY_CONSUMPTION,
+    NVML_FI_DEV_PERF_POLICY_POWER,
+    NVML_FI_DEV_PERF_POLICY_THERMAL,
+    NVML_FI_DEV_REMAPPED_COR,
+    NVML_FI_DEV_REMAPPED_UNC,
+    NVML_FI_DEV_REMAPPED_PENDING,
+    NVML_FI_DEV_REMAPPED_FAILURE,
+];
+/// Upper bound on threads querying GPUs at once
+const MAX_QUERY_THREADS: usize = 8;
+/// How far back to look for per-process utilization samples (μs)
+const PROCESS_UTIL_WINDOW_US: u64 = 1_000_000;
+
+/// Unit of `GpuStat::temperature` values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TempUnit {
+    #[default]
+    #[serde(rename = "C")]
+    Celsius,
+    #[serde(rename = "F")]
+    Fahrenheit,
+}
+
+impl TempUnit {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TempUnit::Celsius => "°C",
+            TempUnit::Fahrenheit => "°F",
+        }
+    }
+
+    /// Convert a value in this unit back to Celsius (thresholds are in °C)
+    pub fn to_celsius(self, value: f64) -> f64 {
+        match self {
+            TempUnit::Celsius => value,
+            TempUnit::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+        }
+    }
+}
+
+impl std::str::FromStr for TempUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_uppercase().as_str() {
+            "C" | "CELSIUS" => Ok(TempUnit::Celsius),
+            "F" | "FAHRENHEIT" => Ok(TempUnit::Fahrenheit),
+            _ => Err(format!("invalid temperature unit '{}' (expected C or F)", s)),
+        }
+    }
+}
+
+/// Unit of the memory column; JSON always carries MiB and raw bytes as well
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MemUnit {
+    #[default]
+    MiB,
+    GiB,
+    #[serde(rename = "%")]
+    Percent,
+}
+
+impl MemUnit {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            MemUnit::MiB => "MB",
+            MemUnit::GiB => "GiB",
+            MemUnit::Percent => "%",
+        }
+    }
+}
+
+impl std::str::FromStr for MemUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_uppercase().as_str() {
+            "MIB" | "MB" | "M" => Ok(MemUnit::MiB),
+            "GIB" | "GB" | "G" => Ok(MemUnit::GiB),
+            "%" | "PERCENT" => Ok(MemUnit::Percent),
+            _ => Err(format!("invalid memory unit '{}' (expected MiB, GiB or %)", s)),
+        }
+    }
+}
+
+/// GPU compute mode (names as printed by nvidia-smi)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComputeMode {
+    Default,
+    #[serde(rename = "Exclusive_Thread")]
+    ExclusiveThread,
+    Prohibited,
+    #[serde(rename = "Exclusive_Process")]
+    ExclusiveProcess,
+}
+
+impl ComputeMode {
+    /// Short table label, as in the nvidia-smi "Compute M." column
+    pub fn label(self) -> &'static str {
+        match self {
+            ComputeMode::Default => "Default",
+            ComputeMode::ExclusiveThread => "E. Thread",
+            ComputeMode::Prohibited => "Prohibited",
+            ComputeMode::ExclusiveProcess => "E. Process",
+        }
+    }
+}
+
+impl From<nvml_wrapper::enum_wrappers::device::ComputeMode> for ComputeMode {
+    fn from(mode: nvml_wrapper::enum_wrappers::device::ComputeMode) -> Self {
+        use nvml_wrapper::enum_wrappers::device::ComputeMode as Nv;
+        match mode {
+            Nv::Default => ComputeMode::Default,
+            Nv::ExclusiveThread => ComputeMode::ExclusiveThread,
+            Nv::Prohibited => ComputeMode::Prohibited,
+            Nv::ExclusiveProcess => ComputeMode::ExclusiveProcess,
+        }
+    }
+}
+
+/// What to query beyond the default fields
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptions {
+    /// Serial, vBIOS, board part number and PCI IDs (extra NVML calls)
+    pub inventory: bool,
+    /// PCIe TX/RX throughput; NVML samples each counter for ~20 ms, per GPU
+    pub pcie: bool,
+}
+
+/// Static hardware identification for fleet inventory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Inventory {
+    pub serial: Option<String>,
+    pub vbios_version: Option<String>,
+    pub board_part_number: Option<String>,
+    pub pci_bus_id: Option<String>,
+    /// Combined device and vendor ID, e.g. 0x20B010DE
+    pub pci_device_id: Option<u32>,
+    pub pci_subsystem_id: Option<u32>,
+}
+
+impl Inventory {
+    fn query(device: &Device) -> Self {
+        let pci = device.pci_info().traced("pci_info");
+        Inventory {
+            serial: device.serial().traced("serial"),
+            vbios_version: device.vbios_version().traced("vbios_version"),
+            board_part_number: device.board_part_number().traced("board_part_number"),
+            pci_bus_id: pci.as_ref().map(|p| p.bus_id.clone()),
+            pci_device_id: pci.as_ref().map(|p| p.pci_device_id),
+            pci_subsystem_id: pci.as_ref().and_then(|p| p.pci_sub_system_id),
+        }
+    }
+}
+
+/// Bytes to GiB
+pub fn to_gib(bytes: u64) -> f64 {
+    bytes as f64 / (1u64 << 30) as f64
+}
+
+/// Metric used to order GPUs (`--sort`); all but `index` put the largest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SortKey {
+    Index,
+    Util,
+    Mem,
+    Temp,
+    Power,
+}
+
+impl SortKey {
+    /// Next key in the cycle used by the watch-mode key binding
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Index => SortKey::Util,
+            SortKey::Util => SortKey::Mem,
+            SortKey::Mem => SortKey::Temp,
+            SortKey::Temp => SortKey::Power,
+            SortKey::Power => SortKey::Index,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SortKey::Index => "index",
+            SortKey::Util => "util",
+            SortKey::Mem => "mem",
+            SortKey::Temp => "temp",
+            SortKey::Power => "power",
+        }
+    }
+}
+
+/// Order of processes within a GPU (`--sort-procs`); `mem` puts the largest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum ProcSortKey {
+    Mem,
+    Pid,
+    User,
+}
+
+/// Process information running on GPU
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GpuProcessInfo {
+    pub pid: u32,
+    pub username: Option<String>,
+    pub command: String,
+    pub gpu_memory_usage: Option<u64>, // in MB
+    /// GPU memory as a percentage of the device's (or MIG instance's) total
+    pub gpu_memory_percent: Option<f64>,
+    /// True when username/command were resolved via Ngid-to-PID mapping
+    pub username_from_ngid_mapping: bool,
+    /// Real host PID when resolved via Ngid mapping (original pid was Ngid)
+    pub real_pid: Option<u32>,
+    /// Full command line, one entry per argument
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_command: Option<Vec<String>>,
+    /// Host CPU usage (% of one core) and resident memory (bytes). CPU is averaged
+    /// over the process lifetime, or over the last sample period in watch mode
+    pub cpu_percent: Option<f64>,
+    pub cpu_memory_usage: Option<u64>,
+    /// User + system CPU time consumed so far (s)
+    pub cpu_time: Option<f64>,
+    /// Conda env or virtualenv name of a Python process
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub python_env: Option<String>,
+    /// The process's CUDA_VISIBLE_DEVICES; None when unset (all GPUs visible) or
+    /// when its environment isn't readable (another user's process, unless root)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cuda_visible_devices: Option<String>,
+    /// Slurm job the process runs in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slurm_job: Option<SlurmJob>,
+    /// Docker / containerd / CRI-O container, and Kubernetes pod, the process runs in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<Container>,
+    /// When the process started, and seconds it has been running
+    pub start_time: Option<DateTime<Utc>>,
+    pub elapsed: Option<u64>,
+    /// Recent SM / memory / encoder / decoder utilization attributed to this process
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sm_util: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mem_util: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enc_util: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dec_util: Option<u32>,
+    /// Lifetime statistics (only when accounting mode is enabled)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accounting: Option<ProcessAccounting>,
+    /// Columns contributed by plugins, keyed by plugin name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, String>,
+}
+
+/// Fine-grained activity from DCGM profiling counters, each in % of time
+/// (averaged over the DCGM sampling interval)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfilingMetrics {
+    /// At least one warp resident on an SM
+    pub sm_active: Option<f64>,
+    /// Resident warps relative to the SM maximum
+    pub sm_occupancy: Option<f64>,
+    /// Tensor core pipes busy
+    pub tensor_active: Option<f64>,
+    /// Device memory interface busy sending or receiving
+    pub dram_active: Option<f64>,
+}
+
+/// NVML accounting statistics over a process's lifetime
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessAccounting {
+    /// Percent of the lifetime a kernel was running / memory was accessed
+    pub gpu_utilization: Option<u32>,
+    pub memory_utilization: Option<u32>,
+    pub max_memory_usage: Option<u64>, // MB
+    /// Seconds since the process created its context
+    pub runtime: u64,
+}
+
+impl GpuProcessInfo {
+    /// Host-side details of `pid` (owner, command, CPU, container, ...) holding
+    /// `gpu_memory_bytes` out of a device total of `memory_total` bytes
+    pub(crate) fn lookup(pid: u32, gpu_memory_bytes: Option<u64>, memory_total: Option<u64>) -> Self {
+        let (username, command, username_from_ngid_mapping, real_pid) = get_process_info(pid);
+        let host = HostStats::query(real_pid.unwrap_or(pid));
+        GpuProcessInfo {
+            pid,
+            username,
+            command,
+            gpu_memory_usage: gpu_memory_bytes.map(|b| b / MB),
+            gpu_memory_percent: gpu_memory_bytes.zip(memory_total).map(|(b, t)| b as f64 / t as f64 * 100.0),
+            username_from_ngid_mapping,
+            real_pid,
+            full_command: host.full_command,
+            cpu_percent: host.cpu_percent,
+            cpu_memory_usage: host.cpu_memory_usage,
+            cpu_time: host.cpu_time,
+            python_env: host.python_env,
+            cuda_visible_devices: host.cuda_visible_devices,
+            slurm_job: host.slurm_job,
+            container: container::detect(real_pid.unwrap_or(pid)),
+            start_time: host.start_time,
+            elapsed: host.elapsed,
+            ..Default::default()
+        }
+    }
+}
+
+impl ProcessAccounting {
+    fn query(device: &Device, pid: u32) -> Option<Self> {
+        let stats = device.accounting_stats_for(pid).traced("accounting_stats_for")?;
+        // `time` is only filled in once the process has exited
+        let runtime = if stats.time > 0 {
+            stats.time / 1000
+        } else {
+            let now_us = Utc::now().timestamp_micros().max(0) as u64;
+            now_us.saturating_sub(stats.start_time) / 1_000_000
+        };
+        Some(ProcessAccounting {
+            gpu_utilization: stats.gpu_utilization,
+            memory_utilization: stats.memory_utilization,
+            max_memory_usage: stats.max_memory_usage.map(|b| b / MB),
+            runtime,
+        })
+    }
+}
+
+/// A MIG GPU/compute instance nested under its physical GPU
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigInstance {
+    pub gpu_instance_id: Option<u32>,
+    pub compute_instance_id: Option<u32>,
+    pub uuid: String,
+    /// Profile name, e.g. "1g.10gb"
+    pub profile: String,
+    pub memory_used: u64,  // MB
+    pub memory_total: u64, // MB
+    /// Usually not supported per instance
+    pub utilization: Option<u32>,
+    pub processes: Option<Vec<GpuProcessInfo>>,
+}
+
+/// A vGPU instance hosted on a physical GPU, as seen from the hypervisor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VgpuInstance {
+    pub id: u32,
+    /// VM name / domain ID or UUID, depending on the hypervisor
+    pub vm_id: Option<String>,
+    pub uuid: Option<String>,
+    /// vGPU type, e.g. "GRID A100-4C"
+    pub vgpu_type: Option<String>,
+    pub memory_used: Option<u64>, // MB
+}
+
+/// Single GPU statistics
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GpuStat {
+    pub index: u32,
+    pub name: String,
+    pub uuid: String,
+    /// CUDA compute capability, e.g. "8.9", and architecture name, e.g. "Ada"
+    pub compute_capability: Option<String>,
+    pub architecture: Option<String>,
+    pub temperature: Option<u32>,
+    /// Memory (HBM) temperature, where supported
+    pub temperature_memory: Option<u32>,
+    /// Temperature thresholds: clocks slow down, the GPU shuts down, max operating
+    /// temperature for the GPU and for memory (same unit as `temperature`)
+    pub temperature_slowdown: Option<u32>,
+    pub temperature_shutdown: Option<u32>,
+    pub temperature_gpu_max: Option<u32>,
+    pub temperature_memory_max: Option<u32>,
+    pub fan_speed: Option<u32>,
+    /// Speed of every fan in %; `fan_speed` is the first of these
+    pub fan_speeds: Vec<u32>,
+    pub utilization: Option<u32>,
+    pub utilization_enc: Option<u32>,
+    pub utilization_dec: Option<u32>,
+    /// JPEG decoder and optical flow accelerator utilization (Ampere+)
+    pub utilization_jpg: Option<u32>,
+    pub utilization_ofa: Option<u32>,
+    /// Active encoder sessions with their trailing average FPS and latency (µs)
+    pub encoder_sessions: Option<u32>,
+    pub encoder_fps: Option<u32>,
+    pub encoder_latency: Option<u32>,
+    /// DCGM profiling metrics (only with the `dcgm` feature and a running nv-hostengine)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profiling: Option<ProfilingMetrics>,
+    pub power_draw: Option<u32>,  // Watts
+    pub power_limit: Option<u32>, // Watts
+    /// Default power management limit and the configurable range (Watts)
+    pub power_limit_default: Option<u32>,
+    pub power_limit_min: Option<u32>,
+    pub power_limit_max: Option<u32>,
+    /// Energy consumed since the driver was loaded (mJ, Volta+)
+    pub energy_total: Option<u64>,
+    /// Energy consumed since the previous sample in joules (watch mode only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy_delta: Option<f64>,
+    /// Current SM, memory and graphics clocks (MHz)
+    pub clock_sm: Option<u32>,
+    pub clock_mem: Option<u32>,
+    pub clock_graphics: Option<u32>,
+    /// Maximum clocks (MHz)
+    pub clock_sm_max: Option<u32>,
+    pub clock_mem_max: Option<u32>,
+    pub clock_graphics_max: Option<u32>,
+    /// Raw `nvmlClocksEventReasons` bitmask (see [`GpuStat::throttles`])
+    pub throttle_reasons: Option<u64>,
+    /// Cumulative time capped by the power / thermal limit since boot (ns)
+    pub violation_power: Option<u64>,
+    pub violation_thermal: Option<u64>,
+    /// Share of the last sample period spent capped (%, watch mode only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub violation_power_pct: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub violation_thermal_pct: Option<f64>,
+    /// PCIe link generation and width, current and maximum
+    pub pcie_gen: Option<u32>,
+    pub pcie_gen_max: Option<u32>,
+    pub pcie_width: Option<u32>,
+    pub pcie_width_max: Option<u32>,
+    /// PCIe throughput over the last 20 ms (KB/s)
+    pub pcie_tx: Option<u32>,
+    pub pcie_rx: Option<u32>,
+    /// Retired memory pages by cause, and whether a retirement awaits a reset (pre-Ampere)
+    pub retired_pages_sbe: Option<u32>,
+    pub retired_pages_dbe: Option<u32>,
+    pub retired_pages_pending: Option<bool>,
+    /// Row remapper state (Ampere+): remapped rows by error type, pending remap, remap failure
+    pub remapped_rows_correctable: Option<u32>,
+    pub remapped_rows_uncorrectable: Option<u32>,
+    pub remapping_pending: Option<bool>,
+    pub remapping_failed: Option<bool>,
+    /// ECC mode, and whether a mode change takes effect at the next reset
+    pub ecc_mode: Option<bool>,
+    pub ecc_mode_pending: Option<bool>,
+    /// Why the GPU needs a reset: "retired_pages", "row_remap", "ecc_mode", "mig_mode"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reset_required: Vec<String>,
+    pub memory_used: u64,  // MB
+    pub memory_total: u64, // MB
+    /// Unallocated memory, and memory the driver reserves for itself (MB). Reserved
+    /// memory is counted in `memory_used` but belongs to no process
+    pub memory_free: u64,
+    pub memory_reserved: u64,
+    /// Raw NVML values in bytes (`memory_used`/`memory_total` are rounded down to MiB)
+    pub memory_used_bytes: u64,
+    pub memory_total_bytes: u64,
+    /// Memory in the selected `--mem-unit` (only present for GiB / %)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_used_gib: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_total_gib: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_percent: Option<f64>,
+    /// BAR1 aperture usage (MB)
+    pub bar1_used: Option<u64>,
+    pub bar1_total: Option<u64>,
+    /// Compute mode and whether persistence mode is on
+    pub compute_mode: Option<ComputeMode>,
+    pub persistence_mode: Option<bool>,
+    /// A display is physically connected / the GPU is driving one
+    pub display_attached: Option<bool>,
+    pub display_active: Option<bool>,
+    /// Windows driver model ("WDDM", "TCC" or "MCDM"), current and after reboot
+    pub driver_model: Option<String>,
+    pub driver_model_pending: Option<String>,
+    /// Hardware inventory, only queried with `--inventory`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inventory: Option<Inventory>,
+    pub processes: Option<Vec<GpuProcessInfo>>,
+    /// MIG mode (None when the GPU does not support MIG)
+    pub mig_mode: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mig_instances: Vec<MigInstance>,
+    /// "None", "Pass-Through", "VGPU" (guest), "Host VGPU" or "Host VSGA"
+    pub virtualization_mode: Option<String>,
+    /// vGPUs running on this GPU (vGPU hosts only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vgpu_instances: Vec<VgpuInstance>,
+    pub available: bool,
+    /// Why the GPU could not be queried (unavailable GPUs only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<DeviceError>,
+    /// NVML calls this GPU or its driver doesn't support, e.g. "fan_speed";
+    /// their fields are null for that reason rather than a failed query
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unsupported: Vec<String>,
+    /// Columns contributed by plugins, keyed by plugin name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, String>,
+}
+
+/// A clock throttle cause worth flagging in the table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throttle {
+    pub label: &'static str,
+    /// Hardware-enforced (HW slowdown, HW thermal, power brake) rather than a software cap
+    pub severe: bool,
+}
+
+impl GpuStat {
+    /// 0% utilization and no running processes
+    pub fn is_idle(&self) -> bool {
+        self.available
+            && self.utilization == Some(0)
+            && self.processes.as_ref().is_some_and(|p| p.is_empty())
+    }
+
+    /// Decode `throttle_reasons`, ignoring benign reasons (idle, application clocks, sync boost)
+    pub fn throttles(&self) -> Vec<Throttle> {
+        let Some(bits) = self.throttle_reasons else {
+            return Vec::new();
+        };
+        let r = ThrottleReasons::from_bits_truncate(bits);
+        let mut out = Vec::new();
+        if r.contains(ThrottleReasons::SW_POWER_CAP) {
+            out.push(Throttle { label: "PWR", severe: false });
+        }
+        if r.intersects(ThrottleReasons::SW_THERMAL_SLOWDOWN | ThrottleReasons::HW_THERMAL_SLOWDOWN) {
+            out.push(Throttle {
+                label: "THERM",
+                severe: r.contains(ThrottleReasons::HW_THERMAL_SLOWDOWN),
+            });
+        }
+        if r.intersects(ThrottleReasons::HW_SLOWDOWN | ThrottleReasons::HW_POWER_BRAKE_SLOWDOWN) {
+            out.push(Throttle { label: "HW", severe: true });
+        }
+        out
+    }
+
+    /// Table marker when the GPU needs attention: `RMA` after a failed row remap,
+    /// `RESET` while a page retirement, row remap or mode change awaits a GPU reset
+    pub fn memory_alert(&self) -> Option<&'static str> {
+        if self.remapping_failed == Some(true) {
+            Some("RMA")
+        } else if !self.reset_required.is_empty() {
+            Some("RESET")
+        } else {
+            None
+        }
+    }
+
+    /// The GPU name, or the error for a GPU that could not be queried
+    pub fn display_name(&self) -> String {
+        match &self.error {
+            Some(e) if !self.available => format!("((Error: {}))", e.message),
+            _ => self.name.clone(),
+        }
+    }
+
+    /// Slowest and fastest fan, if any fan reports a speed
+    pub fn fan_range(&self) -> Option<(u32, u32)> {
+        let min = self.fan_speeds.iter().min().or(self.fan_speed.as_ref())?;
+        let max = self.fan_speeds.iter().max().or(self.fan_speed.as_ref())?;
+        Some((*min, *max))
+    }
+
+    /// Used memory as a fraction of total (0 when the total is unknown)
+    pub fn memory_ratio(&self) -> f64 {
+        if self.memory_total_bytes > 0 {
+            self.memory_used_bytes as f64 / self.memory_total_bytes as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Collection of GPU stats with host info
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuStatCollection {
+    pub hostname: String,
+    pub query_time: DateTime<Utc>,
+    pub driver_version: Option<String>,
+    /// CUDA version supported by the driver, e.g. "12.2"
+    pub cuda_version: Option<String>,
+    pub nvml_version: Option<String>,
+    /// Seconds since the previous sample in watch mode (None for one-shot queries)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_period: Option<f64>,
+    /// Seconds the query of all GPUs took
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_latency: Option<f64>,
+    /// Unit of every GPU `temperature` in this collection
+    pub temperature_unit: TempUnit,
+    /// Unit of the optional per-GPU memory fields
+    pub memory_unit: MemUnit,
+    pub gpus: Vec<GpuStat>,
+    /// Per-user totals (`--by-user` only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<UserUsage>,
+    /// Processes merged across GPUs (`--by-process` only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub by_process: Vec<ProcessSummary>,
+}
+
+/// One process with every GPU it holds memory on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessSummary {
+    /// Host PID (the mapped one when NVML reported a namespaced PID)
+    pub pid: u32,
+    pub username: Option<String>,
+    pub command: String,
+    pub gpus: Vec<u32>,
+    pub gpu_memory_usage: u64, // MB, summed over `gpus`
+}
+
+/// GPU processes and memory of one user, overall and per GPU
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserUsage {
+    pub username: String,
+    pub processes: usize,
+    pub memory: u64, // MB
+    pub gpus: Vec<UserGpuUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserGpuUsage {
+    pub index: u32,
+    pub processes: usize,
+    pub memory: u64, // MB
+}
+
+impl GpuStatCollection {
+    /// Convert all temperatures (queried in Celsius) to `unit`
+    pub fn set_temperature_unit(&mut self, unit: TempUnit) {
+        if unit == self.temperature_unit {
+            return;
+        }
+        let convert = |t: u32| match unit {
+            TempUnit::Fahrenheit => (t as f64 * 9.0 / 5.0 + 32.0).round() as u32,
+            TempUnit::Celsius => self.temperature_unit.to_celsius(t as f64).round() as u32,
+        };
+        for gpu in &mut self.gpus {
+            gpu.temperature = gpu.temperature.map(convert);
+            gpu.temperature_memory = gpu.temperature_memory.map(convert);
+            gpu.temperature_slowdown = gpu.temperature_slowdown.map(convert);
+            gpu.temperature_shutdown = gpu.temperature_shutdown.map(convert);
+            gpu.temperature_gpu_max = gpu.temperature_gpu_max.map(convert);
+            gpu.temperature_memory_max = gpu.temperature_memory_max.map(convert);
+        }
+        self.temperature_unit = unit;
+    }
+
+    /// Fill the per-GPU memory fields for `unit` (MiB uses `memory_used`/`memory_total`)
+    pub fn set_memory_unit(&mut self, unit: MemUnit) {
+        for gpu in &mut self.gpus {
+            let (used, total, percent) = match unit {
+                MemUnit::MiB => (None, None, None),
+                MemUnit::GiB => (Some(to_gib(gpu.memory_used_bytes)), Some(to_gib(gpu.memory_total_bytes)), None),
+                MemUnit::Percent => (None, None, Some(gpu.memory_ratio() * 100.0)),
+            };
+            gpu.memory_used_gib = used;
+            gpu.memory_total_gib = total;
+            gpu.memory_percent = percent;
+        }
+        self.memory_unit = unit;
+    }
+
+    /// Fill `users` with per-user totals, largest memory first. Processes
+    /// whose owner is unknown are grouped under "?"
+    pub fn set_user_usage(&mut self) {
+        let mut users: Vec<UserUsage> = Vec::new();
+        for gpu in &self.gpus {
+            let lists = std::iter::once(&gpu.processes).chain(gpu.mig_instances.iter().map(|m| &m.processes));
+            for p in lists.flatten().flatten() {
+                let name = p.username.as_deref().unwrap_or("?");
+                let mem = p.gpu_memory_usage.unwrap_or(0);
+                let user = match users.iter_mut().position(|u| u.username == name) {
+                    Some(i) => &mut users[i],
+                    None => {
+                        users.push(UserUsage { username: name.to_string(), processes: 0, memory: 0, gpus: Vec::new() });
+                        users.last_mut().unwrap()
+                    }
+                };
+                user.processes += 1;
+                user.memory += mem;
+                match user.gpus.iter_mut().find(|g| g.index == gpu.index) {
+                    Some(g) => {
+                        g.processes += 1;
+                        g.memory += mem;
+                    }
+                    None => user.gpus.push(UserGpuUsage { index: gpu.index, processes: 1, memory: mem }),
+                }
+            }
+        }
+        users.sort_by(|a, b| b.memory.cmp(&a.memory).then_with(|| a.username.cmp(&b.username)));
+        self.users = users;
+    }
+
+    /// Fill `by_process` by merging each PID's entries across GPUs, largest memory first
+    pub fn set_process_summary(&mut self) {
+        let mut merged: Vec<ProcessSummary> = Vec::new();
+        for gpu in &self.gpus {
+            let lists = std::iter::once(&gpu.processes).chain(gpu.mig_instances.iter().map(|m| &m.processes));
+            for p in lists.flatten().flatten() {
+                let pid = p.real_pid.unwrap_or(p.pid);
+                let mem = p.gpu_memory_usage.unwrap_or(0);
+                match merged.iter_mut().find(|m| m.pid == pid) {
+                    Some(m) => {
+                        if !m.gpus.contains(&gpu.index) {
+                            m.gpus.push(gpu.index);
+                        }
+                        m.gpu_memory_usage += mem;
+                    }
+                    None => merged.push(ProcessSummary {
+                        pid,
+                        username: p.username.clone(),
+                        command: p.command.clone(),
+                        gpus: vec![gpu.index],
+                        gpu_memory_usage: mem,
+                    }),
+                }
+            }
+        }
+        merged.sort_by(|a, b| b.gpu_memory_usage.cmp(&a.gpu_memory_usage).then(a.pid.cmp(&b.pid)));
+        self.by_process = merged;
+    }
+
+    /// Reorder every process list (including MIG instances) by `key`; ties keep PID order
+    pub fn sort_processes(&mut self, key: ProcSortKey) {
+        for gpu in &mut self.gpus {
+            let lists = std::iter::once(&mut gpu.processes).chain(gpu.mig_instances.iter_mut().map(|m| &mut m.processes));
+            for procs in lists.flatten() {
+                procs.sort_by(|a, b| {
+                    let ord = match key {
+                        ProcSortKey::Mem => b.gpu_memory_usage.cmp(&a.gpu_memory_usage),
+                        ProcSortKey::Pid => std::cmp::Ordering::Equal,
+                        ProcSortKey::User => a.username.cmp(&b.username),
+                    };
+                    ord.then(a.pid.cmp(&b.pid))
+                });
+            }
+        }
+    }
+
+    /// Mask the identity of processes not owned by the current user; memory is kept
+    pub fn anonymize(&mut self) {
+        const MASK: &str = "***";
+        let me = procinfo::current_username();
+        for gpu in &mut self.gpus {
+            let lists = std::iter::once(&mut gpu.processes).chain(gpu.mig_instances.iter_mut().map(|m| &mut m.processes));
+            for p in lists.flatten().flatten() {
+                if me.is_some() && p.username == me {
+                    continue;
+                }
+                p.username = Some(MASK.to_string());
+                p.command = MASK.to_string();
+                p.full_command = None;
+                p.python_env = None;
+                p.cuda_visible_devices = None;
+                p.slurm_job = None;
+                p.container = None;
+                // Plugins and scripts may put anything here
+                p.extra.clear();
+                p.username_from_ngid_mapping = false;
+            }
+        }
+    }
+
+    /// Drop processes (including those under MIG instances) that don't match `keep`;
+    /// GPU-level stats are untouched
+    pub fn retain_processes(&mut self, keep: impl Fn(&GpuProcessInfo) -> bool) {
+        for gpu in &mut self.gpus {
+            let lists = std::iter::once(&mut gpu.processes).chain(gpu.mig_instances.iter_mut().map(|m| &mut m.processes));
+            for procs in lists.flatten() {
+                procs.retain(&keep);
+            }
+        }
+    }
+
+    /// Reorder `gpus` by `key`; ties keep index order
+    pub fn sort_gpus(&mut self, key: SortKey) {
+        self.gpus.sort_by(|a, b| {
+            let ord = match key {
+                SortKey::Index => a.index.cmp(&b.index),
+                SortKey::Util => b.utilization.cmp(&a.utilization),
+                SortKey::Mem => b.memory_used.cmp(&a.memory_used),
+                SortKey::Temp => b.temperature.cmp(&a.temperature),
+                SortKey::Power => b.power_draw.cmp(&a.power_draw),
+            };
+            ord.then(a.index.cmp(&b.index))
+        });
+    }
+
+    /// Fill `energy_delta` and violation rates (from [`GpuStat::diff`]) and per-process CPU% from the previous sample
+    pub fn set_deltas(&mut self, prev: &GpuStatCollection) {
+        let period = self.period_since(prev);
+        for gpu in &mut self.gpus {
+            let Some(before) = prev.gpus.iter().find(|p| gpu.same_gpu(p)) else {
+                continue;
+            };
+            let delta = gpu.diff(before, period);
+            gpu.energy_delta = delta.energy_used;
+            gpu.violation_power_pct = delta.violation_power_pct;
+            gpu.violation_thermal_pct = delta.violation_thermal_pct;
+
+            // Host CPU% over the period, for processes seen in both samples
+            let (Some(period), Some(procs)) = (self.sample_period, gpu.processes.as_mut()) else {
+                continue;
+            };
+            let prev_procs = before.processes.as_deref().unwrap_or_default();
+            for p in procs {
+                let was = prev_procs.iter().find(|q| q.pid == p.pid).and_then(|q| q.cpu_time);
+                if let (Some(was), Some(now)) = (was, p.cpu_time) {
+                    if now >= was && period > 0.0 {
+                        p.cpu_percent = Some((now - was) / period * 100.0);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Query all GPUs and return a new GpuStatCollection
+    pub fn new_query(
+        nvml: &Nvml,
+        gpu_ids: Option<&[u32]>,
+        query: &QueryOptions,
+    ) -> Result<Self, NvmlError> {
+        let gpus_to_query: Vec<u32> = match gpu_ids {
+            Some(ids) => ids.to_vec(),
+            None => (0..nvml.device_count()?).collect(),
+        };
+        let devices = gpus_to_query.iter().map(|&index| (index, nvml.device_by_index(index))).collect();
+        Ok(Self::from_devices(nvml, devices, query))
+    }
+
+    /// Query already opened devices (`(index, device)`, in output order), a few
+    /// at a time on scoped threads
+    pub(crate) fn from_devices(nvml: &Nvml, devices: Vec<(u32, Result<Device, NvmlError>)>, query: &QueryOptions) -> Self {
+        let started = Instant::now();
+        next_sample();
+        let workers = std::thread::available_parallelism().map_or(1, |n| n.get()).min(MAX_QUERY_THREADS).min(devices.len());
+        let gpus = if workers <= 1 {
+            devices.into_iter().map(|(index, device)| query_gpu(device, index, query)).collect()
+        } else {
+            // Workers take the next device until none are left; results keep the input order
+            let queue = Mutex::new(devices.into_iter().enumerate());
+            let mut gpus: Vec<(usize, GpuStat)> = std::thread::scope(|scope| {
+                let handles: Vec<_> = (0..workers)
+                    .map(|_| {
+                        scope.spawn(|| {
+                            let mut done = Vec::new();
+                            loop {
+                                let next = queue.lock().unwrap_or_else(|e| e.into_inner()).next();
+                                let Some((pos, (index, device))) = next else {
+                                    return done;
+                                };
+                                done.push((pos, query_gpu(device, index, query)));
+                            }
+                        })
+                    })
+                    .collect();
+                handles.into_iter().flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e))).collect()
+            });
+            gpus.sort_by_key(|(pos, _)| *pos);
+            gpus.into_iter().map(|(_, gpu)| gpu).collect()
+        };
+        let mut stats = Self::driver_info(nvml, gpus);
+        stats.query_latency = Some(started.elapsed().as_secs_f64());
+        tracing::debug!(gpus = stats.gpus.len(), workers, latency = ?started.elapsed(), "query done");
+        stats
+    }
+
+    /// Async [`new_query`](Self::new_query) for use inside a tokio runtime: each
+    /// GPU is queried in its own task on the blocking pool, all awaited together
+    #[cfg(feature = "tokio")]
+    pub async fn new_query_async(
+        nvml: std::sync::Arc<Nvml>,
+        gpu_ids: Option<Vec<u32>>,
+        query: QueryOptions,
+    ) -> Result<Self, NvmlError> {
+        // A panicking NVML call panics here as it would in new_query
+        async fn join<T>(task: tokio::task::JoinHandle<T>) -> T {
+            task.await.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+        }
+        let started = Instant::now();
+        next_sample();
+        let gpus_to_query: Vec<u32> = match gpu_ids {
+            Some(ids) => ids,
+            None => {
+                let nvml = nvml.clone();
+                (0..join(tokio::task::spawn_blocking(move || nvml.device_count())).await?).collect()
+            }
+        };
+        let tasks: Vec<_> = gpus_to_query
+            .into_iter()
+            .map(|index| {
+                let (nvml, query) = (nvml.clone(), query.clone());
+                tokio::task::spawn_blocking(move || query_gpu(nvml.device_by_index(index), index, &query))
+            })
+            .collect();
+        let mut gpus = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            gpus.push(join(task).await);
+        }
+        let mut stats = join(tokio::task::spawn_blocking(move || Self::driver_info(&nvml, gpus))).await;
+        stats.query_latency = Some(started.elapsed().as_secs_f64());
+        Ok(stats)
+    }
+
+    /// A collection of `gpus` stamped with the host, the time and the driver versions
+    fn driver_info(nvml: &Nvml, gpus: Vec<GpuStat>) -> Self {
+        let cuda_version = nvml.sys_cuda_driver_version().traced("sys_cuda_driver_version").map(|v| {
+            format!(
+                "{}.{}",
+                nvml_wrapper::cuda_driver_version_major(v),
+                nvml_wrapper::cuda_driver_version_minor(v)
+            )
+        });
+        Self {
+            hostname: host_name(),
+            query_time: Utc::now(),
+            driver_version: nvml.sys_driver_version().traced("sys_driver_version"),
+            cuda_version,
+            nvml_version: nvml.sys_nvml_version().traced("sys_nvml_version"),
+            sample_period: None,
+            query_latency: None,
+            temperature_unit: TempUnit::Celsius,
+            memory_unit: MemUnit::MiB,
+            gpus,
+            users: Vec::new(),
+            by_process: Vec::new(),
+        }
+    }
+}
+
+/// Number of the current sample; cached host state older than that is stale
+static SAMPLE: AtomicU64 = AtomicU64::new(0);
+
+/// Start a new sample: host-side caches are rebuilt when next needed
+fn next_sample() {
+    SAMPLE.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Ngid and NStgid values of a process from its /proc/<pid>/status
+#[cfg(target_os = "linux")]
+fn namespace_pids(status: &str) -> Vec<u32> {
+    let mut pids = Vec::new();
+    for line in status.lines() {
+        if let Some(value) = line.strip_prefix("Ngid:") {
+            pids.extend(value.trim().parse::<u32>().ok().filter(|&v| v != 0));
+        } else if let Some(value) = line.strip_prefix("NStgid:") {
+            pids.extend(value.split_whitespace().filter_map(|part| part.parse::<u32>().ok()));
+        }
+    }
+    pids
+}
+
+/// Build mapping from Ngid/NStgid to host PID by scanning /proc.
+/// On some systems, NVML returns Ngid instead of the real PID; this mapping
+/// allows us to resolve to the actual process.
+#[cfg(target_os = "linux")]
+fn build_ngid_to_pid_mapping() -> HashMap<u32, u32> {
+    let mut mapping = HashMap::new();
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return mapping;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Ok(host_pid) = name.to_string_lossy().parse::<u32>() else {
+            continue;
+        };
+        let Ok(status) = std::fs::read_to_string(entry.path().join("status")) else {
+            continue;
+        };
+        for ns_pid in namespace_pids(&status) {
+            mapping.insert(ns_pid, host_pid);
+        }
+    }
+    tracing::debug!(entries = mapping.len(), "scanned /proc for namespace PIDs");
+    mapping
+}
+
+/// Host PID of the process NVML reports as `pid`, from the Ngid mapping.
+/// The /proc scan is redone at most once per sample, and only when `pid` is
+/// unknown or its entry no longer holds (the process exited, the PID was reused)
+#[cfg(target_os = "linux")]
+fn ngid_to_host_pid(pid: u32) -> Option<u32> {
+    static MAPPING: Mutex<Option<(u64, HashMap<u32, u32>)>> = Mutex::new(None);
+    let still_maps = |host_pid: u32| {
+        std::fs::read_to_string(format!("/proc/{}/status", host_pid)).is_ok_and(|s| namespace_pids(&s).contains(&pid))
+    };
+    let sample = SAMPLE.load(Ordering::Relaxed);
+    let mut mapping = MAPPING.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((built, map)) = mapping.as_ref() {
+        match map.get(&pid) {
+            Some(&host_pid) if still_maps(host_pid) => return Some(host_pid),
+            _ if *built == sample => return None,
+            _ => {}
+        }
+    }
+    let map = build_ngid_to_pid_mapping();
+    let host_pid = map.get(&pid).copied();
+    *mapping = Some((sample, map));
+    host_pid
+}
+
+/// Get process info from PID (username, command).
+/// When direct lookup fails (e.g. NVML returns Ngid instead of PID), retries
+/// using Ngid/NStgid-to-PID mapping.
+/// Returns (username, command, resolved_via_ngid_mapping, real_pid_when_mapped).
+fn get_process_info(pid: u32) -> (Option<String>, String, bool, Option<u32>) {
+    use std::path::Path;
+
+    let try_lookup = |p: u32| -> (Option<String>, String) {
+        let Some(info) = procinfo::lookup(p) else {
+            tracing::debug!(pid = p, "no such host process");
+            return (None, "?".to_string());
+        };
+        let command = match info.cmdline.first() {
+            Some(arg0) => Path::new(arg0)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("?")
+                .to_string(),
+            None => info.name,
+        };
+        (info.username, command)
+    };
+
+    #[cfg(target_os = "linux")]
+    {
+        // When mapping says pid -> real_pid and real_pid != pid, the NVML "pid" is Ngid/ns-pid.
+        // We must use the mapped result even if direct lookup "succeeded" (that would be a wrong
+        // process when Ngid happens to equal some unrelated host PID).
+        if let Some(rp) = ngid_to_host_pid(pid).filter(|&rp| rp != pid) {
+            let result = try_lookup(rp);
+            if result.1 != "?" {
+                return (result.0, result.1, true, Some(rp));
+            }
+        }
+    }
+
+    let (username, command) = try_lookup(pid);
+    (username, command, false, None)
+}
+
+/// Host-side view of a GPU process
+#[derive(Default)]
+struct HostStats {
+    full_command: Option<Vec<String>>,
+    cpu_percent: Option<f64>,
+    cpu_memory_usage: Option<u64>,
+    cpu_time: Option<f64>,
+    start_time: Option<DateTime<Utc>>,
+    elapsed: Option<u64>,
+    python_env: Option<String>,
+    cuda_visible_devices: Option<String>,
+    slurm_job: Option<SlurmJob>,
+}
+
+impl HostStats {
+    fn query(pid: u32) -> Self {
+        let Some(info) = procinfo::lookup(pid) else {
+            return HostStats::default();
+        };
+        // Lifetime average: CPU time used over time since the process started
+        let cpu_percent = (info.run_time > 0).then(|| info.cpu_time / info.run_time as f64 * 100.0);
+        HostStats {
+            python_env: python_env(&info),
+            cuda_visible_devices: info.env_var("CUDA_VISIBLE_DEVICES").map(str::to_string),
+            slurm_job: slurm::detect(pid, &info),
+            full_command: Some(info.cmdline).filter(|c| !c.is_empty()),
+            cpu_percent,
+            cpu_memory_usage: Some(info.memory),
+            cpu_time: Some(info.cpu_time),
+            start_time: DateTime::from_timestamp(info.start_time as i64, 0),
+            elapsed: Some(info.run_time),
+        }
+    }
+}
+
+/// Conda env or virtualenv of a Python process: from the interpreter path
+/// (".../envs/<name>/bin/python", "<venv>/bin/python" next to a pyvenv.cfg),
+/// falling back to the environment, which is only readable for our own processes
+fn python_env(info: &ProcInfo) -> Option<String> {
+    use std::path::Path;
+
+    let exe = info.exe.as_deref()?;
+    let name = exe.file_stem()?.to_str()?;
+    if !name.starts_with("python") {
+        return None;
+    }
+    // Unix: <prefix>/bin/python; Windows venvs: <prefix>\Scripts\python.exe
+    let prefix = exe.parent()?.parent()?;
+    let prefix_name = || prefix.file_name().and_then(|n| n.to_str()).map(str::to_string);
+    if prefix.parent().and_then(Path::file_name).is_some_and(|p| p == "envs") || prefix.join("pyvenv.cfg").exists() {
+        return prefix_name();
+    }
+    info.env_var("VIRTUAL_ENV")
+        .and_then(|v| Path::new(v).file_name().and_then(|n| n.to_str()).map(str::to_string))
+        .or_else(|| info.env_var("CONDA_DEFAULT_ENV").map(str::to_string))
+}
+
+/// Query NVML field values as integers; unsupported fields come back as None
+fn field_values(device: &Device, ids: &[u32]) -> Vec<Option<u64>> {
+    let field_ids: Vec<FieldId> = ids.iter().map(|&id| FieldId(id)).collect();
+    let samples = device.field_values_for(&field_ids).traced("field_values_for").unwrap_or_default();
+    ids.iter()
+        .map(|&id| {
+            samples
+                .iter()
+                .filter_map(|s| s.as_ref().ok())
+                .find(|s| s.field.0 == id)
+                .and_then(|s| match &s.value {
+                    Ok(SampleValue::U32(v)) => Some(*v as u64),
+                    Ok(SampleValue::U64(v)) => Some(*v),
+                    Ok(SampleValue::I64(v)) => u64::try_from(*v).ok(),
+                    Ok(SampleValue::F64(v)) => Some(*v as u64),
+                    Err(e) => {
+                        tracing::debug!(field = id, error = %e, "NVML field value missing");
+                        None
+                    }
+                })
+        })
+        .collect()
+}
+
+/// Current and pending driver model; only Windows has a choice
+#[cfg(target_os = "windows")]
+fn driver_models(device: &Device) -> (Option<String>, Option<String>) {
+    use nvml_wrapper::enum_wrappers::device::DriverModel;
+    let name = |m: DriverModel| match m {
+        DriverModel::WDDM => "WDDM".to_string(),
+        DriverModel::WDM => "TCC".to_string(),
+    };
+    match device.driver_model() {
+        Ok(state) => (Some(name(state.current)), Some(name(state.pending))),
+        // NVML_DRIVER_MCDM (2) postdates nvml-wrapper's enum
+        Err(NvmlError::UnexpectedVariant(2)) => (Some("MCDM".to_string()), None),
+        Err(_) => (None, None),
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn driver_models(_device: &Device) -> (Option<String>, Option<String>) {
+    (None, None)
+}
+
+enum Engine {
+    Jpg,
+    Ofa,
+}
+
+/// JPEG / OFA utilization; nvml-wrapper has no API for these engines
+fn engine_utilization(device: &Device, engine: Engine) -> Option<u32> {
+    let lib = mig::lib()?;
+    let f = match engine {
+        Engine::Jpg => lib.nvmlDeviceGetJpgUtilization.as_ref(),
+        Engine::Ofa => lib.nvmlDeviceGetOfaUtilization.as_ref(),
+    }
+    .ok()?;
+    let (mut util, mut period) = (0, 0);
+    // SAFETY: `device.handle()` is valid while `device` lives; out-pointers are locals
+    let ret = unsafe { f(device.handle(), &mut util, &mut period) };
+    (ret == nvml_wrapper_sys::bindings::nvmlReturn_enum_NVML_SUCCESS).then_some(util)
+}
+
+/// Name of this machine, for the header line
+pub(crate) fn host_name() -> String {
+    hostname::get()
+        .map(|h| h.to_string_lossy().to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Processes on `device`, merging compute and graphics
+/// None = NVML doesn't support process query (both APIs failed)
+/// Some(vec) = API succeeded, vec can be empty (no processes on GPU)
+fn query_processes(device: &Device) -> Option<Vec<GpuProcessInfo>> {
+    let comp_result = device.running_compute_processes();
+    let graphics_result = device.running_graphics_processes();
+    if let (Err(comp), Err(graphics)) = (&comp_result, &graphics_result) {
+        tracing::debug!(compute = %comp, graphics = %graphics, "process query failed");
+        return None; // Not Supported
+    }
+
+    let mut processes = Vec::new();
+    let mut seen_pids = HashSet::new();
+    let accounting = device.is_accounting_enabled().unwrap_or(false);
+    let memory_total = device.memory_info().traced("memory_info").map(|m| m.total).filter(|&t| t > 0);
+
+    // Newest utilization sample per PID from the last second of NVML's buffer
+    let since = (Utc::now().timestamp_micros().max(0) as u64).saturating_sub(PROCESS_UTIL_WINDOW_US);
+    let mut utils: HashMap<u32, ProcessUtilizationSample> = HashMap::new();
+    for sample in device.process_utilization_stats(since).unwrap_or_default() {
+        match utils.get(&sample.pid) {
+            Some(prev) if prev.timestamp >= sample.timestamp => {}
+            _ => {
+                utils.insert(sample.pid, sample);
+            }
+        }
+    }
+
+    for nv_process in comp_result
+        .unwrap_or_default()
+        .into_iter()
+        .chain(graphics_result.unwrap_or_default())
+    {
+        if !seen_pids.insert(nv_process.pid) {
+            continue;
+        }
+
+        let gpu_memory_bytes = match &nv_process.used_gpu_memory {
+            UsedGpuMemory::Used(bytes) => Some(*bytes),
+            UsedGpuMemory::Unavailable => None,
+        };
+
+        processes.push(GpuProcessInfo {
+            sm_util: utils.get(&nv_process.pid).map(|u| u.sm_util),
+            mem_util: utils.get(&nv_process.pid).map(|u| u.mem_util),
+            enc_util: utils.get(&nv_process.pid).map(|u| u.enc_util),
+            dec_util: utils.get(&nv_process.pid).map(|u| u.dec_util),
+            accounting: if accounting { ProcessAccounting::query(device, nv_process.pid) } else { None },
+            ..GpuProcessInfo::lookup(nv_process.pid, gpu_memory_bytes, memory_total)
+        });
+    }
+
+    // A process may exit between the NVML query and the host lookup, leaving a
+    // "?" entry. Ask NVML once more: drop what it no longer lists and retry the
+    // lookup for the rest (those usually live in a PID namespace we can't map)
+    if processes.iter().any(|p| p.command == "?") {
+        let listed: HashSet<u32> = device
+            .running_compute_processes()
+            .unwrap_or_default()
+            .into_iter()
+            .chain(device.running_graphics_processes().unwrap_or_default())
+            .map(|p| p.pid)
+            .collect();
+        processes.retain(|p| p.command != "?" || listed.contains(&p.pid));
+        for p in processes.iter_mut().filter(|p| p.command == "?") {
+            let (username, command, from_mapping, real_pid) = get_process_info(p.pid);
+            if command != "?" {
+                p.username = username;
+                p.command = command;
+                p.username_from_ngid_mapping = from_mapping;
+                p.real_pid = real_pid;
+            }
+        }
+    }
+    Some(processes)
+}
+
+impl VgpuInstance {
+    fn query(id: u32) -> Self {
+        VgpuInstance {
+            id,
+            vm_id: vgpu::vm_id(id),
+            uuid: vgpu::uuid(id),
+            vgpu_type: vgpu::type_name(id),
+            memory_used: vgpu::fb_usage(id).map(|b| b / MB),
+        }
+    }
+}
+
+impl MigInstance {
+    fn query(mig: &mig::MigDevice) -> Self {
+        let device = &mig.device;
+        let memory = device.memory_info().traced("memory_info");
+        MigInstance {
+            gpu_instance_id: mig.gpu_instance_id,
+            compute_instance_id: mig.compute_instance_id,
+            uuid: device.uuid().unwrap_or_default(),
+            profile: device.name().map(|n| mig::profile_name(&n)).unwrap_or_default(),
+            memory_used: memory.as_ref().map_or(0, |m| m.used / MB),
+            memory_total: memory.as_ref().map_or(0, |m| m.total / MB),
+            utilization: device.utilization_rates().traced("utilization_rates").map(|u| u.gpu),
+            processes: query_processes(device),
+        }
+    }
+}
+
+/// One GPU's stats, or a placeholder naming the error if it can't be queried
+fn query_gpu(device: Result<Device, NvmlError>, index: u32, query: &QueryOptions) -> GpuStat {
+    let _span = tracing::debug_span!("gpu", index).entered();
+    let (result, unsupported) = error::collect_unsupported(|| device.and_then(|d| get_gpu_info(&d, index, query)));
+    match result {
+        Ok(stat) => GpuStat { unsupported, ..stat },
+        Err(source) => {
+            tracing::debug!(error = %source, "GPU unavailable");
+            GpuStat {
+                index,
+                error: Some(DeviceError::from(&GpuStatError::Device { index, source })),
+                ..Default::default()
+            }
+        }
+    }
+}
+
+fn get_gpu_info(device: &Device, index: u32, query: &QueryOptions) -> Result<GpuStat, NvmlError> {
+
+    // Basic info
+    let name = device.name()?;
+    let uuid = device.uuid()?;
+    let compute_capability = device
+        .cuda_compute_capability()
+        .traced("cuda_compute_capability")
+        .map(|cc| format!("{}.{}", cc.major, cc.minor));
+    let architecture = device.architecture().traced("architecture").map(|a| a.to_string());
+
+    // Temperature (suppress not supported)
+    let temperature = device
+        .temperature(TemperatureSensor::Gpu)
+        .traced("temperature");
+
+    // Everything NVML exposes as a field value, in one round trip; fields the
+    // driver doesn't know are read with their dedicated calls below
+    let fields: HashMap<u32, u64> = BATCHED_FIELDS
+        .iter()
+        .zip(field_values(device, &BATCHED_FIELDS))
+        .filter_map(|(&id, v)| Some((id, v?)))
+        .collect();
+    let field = |id: u32| fields.get(&id).copied();
+
+    // Temperature thresholds
+    let threshold = |id: u32, t| field(id).map(|v| v as u32).or_else(|| device.temperature_threshold(t).traced("temperature_threshold"));
+    let temperature_slowdown = threshold(NVML_FI_DEV_TEMPERATURE_SLOWDOWN_TLIMIT, TemperatureThreshold::Slowdown);
+    let temperature_shutdown = threshold(NVML_FI_DEV_TEMPERATURE_SHUTDOWN_TLIMIT, TemperatureThreshold::Shutdown);
+    let temperature_gpu_max = threshold(NVML_FI_DEV_TEMPERATURE_GPU_MAX_TLIMIT, TemperatureThreshold::GpuMax);
+    let temperature_memory_max = threshold(NVML_FI_DEV_TEMPERATURE_MEM_MAX_TLIMIT, TemperatureThreshold::MemoryMax);
+
+    // Memory temperature (HBM cards only)
+    let temperature_memory = field(NVML_FI_DEV_MEMORY_TEMP).map(|t| t as u32);
+
+    // Fan speeds; cards without a fan count still report fan 0
+    let fan_count = device.num_fans().unwrap_or(1).max(1);
+    let fan_speeds: Vec<u32> = (0..fan_count).filter_map(|i| device.fan_speed(i).traced("fan_speed")).collect();
+    let fan_speed = fan_speeds.first().copied();
+
+    // Memory
+    let memory = device.memory_info()?;
+    let memory_used = memory.used / MB;
+    let memory_total = memory.total / MB;
+    let memory_free = memory.free / MB;
+    let memory_reserved = memory.reserved / MB;
+
+    // BAR1
+    let bar1 = device.bar1_memory_info().traced("bar1_memory_info");
+    let bar1_used = bar1.as_ref().map(|b| b.used / MB);
+    let bar1_total = bar1.as_ref().map(|b| b.total / MB);
+
+    let inventory = query.inventory.then(|| Inventory::query(device));
+
+    // Display
+    let display_attached = device.is_display_connected().traced("is_display_connected");
+    let display_active = device.is_display_active().traced("is_display_active");
+
+    let (driver_model, driver_model_pending) = driver_models(device);
+
+    // Compute and persistence mode
+    let compute_mode = device.compute_mode().traced("compute_mode").map(ComputeMode::from);
+    let persistence_mode = device.is_in_persistent_mode().traced("is_in_persistent_mode");
+
+    // Utilization
+    let utilization = device.utilization_rates().traced("utilization_rates").map(|u| u.gpu);
+
+    let utilization_enc = device.encoder_utilization().traced("encoder_utilization").map(|u| u.utilization);
+    let utilization_dec = device.decoder_utilization().traced("decoder_utilization").map(|u| u.utilization);
+    let utilization_jpg = engine_utilization(device, Engine::Jpg);
+    let utilization_ofa = engine_utilization(device, Engine::Ofa);
+    let encoder_stats = device.encoder_stats().traced("encoder_stats");
+    let encoder_sessions = encoder_stats.as_ref().map(|e| e.session_count);
+    let encoder_fps = encoder_stats.as_ref().map(|e| e.average_fps);
+    let encoder_latency = encoder_stats.as_ref().map(|e| e.average_latency);
+
+    // Power (NVML returns milliwatts)
+    let power_draw = device.power_usage().traced("power_usage").map(|p| p / 1000);
+    let watts = |id: u32| field(id).map(|mw| (mw / 1000) as u32);
+    let power_limit = watts(NVML_FI_DEV_POWER_CURRENT_LIMIT).or_else(|| device.enforced_power_limit().traced("enforced_power_limit").map(|p| p / 1000));
+    let energy_total = field(NVML_FI_DEV_TOTAL_ENERGY_CONSUMPTION).or_else(|| device.total_energy_consumption().traced("total_energy_consumption"));
+
+    // Time spent capped by power / thermal limits (the fields count μs, the calls ns)
+    let violation = |id: u32, policy| {
+        field(id).map(|us| us * 1000).or_else(|| device.violation_status(policy).traced("violation_status").map(|v| v.violation_time))
+    };
+    let violation_power = violation(NVML_FI_DEV_PERF_POLICY_POWER, PerformancePolicy::Power);
+    let violation_thermal = violation(NVML_FI_DEV_PERF_POLICY_THERMAL, PerformancePolicy::Thermal);
+    let power_limit_default = watts(NVML_FI_DEV_POWER_DEFAULT_LIMIT)
+        .or_else(|| device.power_management_limit_default().traced("power_management_limit_default").map(|p| p / 1000));
+    let (power_limit_min, power_limit_max) = match (watts(NVML_FI_DEV_POWER_MIN_LIMIT), watts(NVML_FI_DEV_POWER_MAX_LIMIT)) {
+        (Some(min), Some(max)) => (Some(min), Some(max)),
+        _ => {
+            let constraints = device.power_management_limit_constraints().traced("power_management_limit_constraints");
+            (constraints.as_ref().map(|c| c.min_limit / 1000), constraints.as_ref().map(|c| c.max_limit / 1000))
+        }
+    };
+
+    // Clocks (MHz)
+    let clock_sm = device.clock_info(Clock::SM).traced("clock_info");
+    let clock_mem = device.clock_info(Clock::Memory).traced("clock_info");
+    let clock_graphics = device.clock_info(Clock::Graphics).traced("clock_info");
+    let clock_sm_max = device.max_clock_info(Clock::SM).traced("max_clock_info");
+    let clock_mem_max = device.max_clock_info(Clock::Memory).traced("max_clock_info");
+    let clock_graphics_max = device.max_clock_info(Clock::Graphics).traced("max_clock_info");
+
+    // Throttle reasons
+    let throttle_reasons = device.current_throttle_reasons().traced("current_throttle_reasons").map(|r| r.bits());
+
+    // PCIe link and throughput
+    let pcie_gen = device.current_pcie_link_gen().traced("current_pcie_link_gen");
+    let pcie_gen_max = device.max_pcie_link_gen().traced("max_pcie_link_gen");
+    let pcie_width = device.current_pcie_link_width().traced("current_pcie_link_width");
+    let pcie_width_max = device.max_pcie_link_width().traced("max_pcie_link_width");
+    let (pcie_tx, pcie_rx) = if query.pcie {
+        (
+            device.pcie_throughput(PcieUtilCounter::Send).traced("pcie_throughput"),
+            device.pcie_throughput(PcieUtilCounter::Receive).traced("pcie_throughput"),
+        )
+    } else {
+        (None, None)
+    };
+
+    // Retired pages (pre-Ampere) and row remapping (Ampere+)
+    let retired = |cause| device.retired_pages(cause).traced("retired_pages").map(|p| p.len() as u32);
+    let retired_pages_sbe = retired(RetirementCause::MultipleSingleBitEccErrors);
+    let retired_pages_dbe = retired(RetirementCause::DoubleBitEccError);
+    let retired_pages_pending = device.are_pages_pending_retired().traced("are_pages_pending_retired");
+    let remapped_rows_correctable = field(NVML_FI_DEV_REMAPPED_COR).map(|v| v as u32);
+    let remapped_rows_uncorrectable = field(NVML_FI_DEV_REMAPPED_UNC).map(|v| v as u32);
+    let remapping_pending = field(NVML_FI_DEV_REMAPPED_PENDING).map(|v| v != 0);
+    let remapping_failed = field(NVML_FI_DEV_REMAPPED_FAILURE).map(|v| v != 0);
+
+    // ECC mode and pending changes that only apply after a reset
+    let ecc = device.is_ecc_enabled().traced("is_ecc_enabled");
+    let ecc_mode = ecc.as_ref().map(|e| e.currently_enabled);
+    let ecc_mode_pending = ecc.as_ref().map(|e| e.pending_enabled != e.currently_enabled);
+    let mig_mode_pending = device.mig_mode().traced("mig_mode").map(|m| m.pending != m.current);
+    let reset_required: Vec<String> = [
+        ("retired_pages", retired_pages_pending),
+        ("row_remap", remapping_pending),
+        ("ecc_mode", ecc_mode_pending),
+        ("mig_mode", mig_mode_pending),
+    ]
+    .into_iter()
+    .filter(|(_, pending)| *pending == Some(true))
+    .map(|(reason, _)| reason.to_string())
+    .collect();
+
+    let processes = query_processes(device);
+
+    // Virtualization; only a vGPU host can see the instances it runs
+    let virtualization_mode = device.virtualization_mode().traced("virtualization_mode");
+    let vgpu_instances = if matches!(virtualization_mode, Some(GpuVirtualizationMode::HostVgpu)) {
+        vgpu::active(device).into_iter().map(VgpuInstance::query).collect()
+    } else {
+        Vec::new()
+    };
+    let virtualization_mode = virtualization_mode.as_ref().map(|m| vgpu::mode_label(m).to_string());
+
+    // MIG instances, each with its own memory and processes
+    let mig_mode = mig::is_enabled(device);
+    let mig_instances = if mig_mode == Some(true) {
+        mig::devices(device.nvml(), device).iter().map(MigInstance::query).collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(GpuStat {
+        index,
+        name,
+        uuid,
+        compute_capability,
+        architecture,
+        temperature,
+        temperature_memory,
+        temperature_slowdown,
+        temperature_shutdown,
+        temperature_gpu_max,
+        temperature_memory_max,
+        fan_speed,
+        fan_speeds,
+        utilization,
+        utilization_enc,
+        utilization_dec,
+        utilization_jpg,
+        utilization_ofa,
+        encoder_sessions,
+        encoder_fps,
+        encoder_latency,
+        profiling: None,
+        power_draw,
+        power_limit,
+        power_limit_default,
+        power_limit_min,
+        power_limit_max,
+        energy_total,
+        energy_delta: None,
+        clock_sm,
+        clock_mem,
+        clock_graphics,
+        clock_sm_max,
+        clock_mem_max,
+        clock_graphics_max,
+        throttle_reasons,
+        violation_power,
+        violation_thermal,
+        violation_power_pct: None,
+        violation_thermal_pct: None,
+        pcie_gen,
+        pcie_gen_max,
+        pcie_width,
+        pcie_width_max,
+        pcie_tx,
+        pcie_rx,
+        retired_pages_sbe,
+        retired_pages_dbe,
+        retired_pages_pending,
+        remapped_rows_correctable,
+        remapped_rows_uncorrectable,
+        remapping_pending,
+        remapping_failed,
+        ecc_mode,
+        ecc_mode_pending,
+        reset_required,
+        memory_used,
+        memory_total,
+        memory_free,
+        memory_reserved,
+        memory_used_bytes: memory.used,
+        memory_total_bytes: memory.total,
+        memory_used_gib: None,
+        memory_total_gib: None,
+        memory_percent: None,
+        bar1_used,
+        bar1_total,
+        compute_mode,
+        persistence_mode,
+        display_attached,
+        display_active,
+        driver_model,
+        driver_model_pending,
+        inventory,
+        processes,
+        mig_mode,
+        mig_instances,
+        virtualization_mode,
+        vgpu_instances,
+        available: true,
+        error: None,
+        unsupported: Vec::new(),
+        extra: BTreeMap::new(),
+    })
+}
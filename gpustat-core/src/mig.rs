@@ -0,0 +1,88 @@
+//! Multi-Instance GPU (MIG) device enumeration
+//!
+//! nvml-wrapper exposes `mig_mode()` but not the MIG device handles, so the
+//! handful of calls needed here go through the raw bindings. The library is
+//! opened a second time; `dlopen` hands back the instance `Nvml::init` already
+//! loaded and initialized.
+
+use nvml_wrapper::{Device, Nvml};
+use nvml_wrapper_sys::bindings::{nvmlDevice_t, nvmlReturn_enum_NVML_SUCCESS, nvmlReturn_t, NvmlLib};
+use std::os::raw::c_uint;
+use std::sync::OnceLock;
+
+#[cfg(target_os = "windows")]
+const NVML_LIB: &str = "nvml.dll";
+#[cfg(not(target_os = "windows"))]
+const NVML_LIB: &str = "libnvidia-ml.so.1";
+
+/// The raw NVML bindings, also used for the other calls nvml-wrapper lacks
+pub(crate) fn lib() -> Option<&'static NvmlLib> {
+    static LIB: OnceLock<Option<NvmlLib>> = OnceLock::new();
+    // SAFETY: loading the same NVML library nvml-wrapper uses; symbols are
+    // resolved lazily and checked before each call.
+    LIB.get_or_init(|| unsafe { NvmlLib::new(NVML_LIB).ok() }).as_ref()
+}
+
+/// A MIG device handle with its GPU / compute instance IDs
+pub struct MigDevice<'nvml> {
+    pub device: Device<'nvml>,
+    pub gpu_instance_id: Option<u32>,
+    pub compute_instance_id: Option<u32>,
+}
+
+/// Whether MIG mode is currently enabled (None when the GPU has no MIG support)
+pub fn is_enabled(device: &Device) -> Option<bool> {
+    device.mig_mode().ok().map(|m| m.current == 1)
+}
+
+/// All MIG devices carved out of `device`, in handle order
+pub fn devices<'nvml>(nvml: &'nvml Nvml, device: &Device) -> Vec<MigDevice<'nvml>> {
+    let Some(lib) = lib() else {
+        return Vec::new();
+    };
+    let (Ok(max_count), Ok(get_handle)) = (
+        lib.nvmlDeviceGetMaxMigDeviceCount.as_ref(),
+        lib.nvmlDeviceGetMigDeviceHandleByIndex.as_ref(),
+    ) else {
+        return Vec::new();
+    };
+
+    // SAFETY: `device.handle()` is a valid handle for the lifetime of `nvml`;
+    // out-pointers are valid locals.
+    unsafe {
+        let parent = device.handle();
+        let mut count: c_uint = 0;
+        if max_count(parent, &mut count) != nvmlReturn_enum_NVML_SUCCESS {
+            return Vec::new();
+        }
+        (0..count)
+            .filter_map(|i| {
+                let mut handle: nvmlDevice_t = std::ptr::null_mut();
+                // Slots without an instance return NOT_FOUND
+                if get_handle(parent, i, &mut handle) != nvmlReturn_enum_NVML_SUCCESS {
+                    return None;
+                }
+                Some(MigDevice {
+                    gpu_instance_id: instance_id(lib.nvmlDeviceGetGpuInstanceId.as_ref().ok(), handle),
+                    compute_instance_id: instance_id(lib.nvmlDeviceGetComputeInstanceId.as_ref().ok(), handle),
+                    device: Device::new(handle, nvml),
+                })
+            })
+            .collect()
+    }
+}
+
+type IdFn = unsafe extern "C" fn(nvmlDevice_t, *mut c_uint) -> nvmlReturn_t;
+
+unsafe fn instance_id(f: Option<&IdFn>, handle: nvmlDevice_t) -> Option<u32> {
+    let mut id: c_uint = 0;
+    (f?(handle, &mut id) == nvmlReturn_enum_NVML_SUCCESS).then_some(id)
+}
+
+/// Profile part of a MIG device name, e.g. "NVIDIA A100-SXM4-40GB MIG 1g.5gb" -> "1g.5gb"
+pub fn profile_name(name: &str) -> String {
+    match name.rfind("MIG ") {
+        Some(i) => name[i + 4..].trim().to_string(),
+        None => name.to_string(),
+    }
+}
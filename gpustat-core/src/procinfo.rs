@@ -0,0 +1,93 @@
+//! Host process lookup through sysinfo, so process names and stats resolve on
+//! Linux, Windows, macOS and FreeBSD alike
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, Signal, System, Uid, UpdateKind, Users};
+
+/// What gpustat needs to know about a host process
+pub struct ProcInfo {
+    pub username: Option<String>,
+    /// Executable name, e.g. "python"
+    pub name: String,
+    pub cmdline: Vec<String>,
+    pub exe: Option<PathBuf>,
+    /// "KEY=value" entries; empty unless the process is ours (or we are root)
+    pub environ: Vec<String>,
+    /// Resident memory (bytes)
+    pub memory: u64,
+    /// User + system CPU time (s)
+    pub cpu_time: f64,
+    /// Start time (seconds since the epoch) and seconds running
+    pub start_time: u64,
+    pub run_time: u64,
+}
+
+impl ProcInfo {
+    /// Value of environment variable `key`, if readable
+    pub fn env_var(&self, key: &str) -> Option<&str> {
+        self.environ.iter().find_map(|kv| kv.strip_prefix(key)?.strip_prefix('='))
+    }
+}
+
+/// Name of `uid`, cached; the user database is re-read only for uids not seen before
+fn username(uid: &Uid) -> Option<String> {
+    static NAMES: Mutex<Option<HashMap<Uid, Option<String>>>> = Mutex::new(None);
+    let mut names = NAMES.lock().unwrap_or_else(|e| e.into_inner());
+    let names = names.get_or_insert_with(HashMap::new);
+    if let Some(name) = names.get(uid) {
+        return name.clone();
+    }
+    let users = Users::new_with_refreshed_list();
+    for user in users.list() {
+        names.insert(user.id().clone(), Some(user.name().to_string()));
+    }
+    // Remember uids without an account too (e.g. from another container)
+    names.entry(uid.clone()).or_insert(None).clone()
+}
+
+/// Look up `pid`; None when it does not exist (or has already exited)
+pub fn lookup(pid: u32) -> Option<ProcInfo> {
+    let pid = Pid::from_u32(pid);
+    let mut sys = System::new();
+    let refresh = ProcessRefreshKind::nothing()
+        .with_cpu()
+        .with_memory()
+        .with_user(UpdateKind::Always)
+        .with_cmd(UpdateKind::Always)
+        .with_exe(UpdateKind::Always)
+        .with_environ(UpdateKind::Always);
+    sys.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), false, refresh);
+    let process = sys.process(pid)?;
+    let to_string = |s: &std::ffi::OsStr| s.to_string_lossy().into_owned();
+    Some(ProcInfo {
+        username: process.user_id().and_then(username),
+        name: to_string(process.name()),
+        cmdline: process.cmd().iter().map(|a| to_string(a)).collect(),
+        exe: process.exe().map(PathBuf::from),
+        environ: process.environ().iter().map(|e| to_string(e)).collect(),
+        memory: process.memory(),
+        cpu_time: process.accumulated_cpu_time() as f64 / 1000.0,
+        start_time: process.start_time(),
+        run_time: process.run_time(),
+    })
+}
+
+/// Name of the user running gpustat
+pub fn current_username() -> Option<String> {
+    lookup(std::process::id())?.username
+}
+
+/// Send `signal` to `pid`. Err says why nothing was sent
+pub fn send_signal(pid: u32, signal: Signal) -> Result<(), String> {
+    let spid = Pid::from_u32(pid);
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessesToUpdate::Some(&[spid]), false, ProcessRefreshKind::nothing());
+    let process = sys.process(spid).ok_or_else(|| format!("no process {}", pid))?;
+    match process.kill_with(signal) {
+        Some(true) => Ok(()),
+        Some(false) => Err(format!("could not signal {}: {}", pid, std::io::Error::last_os_error())),
+        None => Err(format!("{:?} is not supported on this platform", signal)),
+    }
+}
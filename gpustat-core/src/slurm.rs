@@ -0,0 +1,65 @@
+//! Slurm job detection for GPU processes
+//!
+//! slurmd's cgroup plugin puts every task under `.../job_<id>/step_<n>/...`;
+//! `SLURM_JOB_ID` / `SLURM_JOB_NAME` in the task environment are the fallback
+//! (and the only source of the job name), readable for our own processes or as root.
+
+use crate::procinfo::ProcInfo;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlurmJob {
+    pub id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Job ID from a /proc/<pid>/cgroup listing
+fn job_from_cgroup(cgroup: &str) -> Option<u64> {
+    cgroup
+        .lines()
+        .filter_map(|line| line.splitn(3, ':').nth(2))
+        .flat_map(|path| path.split('/'))
+        .find_map(|part| part.strip_prefix("job_")?.parse().ok())
+}
+
+#[cfg(target_os = "linux")]
+fn cgroup_job(pid: u32) -> Option<u64> {
+    job_from_cgroup(&std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cgroup_job(_pid: u32) -> Option<u64> {
+    None
+}
+
+/// The Slurm job `info` (the process `pid`) belongs to, if any
+pub fn detect(pid: u32, info: &ProcInfo) -> Option<SlurmJob> {
+    let id = cgroup_job(pid).or_else(|| info.env_var("SLURM_JOB_ID")?.parse().ok())?;
+    Some(SlurmJob {
+        id,
+        name: info.env_var("SLURM_JOB_NAME").map(str::to_string),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cgroup_v1() {
+        let cgroup = "11:devices:/slurm/uid_1000/job_4242/step_0/task_0\n4:memory:/slurm/uid_1000/job_4242/step_0\n";
+        assert_eq!(job_from_cgroup(cgroup), Some(4242));
+    }
+
+    #[test]
+    fn cgroup_v2() {
+        assert_eq!(job_from_cgroup("0::/system.slice/slurmstepd.scope/job_17/step_batch/user/task_0\n"), Some(17));
+    }
+
+    #[test]
+    fn no_job() {
+        assert_eq!(job_from_cgroup("0::/user.slice/user-1000.slice/session-3.scope\n"), None);
+        assert_eq!(job_from_cgroup("0::/slurm/job_x/step_0\n"), None);
+    }
+}
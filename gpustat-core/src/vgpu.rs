@@ -0,0 +1,83 @@
+//! vGPU host-side instance details
+//!
+//! nvml-wrapper lists the active vGPU instance IDs on a host but nothing
+//! about them, so the per-instance queries go through the raw bindings
+//! opened in [`crate::mig`].
+
+use crate::mig;
+use nvml_wrapper::enum_wrappers::device::GpuVirtualizationMode;
+use nvml_wrapper::Device;
+use nvml_wrapper_sys::bindings::{
+    nvmlReturn_enum_NVML_SUCCESS, nvmlVgpuInstance_t, nvmlVgpuTypeId_t, nvmlVgpuVmIdType_t,
+    NVML_DEVICE_UUID_BUFFER_SIZE, NVML_VGPU_NAME_BUFFER_SIZE,
+};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_uint, c_ulonglong};
+
+/// Short label for a virtualization mode, as nvidia-smi prints it
+pub fn mode_label(mode: &GpuVirtualizationMode) -> &'static str {
+    match mode {
+        GpuVirtualizationMode::Bare => "None",
+        GpuVirtualizationMode::PassThrough => "Pass-Through",
+        GpuVirtualizationMode::Vgpu => "VGPU",
+        GpuVirtualizationMode::HostVgpu => "Host VGPU",
+        GpuVirtualizationMode::HostVsga => "Host VSGA",
+    }
+}
+
+/// IDs of the vGPU instances running on a host GPU
+pub fn active(device: &Device) -> Vec<nvmlVgpuInstance_t> {
+    device.active_vgpus().unwrap_or_default()
+}
+
+fn c_string(buf: &[c_char]) -> Option<String> {
+    // SAFETY: NVML NUL-terminates within the buffer it was given
+    let s = unsafe { CStr::from_ptr(buf.as_ptr()) }.to_string_lossy().into_owned();
+    (!s.is_empty()).then_some(s)
+}
+
+/// Name or UUID of the VM the instance is attached to
+pub fn vm_id(instance: nvmlVgpuInstance_t) -> Option<String> {
+    let f = mig::lib()?.nvmlVgpuInstanceGetVmID.as_ref().ok()?;
+    let mut buf = [0 as c_char; NVML_DEVICE_UUID_BUFFER_SIZE as usize];
+    let mut kind: nvmlVgpuVmIdType_t = 0;
+    // SAFETY: the buffer length is passed alongside it
+    let ret = unsafe { f(instance, buf.as_mut_ptr(), buf.len() as c_uint, &mut kind) };
+    (ret == nvmlReturn_enum_NVML_SUCCESS).then(|| c_string(&buf)).flatten()
+}
+
+/// UUID of the vGPU instance itself
+pub fn uuid(instance: nvmlVgpuInstance_t) -> Option<String> {
+    let f = mig::lib()?.nvmlVgpuInstanceGetUUID.as_ref().ok()?;
+    let mut buf = [0 as c_char; NVML_DEVICE_UUID_BUFFER_SIZE as usize];
+    // SAFETY: the buffer length is passed alongside it
+    let ret = unsafe { f(instance, buf.as_mut_ptr(), buf.len() as c_uint) };
+    (ret == nvmlReturn_enum_NVML_SUCCESS).then(|| c_string(&buf)).flatten()
+}
+
+/// vGPU type name, e.g. "GRID A100-4C"
+pub fn type_name(instance: nvmlVgpuInstance_t) -> Option<String> {
+    let lib = mig::lib()?;
+    let get_type = lib.nvmlVgpuInstanceGetType.as_ref().ok()?;
+    let get_name = lib.nvmlVgpuTypeGetName.as_ref().ok()?;
+    let mut type_id: nvmlVgpuTypeId_t = 0;
+    let mut buf = [0 as c_char; NVML_VGPU_NAME_BUFFER_SIZE as usize];
+    let mut size = buf.len() as c_uint;
+    // SAFETY: out-pointers are valid locals; `size` carries the buffer length
+    unsafe {
+        if get_type(instance, &mut type_id) != nvmlReturn_enum_NVML_SUCCESS
+            || get_name(type_id, buf.as_mut_ptr(), &mut size) != nvmlReturn_enum_NVML_SUCCESS
+        {
+            return None;
+        }
+    }
+    c_string(&buf)
+}
+
+/// Framebuffer used by the instance, in bytes
+pub fn fb_usage(instance: nvmlVgpuInstance_t) -> Option<u64> {
+    let f = mig::lib()?.nvmlVgpuInstanceGetFbUsage.as_ref().ok()?;
+    let mut used: c_ulonglong = 0;
+    // SAFETY: out-pointer is a valid local
+    (unsafe { f(instance, &mut used) } == nvmlReturn_enum_NVML_SUCCESS).then_some(used)
+}
@@ -0,0 +1,314 @@
+//! Cluster dashboard (`gpustat cluster NODE...`): many `gpustat serve` nodes at once
+//!
+//! Each node is a box with one heat cell per GPU for utilization and one for
+//! memory; Enter opens the selected node with the usual GPU panels and process
+//! table. Nodes are polled over HTTP (`/json`) on a background thread, so a
+//! slow or dead node never stalls the screen.
+
+use crate::tui;
+use gpustat_core::GpuStatCollection;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, TableState};
+use ratatui::Frame;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Port of `gpustat serve` when a node is given without one
+const DEFAULT_PORT: u16 = 9101;
+/// Give up on a node after this long; it shows as unreachable until it answers
+const FETCH_TIMEOUT: Duration = Duration::from_secs(3);
+/// Height of a node box: borders, util row, memory row
+const NODE_HEIGHT: u16 = 4;
+/// Width of one heat cell ("87 ")
+const CELL_WIDTH: u16 = 3;
+
+/// `/json` URL of `node`: "host", "host:port" or a full http(s) URL
+pub fn node_url(node: &str) -> String {
+    let base = if node.contains("://") {
+        node.to_string()
+    } else if node.contains(':') {
+        format!("http://{}", node)
+    } else {
+        format!("http://{}:{}", node, DEFAULT_PORT)
+    };
+    format!("{}/json", base.trim_end_matches('/'))
+}
+
+/// Nodes listed in `path`, one per line; blank lines and `#` comments skipped
+pub fn read_hosts(path: &Path) -> std::io::Result<Vec<String>> {
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .map(|l| l.split('#').next().unwrap_or("").trim())
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+struct Node {
+    name: String,
+    stats: Option<GpuStatCollection>,
+    error: Option<String>,
+}
+
+struct App {
+    nodes: Vec<Node>,
+    selected: usize,
+    /// Columns of the node grid in the last frame, for Up/Down
+    columns: usize,
+    /// Node opened with Enter
+    detail: Option<usize>,
+    table: TableState,
+}
+
+impl App {
+    fn move_selection(&mut self, delta: isize) {
+        if !self.nodes.is_empty() {
+            self.selected = (self.selected as isize + delta).clamp(0, self.nodes.len() as isize - 1) as usize;
+        }
+    }
+}
+
+/// Query every node in parallel each `interval` and send `(node, result)` back
+fn spawn_poller(urls: Vec<String>, token: Option<String>, interval: Duration) -> mpsc::Receiver<(usize, Result<GpuStatCollection, String>)> {
+    let (tx, rx) = mpsc::channel();
+    let agent: ureq::Agent = ureq::Agent::config_builder().timeout_global(Some(FETCH_TIMEOUT)).build().into();
+    std::thread::spawn(move || loop {
+        let started = std::time::Instant::now();
+        std::thread::scope(|scope| {
+            for (i, url) in urls.iter().enumerate() {
+                let (tx, agent, token) = (tx.clone(), agent.clone(), token.as_deref());
+                scope.spawn(move || {
+                    let mut request = agent.get(url);
+                    if let Some(token) = token {
+                        request = request.header("Authorization", format!("Bearer {}", token));
+                    }
+                    let result = request
+                        .call()
+                        .and_then(|mut r| r.body_mut().read_to_string())
+                        .map_err(|e| e.to_string())
+                        .and_then(|body| serde_json::from_str(&body).map_err(|e| e.to_string()));
+                    let _ = tx.send((i, result));
+                });
+            }
+        });
+        std::thread::sleep(interval.saturating_sub(started.elapsed()));
+    });
+    rx
+}
+
+/// Run the dashboard until the user quits with `q`
+pub fn run(nodes: &[String], token: Option<&str>, interval: Duration) -> Result<(), Box<dyn std::error::Error>> {
+    let updates = spawn_poller(nodes.iter().map(|n| node_url(n)).collect(), token.map(str::to_string), interval);
+    let mut app = App {
+        nodes: nodes.iter().map(|n| Node { name: n.clone(), stats: None, error: None }).collect(),
+        selected: 0,
+        columns: 1,
+        detail: None,
+        table: TableState::default(),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        loop {
+            for (i, result) in updates.try_iter() {
+                let node = &mut app.nodes[i];
+                match result {
+                    Ok(stats) => {
+                        node.stats = Some(stats);
+                        node.error = None;
+                    }
+                    // Keep the last snapshot on screen, marked stale by the error
+                    Err(e) => node.error = Some(e),
+                }
+            }
+
+            terminal.draw(|f| draw(f, &mut app))?;
+
+            if event::poll(Duration::from_millis(200))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    let columns = app.columns as isize;
+                    match (key.code, app.detail) {
+                        (KeyCode::Char('q'), _) => return Ok(()),
+                        (KeyCode::Esc, None) => return Ok(()),
+                        (KeyCode::Esc | KeyCode::Backspace, Some(_)) => app.detail = None,
+                        (KeyCode::Enter, None) => {
+                            app.detail = Some(app.selected);
+                            app.table = TableState::default();
+                        }
+                        (KeyCode::Right | KeyCode::Char('l'), None) => app.move_selection(1),
+                        (KeyCode::Left | KeyCode::Char('h'), None) => app.move_selection(-1),
+                        (KeyCode::Down | KeyCode::Char('j'), None) => app.move_selection(columns),
+                        (KeyCode::Up | KeyCode::Char('k'), None) => app.move_selection(-columns),
+                        (KeyCode::Down | KeyCode::Char('j'), Some(_)) => app.table.select_next(),
+                        (KeyCode::Up | KeyCode::Char('k'), Some(_)) => app.table.select_previous(),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    })();
+    ratatui::restore();
+    result
+}
+
+fn draw(f: &mut Frame, app: &mut App) {
+    let [header_area, body_area, footer_area] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(NODE_HEIGHT), Constraint::Length(1)]).areas(f.area());
+
+    match app.detail {
+        Some(i) => {
+            draw_detail(f, &app.nodes[i], &mut app.table, header_area, body_area);
+            f.render_widget(
+                Paragraph::new("q: quit  Esc: back to cluster  ↑/↓: select process").style(Style::default().fg(Color::DarkGray)),
+                footer_area,
+            );
+        }
+        None => {
+            f.render_widget(Paragraph::new(summary_line(&app.nodes)), header_area);
+            draw_grid(f, app, body_area);
+            f.render_widget(
+                Paragraph::new("q: quit  arrows: select node  Enter: open node").style(Style::default().fg(Color::DarkGray)),
+                footer_area,
+            );
+        }
+    }
+}
+
+/// "12 nodes (1 down)  96 GPUs  util 43 %  mem 2.1 / 7.5 TiB"
+fn summary_line(nodes: &[Node]) -> Line<'static> {
+    let gpus: Vec<_> = nodes.iter().filter_map(|n| n.stats.as_ref()).flat_map(|s| &s.gpus).collect();
+    let down = nodes.iter().filter(|n| n.error.is_some() || n.stats.is_none()).count();
+    let utils: Vec<u32> = gpus.iter().filter_map(|g| g.utilization).collect();
+    let avg = if utils.is_empty() { 0 } else { utils.iter().sum::<u32>() / utils.len() as u32 };
+    let tib = |bytes: u64| bytes as f64 / (1u64 << 40) as f64;
+    let used = gpus.iter().map(|g| g.memory_used_bytes).sum();
+    let total = gpus.iter().map(|g| g.memory_total_bytes).sum();
+    let mut spans = vec![Span::styled(format!("{} nodes", nodes.len()), Style::default().add_modifier(Modifier::BOLD))];
+    if down > 0 {
+        spans.push(Span::styled(format!(" ({} down)", down), Style::default().fg(Color::Red)));
+    }
+    spans.push(Span::raw(format!(
+        "  {} GPUs  util {} %  mem {:.1} / {:.1} TiB",
+        gpus.len(),
+        avg,
+        tib(used),
+        tib(total)
+    )));
+    Line::from(spans)
+}
+
+/// Background of a heat cell at `percent`
+fn heat(percent: Option<f64>) -> Color {
+    match percent {
+        None => Color::DarkGray,
+        Some(p) if p < 10.0 => Color::Blue,
+        Some(p) if p < 40.0 => Color::Green,
+        Some(p) if p < 75.0 => Color::Yellow,
+        Some(_) => Color::Red,
+    }
+}
+
+fn heat_cells(label: &str, values: impl Iterator<Item = Option<f64>>) -> Line<'static> {
+    let mut spans = vec![Span::styled(format!("{} ", label), Style::default().fg(Color::DarkGray))];
+    for v in values {
+        let text = v.map_or("??".to_string(), |v| format!("{:>2.0}", v.min(99.0)));
+        spans.push(Span::styled(text, Style::default().fg(Color::Black).bg(heat(v))));
+        spans.push(Span::raw(" "));
+    }
+    Line::from(spans)
+}
+
+fn draw_grid(f: &mut Frame, app: &mut App, area: Rect) {
+    let max_gpus = app.nodes.iter().filter_map(|n| n.stats.as_ref()).map(|s| s.gpus.len()).max().unwrap_or(0) as u16;
+    let longest_name = app.nodes.iter().map(|n| n.name.len()).max().unwrap_or(0) as u16;
+    // Borders, the row label, and a cell per GPU; wide enough for the title
+    let width = (4 + max_gpus * CELL_WIDTH).max(longest_name + 4).max(16);
+    app.columns = (area.width / width).max(1) as usize;
+    let rows = app.nodes.len().div_ceil(app.columns);
+    let visible_rows = (area.height / NODE_HEIGHT).max(1) as usize;
+    // Scroll so the selected node stays on screen
+    let first_row = (app.selected / app.columns).saturating_sub(visible_rows - 1);
+
+    for row in first_row..rows.min(first_row + visible_rows) {
+        for col in 0..app.columns {
+            let i = row * app.columns + col;
+            let Some(node) = app.nodes.get(i) else {
+                break;
+            };
+            let cell = Rect {
+                x: area.x + col as u16 * width,
+                y: area.y + (row - first_row) as u16 * NODE_HEIGHT,
+                width,
+                height: NODE_HEIGHT,
+            };
+            draw_node(f, node, i == app.selected, cell);
+        }
+    }
+}
+
+fn draw_node(f: &mut Frame, node: &Node, selected: bool, area: Rect) {
+    let title_style = match (&node.error, &node.stats) {
+        (Some(_), _) | (None, None) => Style::default().fg(Color::Red),
+        _ => Style::default().fg(Color::Blue),
+    };
+    let border_style = if selected {
+        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style)
+        .title(Span::styled(node.name.clone(), title_style));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let lines = match &node.stats {
+        Some(stats) => vec![
+            heat_cells("U", stats.gpus.iter().map(|g| g.utilization.map(f64::from))),
+            heat_cells("M", stats.gpus.iter().map(|g| g.available.then(|| g.memory_ratio() * 100.0))),
+        ],
+        None if node.error.is_some() => vec![Line::styled("unreachable", Style::default().fg(Color::Red))],
+        None => vec![Line::styled("connecting...", Style::default().fg(Color::DarkGray))],
+    };
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+fn draw_detail(f: &mut Frame, node: &Node, table: &mut TableState, header_area: Rect, area: Rect) {
+    let mut header = vec![Span::styled(node.name.clone(), Style::default().add_modifier(Modifier::BOLD))];
+    if let Some(stats) = &node.stats {
+        header.push(Span::raw(format!("  {}  ", stats.query_time.format("%Y-%m-%d %H:%M:%S"))));
+        header.push(Span::styled(
+            stats.driver_version.clone().unwrap_or_else(|| "N/A".to_string()),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if let Some(e) = &node.error {
+        header.push(Span::styled(format!("  {}", e), Style::default().fg(Color::Red)));
+    }
+    f.render_widget(Paragraph::new(Line::from(header)), header_area);
+
+    let Some(stats) = &node.stats else {
+        return;
+    };
+    let gpu_count = stats.gpus.len() as u16;
+    let columns = if gpu_count > 4 { 2 } else { 1 };
+    let panel_rows = gpu_count.div_ceil(columns).max(1);
+    let [gpu_area, proc_area] =
+        Layout::vertical([Constraint::Length(panel_rows * tui::PANEL_HEIGHT), Constraint::Min(3)]).areas(area);
+    let rows = Layout::vertical(vec![Constraint::Length(tui::PANEL_HEIGHT); panel_rows as usize]).split(gpu_area);
+    for (i, gpu) in stats.gpus.iter().enumerate() {
+        let cells = Layout::horizontal(vec![Constraint::Ratio(1, columns as u32); columns as usize])
+            .split(rows[i / columns as usize]);
+        tui::draw_gpu_panel(f, gpu, cells[i % columns as usize]);
+    }
+    let page_size = proc_area.height.saturating_sub(3) as usize;
+    tui::draw_process_table(f, stats, table, page_size, proc_area);
+}
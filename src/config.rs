@@ -0,0 +1,358 @@
+//! Configuration files: `/etc/gpustat.toml`, then `~/.config/gpustat/config.toml`
+//! on top of it; command-line flags override both
+//!
+//! ```toml
+//! [display]
+//! show_cmd = true
+//! show_power = "draw,limit"
+//! sort = "util"
+//!
+//! [watch]
+//! interval = 2          # or "auto"; used by a bare -i
+//! sparkline = 20
+//!
+//! [thresholds]
+//! temp = [60, 80]   # warn, crit
+//! util = [30]
+//!
+//! [theme]
+//! name = "solarized"
+//! user = "#dc322f"
+//!
+//! [[plugins]]
+//! name = "account"
+//! path = "/usr/lib/gpustat/libaccount.so"
+//! ```
+
+use crate::display::{Threshold, Thresholds};
+use crate::plugin::PluginConfig;
+use crate::theme::{ColorValue, Theme, ThemeName};
+use crate::watch::IntervalArg;
+use crate::Args;
+use gpustat_core::{ProcSortKey, SortKey};
+use serde::Deserialize;
+use std::fmt;
+use std::path::PathBuf;
+
+/// System-wide defaults, read before the user's file
+pub const SYSTEM_PATH: &str = "/etc/gpustat.toml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub display: DisplayConfig,
+    pub watch: WatchDefaults,
+    pub thresholds: ThresholdConfig,
+    pub theme: ThemeConfig,
+    /// Column plugins, in display order
+    pub plugins: Vec<PluginConfig>,
+}
+
+/// Default columns and layout; each key is the long flag of the same name
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    pub show_all: bool,
+    pub show_cmd: bool,
+    pub show_user: bool,
+    pub show_pid: bool,
+    pub show_full_cmd: bool,
+    pub show_cpu: bool,
+    pub show_elapsed: bool,
+    pub show_fan_speed: bool,
+    /// `true` or the `--show-codec` value, e.g. "enc,dec,jpg"
+    pub show_codec: Option<FlagValue>,
+    /// `true` or the `--show-power` value, e.g. "draw,limit"
+    pub show_power: Option<FlagValue>,
+    pub show_clocks: bool,
+    pub show_temp_limits: bool,
+    pub show_pcie: bool,
+    pub show_profiling: bool,
+    pub show_bar1: bool,
+    pub show_reserved: bool,
+    pub show_mode: bool,
+    pub no_header: bool,
+    pub no_processes: bool,
+    pub gpuname_width: Option<usize>,
+    pub bars: bool,
+    pub mem_bar: bool,
+    pub compact: bool,
+    pub summary: bool,
+    pub collapse_idle: bool,
+    pub gradient: bool,
+    pub sort: Option<SortKey>,
+    pub sort_procs: Option<ProcSortKey>,
+    pub top_procs: Option<usize>,
+    /// Rhai script run on each snapshot (`--script`)
+    pub script: Option<PathBuf>,
+}
+
+/// A flag with an optional value: `show_power = true` is a bare `--show-power`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum FlagValue {
+    On(bool),
+    Value(String),
+}
+
+/// Watch mode defaults
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchDefaults {
+    /// Interval of a bare `-i` (seconds or "auto")
+    pub interval: Option<IntervalArg>,
+    pub sparkline: Option<usize>,
+    pub highlight_changes: bool,
+    pub no_clear: bool,
+    pub alert_mem_growth: Option<f64>,
+}
+
+impl Config {
+    /// Fill in what the command line left unset; switches set in the file stay on
+    pub fn apply(&self, args: &mut Args) {
+        let d = &self.display;
+        for (flag, value) in [
+            (&mut args.show_all, d.show_all),
+            (&mut args.show_cmd, d.show_cmd),
+            (&mut args.show_user, d.show_user),
+            (&mut args.show_pid, d.show_pid),
+            (&mut args.show_full_cmd, d.show_full_cmd),
+            (&mut args.show_cpu, d.show_cpu),
+            (&mut args.show_elapsed, d.show_elapsed),
+            (&mut args.show_fan_speed, d.show_fan_speed),
+            (&mut args.show_clocks, d.show_clocks),
+            (&mut args.show_temp_limits, d.show_temp_limits),
+            (&mut args.show_pcie, d.show_pcie),
+            (&mut args.show_profiling, d.show_profiling),
+            (&mut args.show_bar1, d.show_bar1),
+            (&mut args.show_reserved, d.show_reserved),
+            (&mut args.show_mode, d.show_mode),
+            (&mut args.no_header, d.no_header),
+            (&mut args.no_processes, d.no_processes),
+            (&mut args.bars, d.bars),
+            (&mut args.mem_bar, d.mem_bar),
+            (&mut args.compact, d.compact),
+            (&mut args.summary, d.summary),
+            (&mut args.collapse_idle, d.collapse_idle),
+            (&mut args.gradient, d.gradient),
+            (&mut args.highlight_changes, self.watch.highlight_changes),
+            (&mut args.no_clear, self.watch.no_clear),
+        ] {
+            *flag |= value;
+        }
+        for (flag, value) in [(&mut args.show_codec, &d.show_codec), (&mut args.show_power, &d.show_power)] {
+            if flag.is_none() {
+                *flag = match value {
+                    Some(FlagValue::On(true)) => Some(None),
+                    Some(FlagValue::Value(v)) => Some(Some(v.clone())),
+                    Some(FlagValue::On(false)) | None => None,
+                };
+            }
+        }
+        args.gpuname_width = args.gpuname_width.or(d.gpuname_width);
+        args.sort = args.sort.or(d.sort);
+        args.sort_procs = args.sort_procs.or(d.sort_procs);
+        args.top_procs = args.top_procs.or(d.top_procs);
+        if args.script.is_none() {
+            args.script = d.script.clone();
+        }
+        args.alert_mem_growth = args.alert_mem_growth.or(self.watch.alert_mem_growth);
+        // A bare -i / --sparkline takes the configured value
+        if let Some(w @ None) = &mut args.watch {
+            *w = self.watch.interval;
+        }
+        if let Some(n @ None) = &mut args.sparkline {
+            *n = self.watch.sparkline;
+        }
+    }
+}
+
+/// Per-field overrides of the default [`Thresholds`]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThresholdConfig {
+    pub temp: Option<Threshold>,
+    pub util: Option<Threshold>,
+    pub fan: Option<Threshold>,
+    pub power: Option<Threshold>,
+    pub codec: Option<Threshold>,
+    pub mem_temp: Option<Threshold>,
+}
+
+impl ThresholdConfig {
+    /// Overlay the configured values onto `base`
+    pub fn apply(&self, base: &mut Thresholds) {
+        let fields = [
+            (&mut base.temp, self.temp),
+            (&mut base.util, self.util),
+            (&mut base.fan, self.fan),
+            (&mut base.power, self.power),
+            (&mut base.codec, self.codec),
+            (&mut base.mem_temp, self.mem_temp),
+        ];
+        for (slot, value) in fields {
+            if let Some(v) = value {
+                *slot = v;
+            }
+        }
+    }
+}
+
+/// Base theme plus per-field color overrides
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub name: Option<ThemeName>,
+    pub hostname: Option<ColorValue>,
+    pub index: Option<ColorValue>,
+    pub gpu_name: Option<ColorValue>,
+    pub temp: Option<ColorValue>,
+    pub fan: Option<ColorValue>,
+    pub util: Option<ColorValue>,
+    pub codec: Option<ColorValue>,
+    pub power: Option<ColorValue>,
+    pub clock: Option<ColorValue>,
+    pub pcie: Option<ColorValue>,
+    pub mem: Option<ColorValue>,
+    pub mem_total: Option<ColorValue>,
+    pub user: Option<ColorValue>,
+    pub cmd: Option<ColorValue>,
+    pub proc_mem: Option<ColorValue>,
+}
+
+impl ThemeConfig {
+    /// Build the theme: `name` (the CLI wins over the file), then field overrides
+    pub fn resolve(&self, name: Option<ThemeName>) -> Theme {
+        let mut theme = Theme::named(name.or(self.name).unwrap_or_default());
+        let fields = [
+            (&mut theme.hostname, self.hostname),
+            (&mut theme.index, self.index),
+            (&mut theme.name, self.gpu_name),
+            (&mut theme.temp, self.temp),
+            (&mut theme.fan, self.fan),
+            (&mut theme.util, self.util),
+            (&mut theme.codec, self.codec),
+            (&mut theme.power, self.power),
+            (&mut theme.clock, self.clock),
+            (&mut theme.pcie, self.pcie),
+            (&mut theme.mem_used, self.mem),
+            (&mut theme.mem_total, self.mem_total),
+            (&mut theme.user, self.user),
+            (&mut theme.cmd, self.cmd),
+            (&mut theme.proc_mem, self.proc_mem),
+        ];
+        for (slot, value) in fields {
+            if let Some(ColorValue(color)) = value {
+                *slot = color;
+            }
+        }
+        theme
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigError {
+    path: PathBuf,
+    message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// `$XDG_CONFIG_HOME/gpustat/config.toml`, falling back to `~/.config`
+pub fn default_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("gpustat").join("config.toml"))
+}
+
+/// Load `paths` in order, later files overriding keys of earlier ones; missing
+/// files are skipped
+pub fn load_layered(paths: &[PathBuf]) -> Result<Config, ConfigError> {
+    let mut merged = toml::Table::new();
+    let mut last = None;
+    for path in paths {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(ConfigError { path: path.clone(), message: e.to_string() }),
+        };
+        let table: toml::Table =
+            toml::from_str(&text).map_err(|e| ConfigError { path: path.clone(), message: e.to_string() })?;
+        merge(&mut merged, table);
+        last = Some(path);
+    }
+    let Some(path) = last else {
+        return Ok(Config::default());
+    };
+    merged.try_into().map_err(|e: toml::de::Error| ConfigError {
+        path: path.clone(),
+        message: e.to_string(),
+    })
+}
+
+/// Overlay `top` onto `base`, recursing into tables ([display], [theme], ...)
+fn merge(base: &mut toml::Table, top: toml::Table) {
+    for (key, value) in top {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(b)), toml::Value::Table(t)) => merge(b, t),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(text: &str) -> toml::Table {
+        toml::from_str(text).unwrap()
+    }
+
+    /// `text` written to a file of its own under the temp directory
+    fn write(name: &str, text: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("gpustat-config-{}-{}", std::process::id(), name));
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn merge_tables() {
+        let mut base = table("top = 1\n[display]\na = true\nb = 1\n[watch]\nc = 2\n");
+        merge(&mut base, table("top = 2\n[display]\nb = 3\n[theme]\nname = \"nvtop\"\n"));
+        assert_eq!(base, table("top = 2\n[display]\na = true\nb = 3\n[watch]\nc = 2\n[theme]\nname = \"nvtop\"\n"));
+    }
+
+    #[test]
+    fn layered() {
+        let system = write("system.toml", "[display]\nshow_cmd = true\nshow_pid = true\n[watch]\nsparkline = 10\n");
+        let user = write("user.toml", "[display]\nshow_pid = false\n[watch]\ninterval = \"auto\"\n");
+        let missing = std::env::temp_dir().join("gpustat-config-missing.toml");
+        let config = load_layered(&[system.clone(), missing, user.clone()]);
+        let _ = (std::fs::remove_file(system), std::fs::remove_file(user));
+
+        let config = config.unwrap();
+        assert!(config.display.show_cmd);
+        assert!(!config.display.show_pid);
+        assert_eq!(config.watch.sparkline, Some(10));
+        assert_eq!(config.watch.interval, Some(IntervalArg::Auto));
+    }
+
+    #[test]
+    fn layered_errors() {
+        assert!(!load_layered(&[]).unwrap().display.show_cmd);
+        let bad = write("bad.toml", "[display]\nshow_cmdd = true\n");
+        let result = load_layered(std::slice::from_ref(&bad));
+        let _ = std::fs::remove_file(&bad);
+        assert_eq!(result.unwrap_err().path, bad);
+    }
+}
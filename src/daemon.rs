@@ -0,0 +1,152 @@
+//! `gpustat daemon`: keep NVML initialized and answer on a unix socket, so
+//! `gpustat --socket` (shell prompts, status bars) skips NVML's startup cost
+//!
+//! One request per connection: the client writes a JSON line
+//! (`{"gpu_ids":[0],"inventory":false}`) and reads back `{"Ok": snapshot}` or
+//! `{"Err": "why"}`, then the connection closes. Snapshots younger than the
+//! refresh interval are reused, so a burst of prompts costs one query.
+
+use gpustat_core::{GpuBackend, GpuStatCollection, GpuStatError, QueryOptions};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::time::Instant;
+use std::time::Duration;
+
+/// How long a client may take to send its request, and to take the reply
+#[cfg(unix)]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize)]
+struct Request {
+    gpu_ids: Option<Vec<u32>>,
+    inventory: bool,
+    #[serde(default)]
+    pcie: bool,
+}
+
+/// `$XDG_RUNTIME_DIR/gpustat.sock`, else `gpustat-$UID.sock` in the temp
+/// directory, so another user can't put a socket where ours is expected
+pub fn default_socket() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("gpustat.sock"),
+        #[cfg(unix)]
+        // SAFETY: getuid has no preconditions and cannot fail
+        None => std::env::temp_dir().join(format!("gpustat-{}.sock", unsafe { libc::getuid() })),
+        #[cfg(not(unix))]
+        None => std::env::temp_dir().join("gpustat.sock"),
+    }
+}
+
+/// Serve `backend` on `path` until killed
+#[cfg(unix)]
+pub fn run(backend: &dyn GpuBackend, path: &Path, max_age: Duration) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    let listener = match crate::systemd::unix_listener() {
+        // The .socket unit owns the path and its mode
+        Some(listener) => listener,
+        None => {
+            if let Ok(meta) = std::fs::symlink_metadata(path) {
+                if !meta.file_type().is_socket() {
+                    return Err(format!("{} exists and is not a socket", path.display()).into());
+                }
+                if UnixStream::connect(path).is_ok() {
+                    return Err(format!("a daemon is already listening on {}", path.display()).into());
+                }
+                // Left over from a daemon that didn't exit cleanly
+                std::fs::remove_file(path)?;
+            }
+            let listener =
+                UnixListener::bind(path).map_err(|e| format!("cannot listen on {}: {}", path.display(), e))?;
+            // Anyone who can run gpustat may ask; a system daemon serves every user
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o666))?;
+            eprintln!("Serving GPU stats on {}", path.display());
+            listener
+        }
+    };
+    crate::systemd::notify_ready();
+
+    // Last snapshot of all GPUs, with and without inventory and PCIe throughput
+    let mut cache: [Option<(Instant, GpuStatCollection)>; 4] = Default::default();
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        // One connection at a time: a client that stops reading mustn't stall everyone else
+        let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+        let _ = stream.set_write_timeout(Some(REQUEST_TIMEOUT));
+        let mut line = String::new();
+        if BufReader::new(&stream).read_line(&mut line).is_err() {
+            continue;
+        }
+        let reply: Result<GpuStatCollection, String> = serde_json::from_str::<Request>(&line)
+            .map_err(|e| format!("bad request: {}", e))
+            .and_then(|request| {
+                let slot = &mut cache[usize::from(request.inventory) | usize::from(request.pcie) << 1];
+                let stats = match slot {
+                    Some((taken, stats)) if taken.elapsed() < max_age => stats.clone(),
+                    _ => {
+                        let query = QueryOptions { inventory: request.inventory, pcie: request.pcie };
+                        let stats = backend.query(None, &query).map_err(|e| e.to_string())?;
+                        *slot = Some((Instant::now(), stats.clone()));
+                        stats
+                    }
+                };
+                Ok(select(stats, request.gpu_ids.as_deref()))
+            });
+        if let Ok(body) = serde_json::to_vec(&reply) {
+            let _ = stream.write_all(&body);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn run(_backend: &dyn GpuBackend, _path: &Path, _max_age: Duration) -> Result<(), Box<dyn std::error::Error>> {
+    Err("gpustat daemon needs unix sockets".into())
+}
+
+fn select(mut stats: GpuStatCollection, gpu_ids: Option<&[u32]>) -> GpuStatCollection {
+    if let Some(ids) = gpu_ids {
+        stats.gpus.retain(|g| ids.contains(&g.index));
+    }
+    stats
+}
+
+/// `--socket`: snapshots from a running `gpustat daemon`
+pub struct SocketBackend {
+    path: PathBuf,
+}
+
+impl SocketBackend {
+    pub fn new(path: &Path) -> Self {
+        SocketBackend { path: path.to_path_buf() }
+    }
+}
+
+impl GpuBackend for SocketBackend {
+    #[cfg(unix)]
+    fn query(&self, gpu_ids: Option<&[u32]>, query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixStream;
+
+        let mut stream = UnixStream::connect(&self.path)
+            .map_err(|e| format!("cannot reach gpustat daemon at {}: {}", self.path.display(), e))?;
+        let request = Request { gpu_ids: gpu_ids.map(<[u32]>::to_vec), inventory: query.inventory, pcie: query.pcie };
+        let mut line = serde_json::to_vec(&request)?;
+        line.push(b'\n');
+        stream.write_all(&line)?;
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply)?;
+        let reply: Result<GpuStatCollection, String> = serde_json::from_slice(&reply)?;
+        Ok(reply.map_err(|e| format!("gpustat daemon: {}", e))?)
+    }
+
+    #[cfg(not(unix))]
+    fn query(&self, _gpu_ids: Option<&[u32]>, _query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        Err("--socket needs unix sockets".into())
+    }
+}
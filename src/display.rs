@@ -1,13 +1,31 @@
 //! Terminal display for GPU stats (colored output like Python gpustat)
 
-use crate::core::{GpuProcessInfo, GpuStat, GpuStatCollection};
-use colored::Colorize;
+use crate::history::{self, GpuHistory, History};
+use crate::theme::{self, fg, Theme};
+use gpustat_core::{
+    to_gib, ComputeMode, GpuProcessInfo, GpuStat, GpuStatCollection, MemUnit, MigInstance, ProcessSummary, SortKey,
+    TempUnit, UserUsage, VgpuInstance,
+};
+use colored::{Color, ColoredString, Colorize};
+use serde::Deserialize;
 use std::io::IsTerminal;
 use std::fmt;
 use std::io::{self, Write};
 
 const DEFAULT_GPUNAME_WIDTH: usize = 16;
+/// Narrowest the GPU name column is shrunk to before dropping columns
+const MIN_GPUNAME_WIDTH: usize = 8;
+/// Below this many columns for processes, continuation lines use a fixed indent
+const MIN_PROCESS_WIDTH: usize = 24;
+const CONTINUATION_INDENT: usize = 4;
 const NOT_SUPPORTED: &str = "Not Supported";
+const BAR_WIDTH: usize = 10;
+/// Temperatures mapped to the green and red ends of the gradient
+const GRADIENT_TEMP_MIN: f64 = 30.0;
+const GRADIENT_TEMP_MAX: f64 = 90.0;
+/// Highlight the slowdown threshold when the GPU is within this many °C of it
+const TEMP_HEADROOM_WARN: f64 = 10.0;
+const BAR_PARTIALS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
 
 /// Shorten string from left with ellipsis
 fn shorten_left(text: &str, width: usize, placeholder: &str) -> String {
@@ -23,21 +41,231 @@ fn shorten_left(text: &str, width: usize, placeholder: &str) -> String {
     format!("{}{}", placeholder, &text[text.len() - (width - placeholder.len())..])
 }
 
+/// Fixed-width block-character gauge for a ratio in [0, 1], like nvtop
+fn bar(ratio: f64, width: usize) -> String {
+    let eighths = (ratio.clamp(0.0, 1.0) * (width * 8) as f64).round() as usize;
+    let full = eighths / 8;
+    let mut s = "█".repeat(full);
+    if full < width {
+        s.push(BAR_PARTIALS[eighths % 8]);
+        s.push_str(&" ".repeat(width - full - 1));
+    }
+    s
+}
+
+/// Bar gauge colored green/yellow/red by fill level
+fn colored_bar(ratio: f64, use_color: bool) -> String {
+    let b = bar(ratio, BAR_WIDTH);
+    if !use_color {
+        return format!("[{}]", b);
+    }
+    let b = if ratio < 0.5 {
+        b.green()
+    } else if ratio < 0.8 {
+        b.yellow()
+    } else {
+        b.red()
+    };
+    format!("[{}]", b)
+}
+
+/// Whole-cell percentage gauge, e.g. `[█████░░░░░] 52%`
+fn percent_bar(ratio: f64, use_color: bool) -> String {
+    let ratio = ratio.clamp(0.0, 1.0);
+    let full = (ratio * BAR_WIDTH as f64).round() as usize;
+    let filled = "█".repeat(full);
+    let empty = "░".repeat(BAR_WIDTH - full);
+    let pct = rjust((ratio * 100.0).round() as u32, 3);
+    if !use_color {
+        return format!("[{}{}] {}%", filled, empty, pct);
+    }
+    let filled = if ratio < 0.5 {
+        filled.green()
+    } else if ratio < 0.8 {
+        filled.yellow()
+    } else {
+        filled.red()
+    };
+    format!("[{}{}] {}%", filled, empty.dimmed(), pct)
+}
+
+/// Color level of a value relative to its [`Threshold`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Normal,
+    Warn,
+    Crit,
+}
+
+/// Warn/crit breakpoints for one metric; values at or above `warn` are bold,
+/// at or above `crit` (if set) are highlighted in red
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "Vec<f64>")]
+pub struct Threshold {
+    pub warn: f64,
+    pub crit: Option<f64>,
+}
+
+impl Threshold {
+    const fn warn(warn: f64) -> Self {
+        Self { warn, crit: None }
+    }
+
+    fn level(&self, value: f64) -> Level {
+        match self.crit {
+            Some(crit) if value >= crit => Level::Crit,
+            _ if value >= self.warn => Level::Warn,
+            _ => Level::Normal,
+        }
+    }
+}
+
+impl TryFrom<Vec<f64>> for Threshold {
+    type Error = String;
+
+    fn try_from(v: Vec<f64>) -> Result<Self, String> {
+        match v[..] {
+            [warn] => Ok(Self::warn(warn)),
+            [warn, crit] if crit >= warn => Ok(Self { warn, crit: Some(crit) }),
+            [_, _] => Err("crit threshold must not be below warn".to_string()),
+            _ => Err(format!("expected 1 or 2 thresholds (warn[,crit]), got {}", v.len())),
+        }
+    }
+}
+
+impl std::str::FromStr for Threshold {
+    type Err = String;
+
+    /// Parse `WARN` or `WARN,CRIT` (e.g. `60,80`)
+    fn from_str(s: &str) -> Result<Self, String> {
+        let values = s
+            .split(',')
+            .map(|x| x.trim().parse::<f64>().map_err(|_| format!("invalid threshold '{}'", x.trim())))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::try_from(values)
+    }
+}
+
+/// Color breakpoints for every thresholded field (defaults match Python gpustat)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// Temperature in °C
+    pub temp: Threshold,
+    /// GPU utilization in %
+    pub util: Threshold,
+    /// Fan speed in %
+    pub fan: Threshold,
+    /// Power draw in % of the enforced limit
+    pub power: Threshold,
+    /// Encoder/decoder utilization in %
+    pub codec: Threshold,
+    /// Memory (HBM) temperature in °C
+    pub mem_temp: Threshold,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            temp: Threshold::warn(50.0),
+            util: Threshold::warn(30.0),
+            fan: Threshold::warn(30.0),
+            power: Threshold::warn(40.0),
+            codec: Threshold::warn(50.0),
+            mem_temp: Threshold { warn: 85.0, crit: Some(95.0) },
+        }
+    }
+}
+
+/// Base color below warn, bold at warn, bold red on reverse at crit
+fn paint(text: &str, color: Option<Color>, level: Level) -> ColoredString {
+    match level {
+        Level::Normal => fg(text, color),
+        Level::Warn => fg(text, color).bold(),
+        Level::Crit => text.red().bold().reversed(),
+    }
+}
+
 /// Display options for GPU stats
 #[derive(Debug, Clone, Default)]
 pub struct DisplayOptions {
     pub show_cmd: bool,
     pub show_user: bool,
+    /// Replace other users' names and commands with "***" (`--anonymize`)
+    pub anonymize: bool,
+    /// List processes once each, across GPUs, instead of per GPU (`--by-process`)
+    pub by_process: bool,
+    /// Print per-user process and memory totals after the table (`--by-user`)
+    pub by_user: bool,
+    /// Only list processes owned by these users (`--user`, `--mine`); empty lists all
+    pub users: Vec<String>,
+    /// Add host CPU% and resident memory to each process
+    pub show_cpu: bool,
+    /// Add each process's CUDA_VISIBLE_DEVICES, when set and readable
+    pub show_visible_devices: bool,
+    /// Add how long each process has been running
+    pub show_elapsed: bool,
+    /// List each process on its own line with host CPU/memory and full command (`-f`)
+    pub show_full_cmd: bool,
     pub show_pid: bool,
     pub show_fan_speed: bool,
     pub show_codec: bool,      // enc,dec or both
+    /// Add JPEG and OFA engines to the codec column (`-e jpg,ofa`)
+    pub show_jpg_ofa: bool,
     pub show_power: bool,     // draw, limit or both
     pub show_power_limit: bool,
+    /// Show current SM/graphics/memory clocks
+    pub show_clocks: bool,
+    /// Show the slowdown temperature next to the current temperature
+    pub show_temp_limits: bool,
+    /// Show PCIe link generation/width and throughput
+    pub show_pcie: bool,
+    /// Show DCGM profiling metrics (SM active/occupancy, tensor, DRAM activity)
+    pub show_profiling: bool,
+    /// Show BAR1 used/total memory
+    pub show_bar1: bool,
+    /// Show memory reserved by the driver next to used/total
+    pub show_reserved: bool,
+    /// Show compute mode, persistence mode and (if virtualized) virtualization mode
+    pub show_mode: bool,
+    /// Show the Windows driver model (WDDM/TCC/MCDM)
+    pub show_driver_model: bool,
     pub no_processes: bool,
     pub no_header: bool,
     pub gpuname_width: Option<usize>,
     pub force_color: bool,
     pub no_color: bool,
+    /// Render utilization/memory sparklines (watch mode only, needs history)
+    pub sparkline: bool,
+    /// Draw utilization and memory as block-character bar gauges
+    pub bars: bool,
+    /// Add a column with used memory as a percentage gauge
+    pub mem_bar: bool,
+    /// One short line per GPU (index, util, mem %, temp); no names or processes
+    pub compact: bool,
+    /// Highlight values that changed since the previous sample (watch mode only)
+    pub highlight_changes: bool,
+    /// Print a footer line with totals across all GPUs
+    pub summary: bool,
+    /// Replace runs of idle GPUs (0% util, no processes) with one line
+    pub collapse_idle: bool,
+    /// Order GPUs by this metric before display
+    pub sort: Option<SortKey>,
+    /// Order processes within each GPU
+    pub sort_procs: Option<gpustat_core::ProcSortKey>,
+    /// Show at most this many processes per GPU, the largest by memory
+    pub top_procs: Option<usize>,
+    /// Color breakpoints for temperature, utilization, fan, power and codec
+    pub thresholds: Thresholds,
+    /// Colors of each field
+    pub theme: Theme,
+    /// Color temperature, utilization and memory on a truecolor gradient
+    pub gradient: bool,
+    /// Unit temperatures are converted to
+    pub temp_unit: TempUnit,
+    /// Unit of the memory column
+    pub mem_unit: MemUnit,
+    /// Fit lines into this many columns (None: unbounded, e.g. when piped)
+    pub width: Option<usize>,
 }
 
 fn opt_repr<T: fmt::Display>(v: Option<T>, none: &str) -> String {
@@ -52,17 +280,136 @@ fn rjust<T: fmt::Display>(v: T, w: usize) -> String {
     format!("{0:>1$}", v, w)
 }
 
-impl GpuStat {
+/// Width of `s` on screen, ignoring ANSI escape sequences
+fn visible_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI sequence: ESC [ params final-byte
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// Columns of the terminal stdout is attached to, if any
+pub fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    crossterm::terminal::size().ok().map(|(w, _)| w as usize).filter(|&w| w > 0)
+}
+
+/// Text rendering of a single GPU
+trait GpuLine {
+    fn format_line(&self, opts: &DisplayOptions, temp_unit: TempUnit, use_color: bool, history: Option<&GpuHistory>) -> String;
+    fn format_compact(&self, opts: &DisplayOptions, temp_unit: TempUnit, use_color: bool) -> String;
+    fn format_stats(&self, opts: &DisplayOptions, temp_unit: TempUnit, use_color: bool, history: Option<&GpuHistory>) -> String;
+    fn format_processes(&self, opts: &DisplayOptions, use_color: bool, prev: Option<&GpuStat>) -> Vec<String>;
+}
+
+impl GpuLine for GpuStat {
+    /// One GPU line; with `opts.width` set, processes that would overflow
+    /// continue on indented lines
     fn format_line(
         &self,
         opts: &DisplayOptions,
+        temp_unit: TempUnit,
+        use_color: bool,
+        history: Option<&GpuHistory>,
+    ) -> String {
+        let mut s = self.format_stats(opts, temp_unit, use_color, history);
+        if opts.no_processes {
+            return s;
+        }
+        s.push_str(" |");
+
+        let prev = history
+            .filter(|_| opts.highlight_changes)
+            .and_then(|h| h.previous.as_ref());
+        // With MIG, processes are listed under their instances instead
+        let entries = if self.mig_instances.is_empty() {
+            self.format_processes(opts, use_color, prev)
+        } else {
+            vec![format!(" MIG: {} instances", self.mig_instances.len())]
+        };
+        let Some(width) = opts.width else {
+            s.extend(entries);
+            return s;
+        };
+
+        let first_indent = visible_width(&s);
+        let indent = if first_indent + MIN_PROCESS_WIDTH <= width {
+            first_indent
+        } else {
+            CONTINUATION_INDENT
+        };
+        let mut col = first_indent;
+        let mut first_line = true;
+        let mut placed = false;
+        for entry in entries {
+            let w = visible_width(&entry);
+            if col + w > width && (placed || (first_line && indent < col)) {
+                s.push('\n');
+                s.push_str(&" ".repeat(indent));
+                col = indent;
+                first_line = false;
+            }
+            s.push_str(&entry);
+            col += w;
+            placed = true;
+        }
+        s
+    }
+
+    /// `--compact` line: index, utilization, memory percentage and temperature only
+    fn format_compact(&self, opts: &DisplayOptions, temp_unit: TempUnit, use_color: bool) -> String {
+        let th = &opts.thresholds;
+        let theme = &opts.theme;
+        let index = format!("[{}]", self.index);
+        let util = format!("{}%", rjust(opt_repr(self.utilization.as_ref(), "??"), 3));
+        let mem = format!("{}%", rjust((self.memory_ratio() * 100.0).round() as u32, 3));
+        let temp = format!("{}{}", rjust(opt_repr(self.temperature.as_ref(), "??"), 3), temp_unit.symbol());
+        if !use_color {
+            return format!("{} {} {} {}", index, util, mem, temp);
+        }
+        let util = match self.utilization {
+            Some(u) => paint(&util, theme.util, th.util.level(u as f64)),
+            None => paint(&util, theme.util, Level::Warn),
+        };
+        let temp = match self.temperature.map(|t| temp_unit.to_celsius(t as f64)) {
+            Some(t) => paint(&temp, theme.temp, th.temp.level(t)),
+            None => temp.normal(),
+        };
+        let index = if self.available { fg(&index, theme.index) } else { fg(&index, theme.name_error) };
+        format!("{} {} {} {}", index, util, fg(&mem, theme.mem_used).bold(), temp)
+    }
+
+    /// Everything before the process list: index, name, sensors, memory
+    fn format_stats(
+        &self,
+        opts: &DisplayOptions,
+        temp_unit: TempUnit,
         use_color: bool,
+        history: Option<&GpuHistory>,
     ) -> String {
+        let sparks = history.filter(|_| opts.sparkline);
+        let prev = history
+            .filter(|_| opts.highlight_changes)
+            .and_then(|h| h.previous.as_ref());
         let mut s = String::new();
+        let theme = &opts.theme;
 
         // [index]
         if use_color {
-            s.push_str(&format!("[{}] ", self.index).cyan().to_string());
+            s.push_str(&fg(&format!("[{}] ", self.index), theme.index).to_string());
         } else {
             s.push_str(&format!("[{}] ", self.index));
         }
@@ -70,12 +417,12 @@ impl GpuStat {
         // GPU name
         let gpu_width = opts.gpuname_width.unwrap_or(DEFAULT_GPUNAME_WIDTH);
         if gpu_width > 0 {
-            let name = shorten_left(&self.name, gpu_width, "…");
+            let name = shorten_left(&self.display_name(), gpu_width, "…");
             if use_color {
                 let name_colored = if self.available {
-                    name.blue().to_string()
+                    fg(&name, theme.name).to_string()
                 } else {
-                    name.red().to_string()
+                    fg(&name, theme.name_error).to_string()
                 };
                 s.push_str(&format!("{:>width$} | ", name_colored, width = gpu_width));
             } else {
@@ -84,25 +431,62 @@ impl GpuStat {
         }
 
         // Temperature - rjust 3 then color (Python: CTemp < 50 → red, else bold_red)
+        let th = &opts.thresholds;
         let temp_str = rjust(opt_repr(self.temperature.as_ref(), "??"), 3);
         if use_color {
-            let temp_colored = match self.temperature {
-                Some(t) if t < 50 => temp_str.red().to_string(),
-                Some(_) => temp_str.bold().red().to_string(),
+            let temp_colored = match self.temperature.map(|t| temp_unit.to_celsius(t as f64)) {
+                Some(t) if opts.gradient => {
+                    let ratio = (t - GRADIENT_TEMP_MIN) / (GRADIENT_TEMP_MAX - GRADIENT_TEMP_MIN);
+                    temp_str.color(theme::gradient(ratio)).to_string()
+                }
+                Some(t) => paint(&temp_str, theme.temp, th.temp.level(t)).to_string(),
                 _ => temp_str.to_string(),
             };
-            s.push_str(&format!("{}°C, ", temp_colored));
+            s.push_str(&format!("{}{}", temp_colored, temp_unit.symbol()));
         } else {
-            s.push_str(&format!("{}°C, ", temp_str));
+            s.push_str(&format!("{}{}", temp_str, temp_unit.symbol()));
+        }
+        // Memory temperature, where supported, as `61°C/72°C`
+        if let Some(mt) = self.temperature_memory {
+            let mt_str = mt.to_string();
+            if use_color {
+                let level = th.mem_temp.level(temp_unit.to_celsius(mt as f64));
+                s.push_str(&format!("/{}{}", paint(&mt_str, theme.temp, level), temp_unit.symbol()));
+            } else {
+                s.push_str(&format!("/{}{}", mt_str, temp_unit.symbol()));
+            }
         }
+        // Slowdown threshold (optional), bold once within TEMP_HEADROOM_WARN of it
+        if opts.show_temp_limits {
+            let limit = format!("(slowdown {}{})", opt_repr(self.temperature_slowdown, "??"), temp_unit.symbol());
+            if use_color {
+                let close = match (self.temperature, self.temperature_slowdown) {
+                    (Some(t), Some(l)) => {
+                        temp_unit.to_celsius(l as f64) - temp_unit.to_celsius(t as f64) < TEMP_HEADROOM_WARN
+                    }
+                    _ => false,
+                };
+                let level = if close { Level::Warn } else { Level::Normal };
+                s.push_str(&format!(" {}", paint(&limit, theme.temp, level)));
+            } else {
+                s.push_str(&format!(" {}", limit));
+            }
+        }
+        s.push_str(", ");
 
-        // Fan speed (optional) - rjust 3 (Python: FSpeed < 30 → cyan, else bold_cyan)
+        // Fan speed (optional) - rjust 3 (Python: FSpeed < 30 → cyan, else bold_cyan);
+        // cards whose fans disagree show the range, colored by the fastest
         if opts.show_fan_speed {
-            let fan_str = rjust(opt_repr(self.fan_speed.as_ref(), "??"), 3);
+            let fan_range = self.fan_range();
+            let fan_str = match fan_range {
+                Some((min, max)) if min != max => format!("{}-{}", min, max),
+                Some((_, max)) => rjust(max, 3),
+                None => rjust("??", 3),
+            };
             if use_color {
-                let fan_colored = match self.fan_speed {
-                    Some(f) if f < 30 => fan_str.cyan().to_string(),
-                    _ => fan_str.bold().cyan().to_string(),
+                let fan_colored = match fan_range {
+                    Some((_, f)) => paint(&fan_str, theme.fan, th.fan.level(f as f64)),
+                    _ => paint(&fan_str, theme.fan, Level::Warn),
                 };
                 s.push_str(&format!("{} %, ", fan_colored));
             } else {
@@ -114,14 +498,28 @@ impl GpuStat {
         // Build full "  XX %" string first so padding is correct, then color entire field
         let util_display = format!("{} %", rjust(opt_repr(self.utilization.as_ref(), "??"), 3));
         if use_color {
-            let util_colored = match self.utilization {
-                Some(u) if u < 30 => util_display.as_str().green().to_string(),
-                _ => util_display.as_str().bold().green().to_string(),
+            let mut util_colored = match self.utilization {
+                Some(u) if opts.gradient => util_display.color(theme::gradient(u as f64 / 100.0)),
+                Some(u) => paint(&util_display, theme.util, th.util.level(u as f64)),
+                _ => paint(&util_display, theme.util, Level::Warn),
             };
-            s.push_str(&util_colored);
+            if prev.is_some_and(|p| p.utilization != self.utilization) {
+                util_colored = util_colored.reversed();
+            }
+            s.push_str(&util_colored.to_string());
         } else {
             s.push_str(&util_display);
         }
+        if opts.bars {
+            let ratio = self.utilization.map_or(0.0, |u| u as f64 / 100.0);
+            s.push(' ');
+            s.push_str(&colored_bar(ratio, use_color));
+        }
+        if let Some(h) = sparks {
+            let spark = history::sparkline(h.utilization.iter().map(|u| u.map(f64::from)), 100.0);
+            s.push(' ');
+            s.push_str(&if use_color { fg(&spark, theme.util).to_string() } else { spark });
+        }
 
         // Codec (optional) - rjust 3 for enc/dec (Python: < 50 → green, else bold_green)
         if opts.show_codec {
@@ -129,19 +527,37 @@ impl GpuStat {
             let dec_str = rjust(opt_repr(self.utilization_dec.as_ref(), "??"), 3);
             s.push_str(" (");
             if use_color {
-                let enc_c = match self.utilization_enc {
-                    Some(u) if u < 50 => enc_str.green().to_string(),
-                    _ => enc_str.bold().green().to_string(),
-                };
-                let dec_c = match self.utilization_dec {
-                    Some(u) if u < 50 => dec_str.green().to_string(),
-                    _ => dec_str.bold().green().to_string(),
-                };
+                let codec_level = |u: Option<u32>| u.map_or(Level::Warn, |u| th.codec.level(u as f64));
+                let enc_c = paint(&enc_str, theme.codec, codec_level(self.utilization_enc));
+                let dec_c = paint(&dec_str, theme.codec, codec_level(self.utilization_dec));
                 s.push_str(&format!("E: {} %, D: {} %", enc_c, dec_c));
             } else {
                 s.push_str(&format!("E: {} %, D: {} %", enc_str, dec_str));
             }
-            s.push_str(")");
+            // JPEG / OFA, only on GPUs that have the engines
+            if opts.show_jpg_ofa {
+                for (label, u) in [("J", self.utilization_jpg), ("O", self.utilization_ofa)] {
+                    let Some(u) = u else { continue };
+                    let util = rjust(u, 3);
+                    if use_color {
+                        s.push_str(&format!(", {}: {} %", label, paint(&util, theme.codec, th.codec.level(u as f64))));
+                    } else {
+                        s.push_str(&format!(", {}: {} %", label, util));
+                    }
+                }
+            }
+            // Encoder sessions, only while any are running
+            if let Some(sessions) = self.encoder_sessions.filter(|&n| n > 0) {
+                let fps = opt_repr(self.encoder_fps, "??");
+                let latency = opt_repr(self.encoder_latency.map(|us| format!("{:.1}", us as f64 / 1000.0)), "??");
+                let stats = format!("{} sess, {} fps, {} ms", sessions, fps, latency);
+                if use_color {
+                    s.push_str(&format!(", {}", fg(&stats, theme.codec)));
+                } else {
+                    s.push_str(&format!(", {}", stats));
+                }
+            }
+            s.push(')');
         }
 
         // Power (optional) - rjust 3 (Python: draw/limit < 0.4 → magenta, else bold_magenta)
@@ -149,8 +565,10 @@ impl GpuStat {
             let pow_str = rjust(opt_repr(self.power_draw.as_ref(), "??"), 3);
             if use_color {
                 let pow_colored = match (self.power_draw, self.power_limit) {
-                    (Some(d), Some(l)) if l > 0 && (d as f32 / l as f32) < 0.4 => pow_str.magenta().to_string(),
-                    _ => pow_str.bold().magenta().to_string(),
+                    (Some(d), Some(l)) if l > 0 => {
+                        paint(&pow_str, theme.power, th.power.level(d as f64 / l as f64 * 100.0))
+                    }
+                    _ => paint(&pow_str, theme.power, Level::Warn),
                 };
                 s.push_str(&format!(",  {} ", pow_colored));
             } else {
@@ -159,49 +577,446 @@ impl GpuStat {
             if opts.show_power_limit {
                 let limit_str = rjust(opt_repr(self.power_limit.as_ref(), "??"), 3);
                 if use_color {
-                    s.push_str(&format!("/ {} W", limit_str.magenta()));
+                    s.push_str(&format!("/ {} W", fg(&limit_str, theme.power)));
                 } else {
                     s.push_str(&format!("/ {} W", limit_str));
                 }
             }
+            // Watch mode: energy since the previous sample and since start
+            if let (Some(delta), Some(h)) = (self.energy_delta, history) {
+                let energy = format!("+{:.0} J ({:.2} Wh)", delta, h.energy / 3600.0);
+                if use_color {
+                    s.push_str(&format!(" {}", energy.dimmed()));
+                } else {
+                    s.push_str(&format!(" {}", energy));
+                }
+            }
+            // Watch mode: share of the last period spent power / thermal capped
+            let capped = [("P", self.violation_power_pct), ("T", self.violation_thermal_pct)]
+                .into_iter()
+                .filter_map(|(label, pct)| pct.filter(|&p| p >= 0.5).map(|p| format!("{}:{:.0}%", label, p)))
+                .collect::<Vec<_>>();
+            if !capped.is_empty() {
+                let capped = format!("capped {}", capped.join(" "));
+                if use_color {
+                    s.push_str(&format!(" {}", paint(&capped, Some(Color::Yellow), Level::Warn)));
+                } else {
+                    s.push_str(&format!(" {}", capped));
+                }
+            }
+        }
+
+        // Clocks (optional) - current SM, graphics and memory clocks in MHz
+        if opts.show_clocks {
+            let sm = rjust(opt_repr(self.clock_sm.as_ref(), "??"), 4);
+            let gfx = rjust(opt_repr(self.clock_graphics.as_ref(), "??"), 4);
+            let mem = rjust(opt_repr(self.clock_mem.as_ref(), "??"), 5);
+            if use_color {
+                s.push_str(&format!(
+                    " | SM {} Gfx {} Mem {} MHz",
+                    fg(&sm, theme.clock),
+                    fg(&gfx, theme.clock),
+                    fg(&mem, theme.clock)
+                ));
+            } else {
+                s.push_str(&format!(" | SM {} Gfx {} Mem {} MHz", sm, gfx, mem));
+            }
+        }
+
+        // PCIe (optional) - link as GenNxW, with the maximum appended when running
+        // below it, and TX/RX throughput in MB/s
+        if opts.show_pcie {
+            let link = |g: Option<u32>, w: Option<u32>| format!("{}x{}", opt_repr(g, "?"), opt_repr(w, "?"));
+            let mut link_str = format!("Gen{}", link(self.pcie_gen, self.pcie_width));
+            let below = |cur: Option<u32>, max: Option<u32>| matches!((cur, max), (Some(c), Some(m)) if c < m);
+            let degraded = below(self.pcie_gen, self.pcie_gen_max) || below(self.pcie_width, self.pcie_width_max);
+            if degraded {
+                link_str.push_str(&format!("/{}", link(self.pcie_gen_max, self.pcie_width_max)));
+            }
+            let mbps = |kb: Option<u32>| rjust(opt_repr(kb.map(|k| (k as f64 / 1024.0).round() as u32), "??"), 5);
+            let (tx, rx) = (mbps(self.pcie_tx), mbps(self.pcie_rx));
+            if use_color {
+                let level = if degraded { Level::Warn } else { Level::Normal };
+                s.push_str(&format!(
+                    " | {} TX {} RX {} MB/s",
+                    paint(&link_str, theme.pcie, level),
+                    fg(&tx, theme.pcie),
+                    fg(&rx, theme.pcie)
+                ));
+            } else {
+                s.push_str(&format!(" | {} TX {} RX {} MB/s", link_str, tx, rx));
+            }
+        }
+
+        // Profiling (optional) - DCGM activity counters in %; "--" without DCGM
+        if opts.show_profiling {
+            let p = self.profiling.clone().unwrap_or_default();
+            let pct = |v: Option<f64>| rjust(opt_repr(v.map(|v| format!("{:.0}%", v)), "--"), 4);
+            let (sm, occ, tc, dram) = (pct(p.sm_active), pct(p.sm_occupancy), pct(p.tensor_active), pct(p.dram_active));
+            if use_color {
+                s.push_str(&format!(
+                    " | SM {} Occ {} TC {} DRAM {}",
+                    fg(&sm, theme.util),
+                    fg(&occ, theme.util),
+                    fg(&tc, theme.util),
+                    fg(&dram, theme.util)
+                ));
+            } else {
+                s.push_str(&format!(" | SM {} Occ {} TC {} DRAM {}", sm, occ, tc, dram));
+            }
+        }
+
+        // Plugin columns, e.g. " | account: proj-x"
+        for (name, value) in &self.extra {
+            if use_color {
+                s.push_str(&format!(" | {}: {}", name.dimmed(), value));
+            } else {
+                s.push_str(&format!(" | {}: {}", name, value));
+            }
         }
 
         // Memory - rjust 5 for used/total (Python: CMemU bold_yellow, CMemT yellow)
         s.push_str(" | ");
+        let (mem_used_str, mem_total_str) = memory_strs(
+            (self.memory_used, self.memory_total),
+            (self.memory_used_bytes, self.memory_total_bytes),
+            opts.mem_unit,
+        );
+        let mem_suffix = opts.mem_unit.symbol();
         if use_color {
-            let mem_used_str = rjust(self.memory_used.to_string(), 5);
-            let mem_total_str = rjust(self.memory_total.to_string(), 5);
-            s.push_str(&format!(
-                "{} / {} MB",
-                mem_used_str.bold().yellow(),
-                mem_total_str.yellow()
-            ));
+            let mut mem_used_colored = if opts.gradient && self.memory_total > 0 {
+                let ratio = self.memory_used as f64 / self.memory_total as f64;
+                mem_used_str.color(theme::gradient(ratio)).bold()
+            } else {
+                fg(&mem_used_str, theme.mem_used).bold()
+            };
+            if prev.is_some_and(|p| p.memory_used != self.memory_used) {
+                mem_used_colored = mem_used_colored.reversed();
+            }
+            match &mem_total_str {
+                Some(total) => s.push_str(&format!(
+                    "{} / {} {}",
+                    mem_used_colored,
+                    fg(total, theme.mem_total),
+                    mem_suffix
+                )),
+                None => s.push_str(&format!("{} {}", mem_used_colored, mem_suffix)),
+            }
         } else {
-            s.push_str(&format!("{:>5} / {:>5} MB", self.memory_used, self.memory_total));
-        }
-
-        // Processes - only "(Not Supported)" when processes is None (NVML API failed)
-        // When Some([]) (no processes on GPU), show nothing after " |"
-        if !opts.no_processes {
-            s.push_str(" |");
-            match &self.processes {
-                None => s.push_str(&format!(" ({})", NOT_SUPPORTED)),
-                Some(procs) => {
-                    for p in procs {
-                        s.push_str(&format_process(p, opts, use_color));
-                    }
+            match &mem_total_str {
+                Some(total) => s.push_str(&format!("{} / {} {}", mem_used_str, total, mem_suffix)),
+                None => s.push_str(&format!("{} {}", mem_used_str, mem_suffix)),
+            }
+        }
+        // Driver-reserved memory (optional), in the same unit as used/total
+        if opts.show_reserved {
+            let reserved = match opts.mem_unit {
+                MemUnit::MiB => format!("{} MB", self.memory_reserved),
+                MemUnit::GiB => format!("{:.1} GiB", self.memory_reserved as f64 / 1024.0),
+                MemUnit::Percent if self.memory_total > 0 => {
+                    format!("{:.0} %", self.memory_reserved as f64 / self.memory_total as f64 * 100.0)
                 }
+                MemUnit::Percent => "?? %".to_string(),
+            };
+            let reserved = format!(" (rsvd {})", reserved);
+            if use_color {
+                s.push_str(&reserved.dimmed().to_string());
+            } else {
+                s.push_str(&reserved);
+            }
+        }
+        if opts.bars {
+            let ratio = if self.memory_total > 0 {
+                self.memory_used as f64 / self.memory_total as f64
+            } else {
+                0.0
+            };
+            s.push(' ');
+            s.push_str(&colored_bar(ratio, use_color));
+        }
+        if let Some(h) = sparks {
+            let spark = history::sparkline(h.memory.iter().map(|&m| Some(m)), 1.0);
+            s.push(' ');
+            s.push_str(&if use_color { fg(&spark, theme.mem_used).to_string() } else { spark });
+        }
+
+        // Compute / persistence mode (optional); anything but Default is highlighted
+        // since exclusive modes make a busy GPU reject new contexts
+        if opts.show_mode {
+            let mode = format!("{:<10}", self.compute_mode.map_or("??", |m| m.label()));
+            let persist = match self.persistence_mode {
+                Some(true) => "P:On ",
+                Some(false) => "P:Off",
+                None => "P:?? ",
+            };
+            if use_color {
+                let level = match self.compute_mode {
+                    Some(ComputeMode::Default) | None => Level::Normal,
+                    Some(_) => Level::Warn,
+                };
+                s.push_str(&format!(" | {} {}", paint(&mode, Some(Color::Yellow), level), persist.dimmed()));
+            } else {
+                s.push_str(&format!(" | {} {}", mode, persist));
+            }
+            // Only virtualized GPUs get a marker, bare metal is the common case
+            if let Some(virt) = self.virtualization_mode.as_deref().filter(|v| *v != "None") {
+                s.push_str(&format!(" V:{}", virt));
+            }
+        }
+
+        // Windows driver model (optional); a pending change is shown after an arrow
+        if opts.show_driver_model {
+            let mut model = opt_repr(self.driver_model.as_deref(), "N/A");
+            if let Some(pending) = self.driver_model_pending.as_deref().filter(|p| Some(*p) != self.driver_model.as_deref()) {
+                model.push_str(&format!("->{}", pending));
             }
+            if use_color && self.driver_model.as_deref() == Some("WDDM") {
+                // WDDM shares the GPU with the desktop compositor and adds launch latency
+                s.push_str(&format!(" | {}", paint(&model, Some(Color::Yellow), Level::Warn)));
+            } else {
+                s.push_str(&format!(" | {}", model));
+            }
+        }
+
+        // BAR1 usage (optional)
+        if opts.show_bar1 {
+            let used = rjust(opt_repr(self.bar1_used, "??"), 5);
+            let total = rjust(opt_repr(self.bar1_total, "??"), 5);
+            if use_color {
+                s.push_str(&format!(
+                    " | BAR1 {} / {} MB",
+                    fg(&used, theme.mem_used),
+                    fg(&total, theme.mem_total)
+                ));
+            } else {
+                s.push_str(&format!(" | BAR1 {} / {} MB", used, total));
+            }
+        }
+
+        // Memory percentage gauge (optional), comparable across cards of different sizes
+        if opts.mem_bar {
+            s.push_str(" | ");
+            s.push_str(&percent_bar(self.memory_ratio(), use_color));
+        }
+
+        // Display marker: `DISP` when driving a display, dimmed `disp` when only connected
+        if self.display_active == Some(true) {
+            s.push_str(&format!(" [{}]", if use_color { "DISP".bold().to_string() } else { "DISP".to_string() }));
+        } else if self.display_attached == Some(true) {
+            s.push_str(&format!(" [{}]", if use_color { "disp".dimmed().to_string() } else { "disp".to_string() }));
+        }
+
+        // Health marker when the GPU needs a reset (pending retirement, remap or mode change) or replacement
+        if let Some(alert) = self.memory_alert() {
+            let badge = if use_color { paint(alert, None, Level::Crit).to_string() } else { alert.to_string() };
+            s.push_str(&format!(" [{}]", badge));
+        }
+
+        // Throttle badge, only while clocks are being held down
+        let throttles = self.throttles();
+        if !throttles.is_empty() {
+            let badges: Vec<String> = throttles
+                .iter()
+                .map(|t| {
+                    if !use_color {
+                        t.label.to_string()
+                    } else if t.severe {
+                        paint(t.label, None, Level::Crit).to_string()
+                    } else {
+                        paint(t.label, Some(Color::Yellow), Level::Warn).to_string()
+                    }
+                })
+                .collect();
+            s.push_str(&format!(" [{}]", badges.join(",")));
         }
 
         s
     }
+
+    /// Process entries, each with its leading space; only "(Not Supported)"
+    /// when processes is None (NVML API failed), nothing when Some([])
+    fn format_processes(&self, opts: &DisplayOptions, use_color: bool, prev: Option<&GpuStat>) -> Vec<String> {
+        let Some(procs) = &self.processes else {
+            return vec![format!(" ({})", NOT_SUPPORTED)];
+        };
+        let (shown, hidden) = top_processes(procs, opts.top_procs);
+        let mut entries = Vec::new();
+        // With change highlighting, mark new processes with '+' and
+        // list processes gone since the previous sample with '-'
+        let prev_procs = prev.and_then(|p| p.processes.as_ref());
+        for p in shown {
+            let is_new = prev_procs.is_some_and(|pp| pp.iter().all(|q| q.pid != p.pid));
+            let entry = format_process(p, opts, use_color);
+            if is_new {
+                let marker = if use_color { "+".bold().green().to_string() } else { "+".to_string() };
+                entries.push(format!(" {}{}", marker, &entry[1..]));
+            } else {
+                entries.push(entry);
+            }
+        }
+        for gone in prev_procs.into_iter().flatten().filter(|q| procs.iter().all(|p| p.pid != q.pid)) {
+            let entry = format_process(gone, opts, false);
+            let entry = format!("-{}", &entry[1..]);
+            if use_color {
+                entries.push(format!(" {}", entry.dimmed().strikethrough()));
+            } else {
+                entries.push(format!(" {}", entry));
+            }
+        }
+        if hidden > 0 {
+            entries.push(format_more(hidden, use_color));
+        }
+        entries
+    }
+}
+
+/// The `limit` processes using the most GPU memory, in their original order,
+/// and how many were left out
+fn top_processes(procs: &[GpuProcessInfo], limit: Option<usize>) -> (Vec<&GpuProcessInfo>, usize) {
+    let Some(limit) = limit.filter(|&n| n < procs.len()) else {
+        return (procs.iter().collect(), 0);
+    };
+    let mut largest: Vec<usize> = (0..procs.len()).collect();
+    largest.sort_by_key(|&i| std::cmp::Reverse(procs[i].gpu_memory_usage));
+    largest.truncate(limit);
+    largest.sort_unstable();
+    (largest.into_iter().map(|i| &procs[i]).collect(), procs.len() - limit)
+}
+
+/// " +k more" entry for processes hidden by `--top-procs`
+fn format_more(hidden: usize, use_color: bool) -> String {
+    let more = format!("+{} more", hidden);
+    if use_color {
+        format!(" {}", more.dimmed())
+    } else {
+        format!(" {}", more)
+    }
+}
+
+/// Tree lines for `-f`: PID, host CPU% and RSS, then the full command line,
+/// wrapped under itself when wider than `opts.width`
+fn format_full_processes(gpu: &GpuStat, opts: &DisplayOptions, use_color: bool) -> Vec<String> {
+    let procs = gpu.processes.as_deref().unwrap_or_default();
+    let mut lines = Vec::new();
+    for (i, p) in procs.iter().enumerate() {
+        let branch = if i + 1 == procs.len() { "└─" } else { "├─" };
+        let cpu = opt_repr(p.cpu_percent.map(|c| format!("{:3.0}%", c)), "  ?%");
+        let mem = opt_repr(p.cpu_memory_usage.map(format_host_bytes), "?");
+        let prefix = format!(" {} {:>7} ({}, {:>6}): ", branch, p.real_pid.unwrap_or(p.pid), cpu, mem);
+        let cmd = match &p.full_command {
+            Some(args) => args.join(" "),
+            None => p.command.clone(),
+        };
+        let indent = visible_width(&prefix);
+        let room = opts.width.map_or(usize::MAX, |w| w.saturating_sub(indent).max(MIN_PROCESS_WIDTH));
+        let chars: Vec<char> = cmd.chars().collect();
+        for (j, chunk) in chars.chunks(room.min(chars.len().max(1))).enumerate() {
+            let chunk: String = chunk.iter().collect();
+            let chunk = if use_color { fg(&chunk, opts.theme.cmd).to_string() } else { chunk };
+            if j == 0 {
+                let head = if use_color { prefix.dimmed().to_string() } else { prefix.clone() };
+                lines.push(format!("{}{}", head, chunk));
+            } else {
+                // Keep the tree rail going while later processes follow
+                let rail = if i + 1 == procs.len() { "  " } else { " │" };
+                lines.push(format!("{}{}{}", rail, " ".repeat(indent - 2), chunk));
+            }
+        }
+    }
+    lines
+}
+
+/// Tree line for a vGPU instance under its host GPU: type, VM and framebuffer
+fn format_vgpu_line(vgpu: &VgpuInstance, last: bool, opts: &DisplayOptions, use_color: bool) -> String {
+    let theme = &opts.theme;
+    let branch = if last { "└─" } else { "├─" };
+    let vgpu_type = vgpu.vgpu_type.as_deref().unwrap_or("??");
+    let vm = format!("VM {}", vgpu.vm_id.as_deref().unwrap_or("??"));
+    let used = rjust(opt_repr(vgpu.memory_used, "??"), 5);
+    if use_color {
+        format!(
+            "  {} vGPU {} | {} | {} MB",
+            branch,
+            fg(vgpu_type, theme.name),
+            fg(&vm, theme.user),
+            fg(&used, theme.mem_used).bold()
+        )
+    } else {
+        format!("  {} vGPU {} | {} | {} MB", branch, vgpu_type, vm, used)
+    }
+}
+
+/// Nested line for a MIG instance: tree branch, profile, IDs, memory, processes
+fn format_mig_line(mig: &MigInstance, last: bool, opts: &DisplayOptions, use_color: bool) -> String {
+    let theme = &opts.theme;
+    let branch = if last { "└─" } else { "├─" };
+    let ids = format!(
+        "GI {} CI {}",
+        opt_repr(mig.gpu_instance_id, "?"),
+        opt_repr(mig.compute_instance_id, "?")
+    );
+    let mut s = if use_color {
+        format!("  {} MIG {} {}", branch, fg(&mig.profile, theme.name), ids.dimmed())
+    } else {
+        format!("  {} MIG {} {}", branch, mig.profile, ids)
+    };
+    if let Some(u) = mig.utilization {
+        let util = format!("{} %", rjust(u, 3));
+        if use_color {
+            s.push_str(&format!(" | {}", paint(&util, theme.util, opts.thresholds.util.level(u as f64))));
+        } else {
+            s.push_str(&format!(" | {}", util));
+        }
+    }
+    let used = rjust(mig.memory_used, 5);
+    let total = rjust(mig.memory_total, 5);
+    if use_color {
+        s.push_str(&format!(" | {} / {} MB", fg(&used, theme.mem_used).bold(), fg(&total, theme.mem_total)));
+    } else {
+        s.push_str(&format!(" | {} / {} MB", used, total));
+    }
+    if !opts.no_processes {
+        s.push_str(" |");
+        match &mig.processes {
+            None => s.push_str(&format!(" ({})", NOT_SUPPORTED)),
+            Some(procs) => {
+                let (procs, hidden) = top_processes(procs, opts.top_procs);
+                for p in procs {
+                    s.push_str(&format_process(p, opts, use_color));
+                }
+                if hidden > 0 {
+                    s.push_str(&format_more(hidden, use_color));
+                }
+            }
+        }
+    }
+    s
 }
 
 fn format_process(p: &GpuProcessInfo, opts: &DisplayOptions, use_color: bool) -> String {
     let mut s = String::new();
     s.push(' ');
 
+    // Slurm job, e.g. "[job 123456] "
+    if let Some(job) = &p.slurm_job {
+        let label = format!("[job {}]", job.id);
+        if use_color {
+            s.push_str(&format!("{} ", label.magenta()));
+        } else {
+            s.push_str(&format!("{} ", label));
+        }
+    }
+
+    // Container or pod the process runs in, e.g. "pod:trainer-7f9/"
+    if let Some(c) = &p.container {
+        let label = c.label();
+        if use_color {
+            s.push_str(&format!("{}/", label.cyan()));
+        } else {
+            s.push_str(&format!("{}/", label));
+        }
+    }
+
     // Python: CUser = term.bold_black (gray for username)
     // Username resolved via Ngid mapping: green (.green() for terminal compatibility)
     let show_username = opts.show_user || !opts.show_cmd;
@@ -209,9 +1024,9 @@ fn format_process(p: &GpuProcessInfo, opts: &DisplayOptions, use_color: bool) ->
         let username = p.username.as_deref().unwrap_or("--");
         if use_color {
             let username_str = if p.username_from_ngid_mapping {
-                username.green().to_string()
+                fg(username, opts.theme.user_ngid).to_string()
             } else {
-                username.bright_black().to_string()
+                fg(username, opts.theme.user).to_string()
             };
             s.push_str(&username_str);
         } else {
@@ -224,10 +1039,18 @@ fn format_process(p: &GpuProcessInfo, opts: &DisplayOptions, use_color: bool) ->
         }
         let cmd = &p.command;
         if use_color {
-            s.push_str(&cmd.cyan().to_string());
+            s.push_str(&fg(cmd, opts.theme.cmd).to_string());
         } else {
             s.push_str(cmd);
         }
+        // Python environment, e.g. "python[torch21]"
+        if let Some(env) = &p.python_env {
+            if use_color {
+                s.push_str(&format!("[{}]", env.dimmed()));
+            } else {
+                s.push_str(&format!("[{}]", env));
+            }
+        }
     }
     if opts.show_pid {
         let pid_str = match p.real_pid {
@@ -240,32 +1063,227 @@ fn format_process(p: &GpuProcessInfo, opts: &DisplayOptions, use_color: bool) ->
         Some(m) => m.to_string(),
         None => "?".to_string(),
     };
+    // Share of the device's memory, so usage compares across card sizes
+    let mem_pct = opt_repr(p.gpu_memory_percent.map(|pct| format!(", {:.0}%", pct)), "");
+    // Per-process SM utilization; encoder/decoder too when the codec column is on
+    let mut util = String::new();
+    if let Some(sm) = p.sm_util {
+        util.push_str(&format!(", SM:{}%", sm));
+    }
+    if opts.show_codec {
+        for (label, u) in [("E", p.enc_util), ("D", p.dec_util)] {
+            if let Some(u) = u.filter(|&u| u > 0) {
+                util.push_str(&format!(" {}:{}%", label, u));
+            }
+        }
+    }
+    // Host CPU and resident memory (optional)
+    let mut host = String::new();
+    if opts.show_cpu {
+        host.push_str(&format!(
+            ", cpu {}, {}",
+            opt_repr(p.cpu_percent.map(|c| format!("{:.0}%", c)), "?"),
+            opt_repr(p.cpu_memory_usage.map(format_host_bytes), "?")
+        ));
+    }
+    // Devices the process was restricted to (optional)
+    if opts.show_visible_devices {
+        if let Some(cvd) = &p.cuda_visible_devices {
+            host.push_str(&format!(", CVD={}", cvd));
+        }
+    }
+    // Time since the process started (optional)
+    if opts.show_elapsed {
+        host.push_str(&format!(", {}", opt_repr(p.elapsed.map(format_runtime), "?")));
+    }
+    // Plugin columns
+    for value in p.extra.values() {
+        host.push_str(&format!(", {}", value));
+    }
+    // Accounting stats follow memory: lifetime average util, peak memory, runtime
+    let acct = p.accounting.as_ref().map(|a| {
+        format!(
+            ", avg {}%, peak {}M, {}",
+            opt_repr(a.gpu_utilization, "?"),
+            opt_repr(a.max_memory_usage, "?"),
+            format_runtime(a.runtime)
+        )
+    });
+    let acct = acct.unwrap_or_default();
     if use_color {
-        s.push_str(&format!("({}M)", mem_str.yellow()));
+        s.push_str(&format!(
+            "({}M{}{}{}{})",
+            fg(&mem_str, opts.theme.proc_mem),
+            fg(&mem_pct, opts.theme.proc_mem),
+            fg(&util, opts.theme.util),
+            host,
+            acct.dimmed()
+        ));
     } else {
-        s.push_str(&format!("({}M)", mem_str));
+        s.push_str(&format!("({}M{}{}{}{})", mem_str, mem_pct, util, host, acct));
     }
     s
 }
 
-impl GpuStatCollection {
-    /// Print formatted GPU stats to stdout
-    pub fn print_formatted(
-        &self,
-        opts: &DisplayOptions,
-    ) -> io::Result<()> {
-        let use_color = if opts.no_color {
+/// `--by-process`: one row per PID with the GPUs it occupies and its total memory
+fn format_process_table(procs: &[ProcessSummary], opts: &DisplayOptions, use_color: bool) -> String {
+    let user_width = procs.iter().filter_map(|p| p.username.as_ref()).map(|u| u.chars().count()).max().unwrap_or(0).max(4);
+    let gpus: Vec<String> = procs.iter().map(|p| index_ranges(&p.gpus)).collect();
+    let gpu_width = gpus.iter().map(|g| g.len()).max().unwrap_or(0).max(4);
+    let header = format!("{:>7}  {:<uw$}  {:<gw$}  {:>8}  COMMAND", "PID", "USER", "GPUS", "MEMORY", uw = user_width, gw = gpu_width);
+    let mut out = if use_color { format!("{}\n", header.dimmed()) } else { format!("{}\n", header) };
+    for (p, gpus) in procs.iter().zip(&gpus) {
+        let user = format!("{:<w$}", p.username.as_deref().unwrap_or("--"), w = user_width);
+        let mem = format!("{:>6}M", p.gpu_memory_usage);
+        if use_color {
+            out.push_str(&format!(
+                "{:>7}  {}  {:<gw$}  {:>8}  {}\n",
+                p.pid,
+                fg(&user, opts.theme.user),
+                gpus,
+                fg(&mem, opts.theme.proc_mem),
+                fg(&p.command, opts.theme.cmd),
+                gw = gpu_width
+            ));
+        } else {
+            out.push_str(&format!("{:>7}  {}  {:<gw$}  {:>8}  {}\n", p.pid, user, gpus, mem, p.command, gw = gpu_width));
+        }
+    }
+    out
+}
+
+/// "alice: 2 procs, 31.0 GB ([0] 16.0 GB, [1] 15.0 GB)"
+fn format_user_usage(user: &UserUsage, theme: &Theme, use_color: bool) -> String {
+    let gb = |mb: u64| format!("{:.1} GB", mb as f64 / 1024.0);
+    let procs = |n: usize| if n == 1 { "1 proc".to_string() } else { format!("{} procs", n) };
+    let per_gpu: Vec<String> = user.gpus.iter().map(|g| format!("[{}] {}", g.index, gb(g.memory))).collect();
+    let name = if use_color { fg(&user.username, theme.user).bold().to_string() } else { user.username.clone() };
+    let total = if use_color { fg(&gb(user.memory), theme.mem_used).to_string() } else { gb(user.memory) };
+    format!("{}: {}, {} ({})", name, procs(user.processes), total, per_gpu.join(", "))
+}
+
+/// Host memory as gpustat prints it: "512MB", "1.6GB"
+fn format_host_bytes(bytes: u64) -> String {
+    const GB: u64 = 1024 * 1024 * 1024;
+    if bytes >= GB {
+        format!("{:.1}GB", bytes as f64 / GB as f64)
+    } else {
+        format!("{}MB", bytes / (1024 * 1024))
+    }
+}
+
+/// Compact duration: "45s", "12m", "3h05m", "2d04h"
+fn format_runtime(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h{:02}m", s / 3600, s / 60 % 60),
+        s => format!("{}d{:02}h", s / 86400, s / 3600 % 24),
+    }
+}
+
+/// Compress GPU indices into ranges, e.g. [2, 3, 4, 7] -> "2-4,7"
+fn index_ranges(indices: &[u32]) -> String {
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < indices.len() {
+        let start = indices[i];
+        let mut end = start;
+        while i + 1 < indices.len() && indices[i + 1] == end + 1 {
+            i += 1;
+            end = indices[i];
+        }
+        parts.push(if start == end {
+            start.to_string()
+        } else {
+            format!("{}-{}", start, end)
+        });
+        i += 1;
+    }
+    parts.join(",")
+}
+
+/// Emit the `GPUs 2-7: idle` line for a pending run of idle GPUs
+fn flush_idle_run(out: &mut String, run: &mut Vec<u32>, theme: &Theme, use_color: bool) {
+    if run.is_empty() {
+        return;
+    }
+    let label = if run.len() == 1 {
+        format!("[GPU {}]", run[0])
+    } else {
+        format!("[GPUs {}]", index_ranges(run))
+    };
+    if use_color {
+        out.push_str(&format!("{} {}\n", fg(&label, theme.index), "idle".dimmed()));
+    } else {
+        out.push_str(&format!("{} idle\n", label));
+    }
+    run.clear();
+}
+
+impl DisplayOptions {
+    /// Resolve whether to emit ANSI colors (flags first, then TTY detection)
+    pub fn use_color(&self) -> bool {
+        if self.no_color {
             false
-        } else if opts.force_color {
+        } else if self.force_color {
             true
         } else {
             std::io::stdout().is_terminal()
-        };
+        }
+    }
+}
+
+/// Display side of [`GpuStatCollection`]: option-driven post-processing and the text table
+pub trait Render {
+    /// Post-process a fresh query according to the display options (units, order)
+    fn apply_options(&mut self, opts: &DisplayOptions);
+    /// Just the `--user` filter and `--anonymize`, for output that keeps its units
+    fn apply_privacy(&mut self, opts: &DisplayOptions);
+    /// Render GPU stats as the full text table (header + one line per GPU)
+    fn format_table(&self, opts: &DisplayOptions, use_color: bool, history: Option<&History>) -> String;
+    /// Print formatted GPU stats to stdout
+    fn print_formatted(&self, opts: &DisplayOptions) -> io::Result<()>;
+}
 
+impl Render for GpuStatCollection {
+    fn apply_options(&mut self, opts: &DisplayOptions) {
+        self.set_temperature_unit(opts.temp_unit);
+        self.set_memory_unit(opts.mem_unit);
+        self.apply_privacy(opts);
+        if let Some(key) = opts.sort_procs {
+            self.sort_processes(key);
+        }
+        if opts.by_user {
+            self.set_user_usage();
+        }
+        if opts.by_process {
+            self.set_process_summary();
+        }
+        if let Some(key) = opts.sort {
+            self.sort_gpus(key);
+        }
+    }
+
+    fn apply_privacy(&mut self, opts: &DisplayOptions) {
+        if !opts.users.is_empty() {
+            self.retain_processes(|p| p.username.as_ref().is_some_and(|u| opts.users.contains(u)));
+        }
+        if opts.anonymize {
+            self.anonymize();
+        }
+    }
+
+    fn format_table(
+        &self,
+        opts: &DisplayOptions,
+        use_color: bool,
+        history: Option<&History>,
+    ) -> String {
         let gpu_width = opts.gpuname_width.unwrap_or_else(|| {
             self.gpus
                 .iter()
-                .map(|g| g.name.len())
+                .map(|g| g.display_name().len())
                 .max()
                 .unwrap_or(0)
                 .max(DEFAULT_GPUNAME_WIDTH)
@@ -273,36 +1291,269 @@ impl GpuStatCollection {
 
         let mut opts = opts.clone();
         opts.gpuname_width = Some(gpu_width);
+        if let Some(width) = opts.width.filter(|_| !opts.compact) {
+            fit_width(self, &mut opts, width, history);
+        }
+
+        let mut out = String::new();
 
         // Header
         if !opts.no_header {
             let timestr = self.query_time.format("%Y-%m-%d %H:%M:%S");
-            let driver = self.driver_version.as_deref().unwrap_or("N/A");
+            let mut versions = self.driver_version.as_deref().unwrap_or("N/A").to_string();
+            if let Some(cuda) = &self.cuda_version {
+                versions.push_str(&format!("  CUDA {}", cuda));
+            }
+            if let Some(nvml) = &self.nvml_version {
+                versions.push_str(&format!("  NVML {}", nvml));
+            }
             if use_color {
-                println!(
-                    "{}  {}  {}",
-                    self.hostname.bold().white(),
+                out.push_str(&format!(
+                    "{}  {}  {}\n",
+                    fg(&self.hostname, opts.theme.hostname).bold(),
                     timestr,
-                    driver.dimmed()
-                );
+                    versions.dimmed()
+                ));
             } else {
-                println!("{}  {}  {}", self.hostname, timestr, driver);
+                out.push_str(&format!("{}  {}  {}\n", self.hostname, timestr, versions));
             }
         }
 
-        // Body
+        // Body; with --collapse-idle, runs of idle GPUs become one summary line
+        let mut idle_run: Vec<u32> = Vec::new();
         for gpu in &self.gpus {
-            println!("{}", gpu.format_line(&opts, use_color));
+            if opts.collapse_idle && gpu.is_idle() {
+                idle_run.push(gpu.index);
+                continue;
+            }
+            flush_idle_run(&mut out, &mut idle_run, &opts.theme, use_color);
+            if opts.compact {
+                out.push_str(&gpu.format_compact(&opts, self.temperature_unit, use_color));
+                out.push('\n');
+                continue;
+            }
+            let gpu_history = history.and_then(|h| h.gpu(gpu.index));
+            out.push_str(&gpu.format_line(&opts, self.temperature_unit, use_color, gpu_history));
+            out.push('\n');
+            if opts.show_full_cmd && !opts.no_processes {
+                for line in format_full_processes(gpu, &opts, use_color) {
+                    out.push_str(&line);
+                    out.push('\n');
+                }
+            }
+            for (i, mig) in gpu.mig_instances.iter().enumerate() {
+                let last = i + 1 == gpu.mig_instances.len() && gpu.vgpu_instances.is_empty();
+                out.push_str(&format_mig_line(mig, last, &opts, use_color));
+                out.push('\n');
+            }
+            for (i, vgpu) in gpu.vgpu_instances.iter().enumerate() {
+                let last = i + 1 == gpu.vgpu_instances.len();
+                out.push_str(&format_vgpu_line(vgpu, last, &opts, use_color));
+                out.push('\n');
+            }
         }
+        flush_idle_run(&mut out, &mut idle_run, &opts.theme, use_color);
 
         if self.gpus.is_empty() {
             if use_color {
-                println!("{}", "(No GPUs are available)".yellow());
+                out.push_str(&format!("{}\n", "(No GPUs are available)".yellow()));
             } else {
-                println!("(No GPUs are available)");
+                out.push_str("(No GPUs are available)\n");
+            }
+        } else if opts.summary {
+            out.push_str(&format_summary(self, &opts, use_color));
+            out.push('\n');
+        }
+        if opts.by_process && !self.by_process.is_empty() {
+            out.push_str(&format_process_table(&self.by_process, &opts, use_color));
+        }
+        if opts.by_user {
+            for user in &self.users {
+                out.push_str(&format_user_usage(user, &opts.theme, use_color));
+                out.push('\n');
             }
         }
 
-        io::stdout().flush()
+        out
+    }
+
+    fn print_formatted(
+        &self,
+        opts: &DisplayOptions,
+    ) -> io::Result<()> {
+        let mut stdout = io::stdout().lock();
+        let mut opts = opts.clone();
+        opts.width = opts.width.or_else(terminal_width);
+        stdout.write_all(self.format_table(&opts, opts.use_color(), None).as_bytes())?;
+        stdout.flush()
+    }
+}
+
+/// Narrow `opts` until the stats columns of every GPU fit into `width`:
+/// first shrink the name column, then drop optional columns in priority order
+fn fit_width(stats: &GpuStatCollection, opts: &mut DisplayOptions, width: usize, history: Option<&History>) {
+    let needed = |opts: &DisplayOptions| {
+        let tail = if opts.no_processes { 0 } else { 2 };
+        stats.gpus
+            .iter()
+            .map(|g| {
+                let h = history.and_then(|h| h.gpu(g.index));
+                visible_width(&g.format_stats(opts, stats.temperature_unit, false, h)) + tail
+            })
+            .max()
+            .unwrap_or(0)
+    };
+
+    let mut overflow = needed(opts).saturating_sub(width);
+    if overflow == 0 {
+        return;
+    }
+    let name_width = opts.gpuname_width.unwrap_or(DEFAULT_GPUNAME_WIDTH);
+    if name_width > MIN_GPUNAME_WIDTH {
+        opts.gpuname_width = Some(name_width.saturating_sub(overflow).max(MIN_GPUNAME_WIDTH));
+        overflow = needed(opts).saturating_sub(width);
+    }
+
+    let drops: [fn(&mut DisplayOptions); 16] = [
+        |o| o.sparkline = false,
+        |o| o.bars = false,
+        |o| o.mem_bar = false,
+        |o| o.show_mode = false,
+        |o| o.show_driver_model = false,
+        |o| o.show_temp_limits = false,
+        |o| o.show_bar1 = false,
+        |o| o.show_reserved = false,
+        |o| o.show_profiling = false,
+        |o| o.show_pcie = false,
+        |o| o.show_clocks = false,
+        |o| o.show_power_limit = false,
+        |o| o.show_codec = false,
+        |o| o.show_power = false,
+        |o| o.show_fan_speed = false,
+        |o| o.gpuname_width = Some(0),
+    ];
+    for drop in drops {
+        if overflow == 0 {
+            break;
+        }
+        drop(opts);
+        overflow = needed(opts).saturating_sub(width);
+    }
+}
+
+/// Footer aggregating all GPUs: memory, average utilization, power and process count
+/// Used and total memory in `unit`, right-aligned like the memory column; no
+/// total for %. Takes (used, total) in MiB and in bytes
+fn memory_strs(mib: (u64, u64), bytes: (u64, u64), unit: MemUnit) -> (String, Option<String>) {
+    match unit {
+        MemUnit::MiB => (rjust(mib.0.to_string(), 5), Some(rjust(mib.1.to_string(), 5))),
+        MemUnit::GiB => (format!("{:>5.1}", to_gib(bytes.0)), Some(format!("{:>5.1}", to_gib(bytes.1)))),
+        MemUnit::Percent => {
+            let ratio = if bytes.1 > 0 { bytes.0 as f64 / bytes.1 as f64 } else { 0.0 };
+            (rjust((ratio * 100.0).round() as u64, 3), None)
+        }
+    }
+}
+
+fn format_summary(stats: &GpuStatCollection, opts: &DisplayOptions, use_color: bool) -> String {
+    let theme = &opts.theme;
+    let sum = |f: fn(&GpuStat) -> u64| stats.gpus.iter().map(f).sum::<u64>();
+    let (mem_used, mem_total) = memory_strs(
+        (sum(|g| g.memory_used), sum(|g| g.memory_total)),
+        (sum(|g| g.memory_used_bytes), sum(|g| g.memory_total_bytes)),
+        opts.mem_unit,
+    );
+    let utils: Vec<u32> = stats.gpus.iter().filter_map(|g| g.utilization).collect();
+    let util_avg = if utils.is_empty() {
+        None
+    } else {
+        Some(utils.iter().sum::<u32>() / utils.len() as u32)
+    };
+    let powers: Vec<u32> = stats.gpus.iter().filter_map(|g| g.power_draw).collect();
+    let power = if powers.is_empty() {
+        None
+    } else {
+        Some(powers.iter().sum::<u32>())
+    };
+    let procs: usize = stats.gpus.iter().map(|g| g.processes.as_ref().map_or(0, |p| p.len())).sum();
+
+    let label = format!("Total ({} GPUs)", stats.gpus.len());
+    let util_str = format!("{} %", rjust(opt_repr(util_avg, "??"), 3));
+    let power_str = format!("{} W", opt_repr(power, "??"));
+    let mem_str = match mem_total {
+        Some(total) => format!("{} / {} {}", mem_used, total, opts.mem_unit.symbol()),
+        None => format!("{} {}", mem_used, opts.mem_unit.symbol()),
+    };
+    if use_color {
+        format!(
+            "{} | avg {} | {} | {} | {} processes",
+            label.bold(),
+            fg(&util_str, theme.util),
+            fg(&power_str, theme.power),
+            fg(&mem_str, theme.mem_used),
+            procs
+        )
+    } else {
+        format!(
+            "{} | avg {} | {} | {} | {} processes",
+            label, util_str, power_str, mem_str, procs
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpustat_core::{GpuBackend, MockBackend, QueryOptions};
+
+    #[test]
+    fn thresholds() {
+        assert_eq!("60".parse(), Ok(Threshold { warn: 60.0, crit: None }));
+        assert_eq!(" 60, 80.5 ".parse(), Ok(Threshold { warn: 60.0, crit: Some(80.5) }));
+        assert_eq!("70,70".parse(), Ok(Threshold { warn: 70.0, crit: Some(70.0) }));
+    }
+
+    #[test]
+    fn bad_thresholds() {
+        assert_eq!("80,60".parse::<Threshold>(), Err("crit threshold must not be below warn".to_string()));
+        assert_eq!("hot".parse::<Threshold>(), Err("invalid threshold 'hot'".to_string()));
+        assert!("1,2,3".parse::<Threshold>().is_err());
+        assert!("".parse::<Threshold>().is_err());
+    }
+
+    #[test]
+    fn levels() {
+        let t: Threshold = "60,80".parse().unwrap();
+        assert_eq!(t.level(59.9), Level::Normal);
+        assert_eq!(t.level(60.0), Level::Warn);
+        assert_eq!(t.level(80.0), Level::Crit);
+    }
+
+    fn mock() -> GpuStatCollection {
+        MockBackend::default().query(None, &QueryOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn mock_table() {
+        let table = mock().format_table(&DisplayOptions::default(), false, None);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("mock-node"), "{}", table);
+        assert!(lines[1].starts_with("[0] NVIDIA A100-SXM4-80GB"), "{}", table);
+        assert!(lines[1].contains("61903 / 81920 MB"), "{}", table);
+        assert!(lines[1].contains("alice(40960M"), "{}", table);
+        assert!(lines[2].starts_with("[1] NVIDIA A100-SXM4-80GB"), "{}", table);
+        assert!(!table.contains('\x1b'), "no color requested: {:?}", table);
+    }
+
+    #[test]
+    fn mock_json_round_trip() {
+        let stats = mock();
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["hostname"], "mock-node");
+        assert_eq!(json["gpus"][0]["memory_used"], 61903);
+        assert_eq!(json["gpus"][0]["processes"][1]["username"], "bob");
+        let back: GpuStatCollection = serde_json::from_value(json).unwrap();
+        assert_eq!(back.gpus.len(), 2);
+        assert_eq!(back.gpus[1].utilization, Some(0));
     }
 }
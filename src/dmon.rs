@@ -0,0 +1,113 @@
+//! `gpustat dmon`: one compact row per GPU per interval, like `nvidia-smi dmon`
+//!
+//! Utilization, clocks and power are averaged over NVML's internal samples
+//! since the previous row rather than read instantaneously.
+
+use nvml_wrapper::enum_wrappers::device::{Sampling, TemperatureSensor};
+use nvml_wrapper::enums::device::SampleValue;
+use nvml_wrapper::{Device, Nvml};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Columns after the GPU index: (name, unit, sample type)
+const COLUMNS: [(&str, &str, Option<Sampling>); 8] = [
+    ("pwr", "W", Some(Sampling::Power)),
+    ("gtemp", "C", None),
+    ("sm", "%", Some(Sampling::GpuUtilization)),
+    ("mem", "%", Some(Sampling::MemoryUtilization)),
+    ("enc", "%", Some(Sampling::EncoderUtilization)),
+    ("dec", "%", Some(Sampling::DecoderUtilization)),
+    ("mclk", "MHz", Some(Sampling::MemoryClock)),
+    ("pclk", "MHz", Some(Sampling::ProcessorClock)),
+];
+/// Repeat the header after this many rows, as nvidia-smi does
+const HEADER_EVERY: u64 = 20;
+
+fn header() -> String {
+    let mut names = String::from("# gpu");
+    let mut units = String::from("# Idx");
+    for (name, unit, _) in COLUMNS {
+        names.push_str(&format!(" {:>6}", name));
+        units.push_str(&format!(" {:>6}", unit));
+    }
+    format!("{}\n{}\n", names, units)
+}
+
+fn sample_as_f64(v: &SampleValue) -> f64 {
+    match *v {
+        SampleValue::F64(v) => v,
+        SampleValue::U32(v) => v as f64,
+        SampleValue::U64(v) => v as f64,
+        SampleValue::I64(v) => v as f64,
+    }
+}
+
+/// Mean of the samples newer than `last_seen`, advancing it to the newest one
+fn average(device: &Device, kind: Sampling, last_seen: &mut u64) -> Option<f64> {
+    let samples = device.samples(kind, Some(*last_seen).filter(|&t| t > 0)).ok()?;
+    let fresh: Vec<_> = samples.iter().filter(|s| s.timestamp > *last_seen).collect();
+    *last_seen = fresh.iter().map(|s| s.timestamp).max().unwrap_or(*last_seen);
+    if fresh.is_empty() {
+        return None;
+    }
+    Some(fresh.iter().map(|s| sample_as_f64(&s.value)).sum::<f64>() / fresh.len() as f64)
+}
+
+/// Print a row per GPU every `interval` until `count` rows per GPU (or forever)
+pub fn run(gpu_ids: Option<&[u32]>, interval: Duration, count: Option<u64>) -> Result<(), Box<dyn std::error::Error>> {
+    let nvml = Nvml::init()?;
+    let indices: Vec<u32> = match gpu_ids {
+        Some(ids) => ids.to_vec(),
+        None => (0..nvml.device_count()?).collect(),
+    };
+    // Last sample timestamp seen per (gpu, column)
+    let mut last_seen: HashMap<(u32, usize), u64> = HashMap::new();
+    let mut stdout = io::stdout().lock();
+    let mut next_tick = Instant::now();
+    let mut rows = 0u64;
+
+    // Prime the timestamps so the first row only averages samples from this run
+    for &index in &indices {
+        let device = nvml.device_by_index(index)?;
+        for (col, (_, _, kind)) in COLUMNS.iter().enumerate() {
+            if let Some(kind) = kind {
+                average(&device, *kind, last_seen.entry((index, col)).or_default());
+            }
+        }
+    }
+
+    loop {
+        next_tick += interval;
+        std::thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+
+        if rows.is_multiple_of(HEADER_EVERY) {
+            stdout.write_all(header().as_bytes())?;
+        }
+        for &index in &indices {
+            let device = nvml.device_by_index(index)?;
+            let mut line = format!("{:>5}", index);
+            for (col, (_, _, kind)) in COLUMNS.iter().enumerate() {
+                let value = match *kind {
+                    // Power samples are in milliwatts
+                    Some(Sampling::Power) => average(&device, Sampling::Power, last_seen.entry((index, col)).or_default())
+                        .map(|mw| mw / 1000.0),
+                    Some(kind) => average(&device, kind, last_seen.entry((index, col)).or_default()),
+                    None => device.temperature(TemperatureSensor::Gpu).ok().map(f64::from),
+                };
+                match value {
+                    Some(v) => line.push_str(&format!(" {:>6.0}", v)),
+                    None => line.push_str(&format!(" {:>6}", "-")),
+                }
+            }
+            line.push('\n');
+            stdout.write_all(line.as_bytes())?;
+        }
+        stdout.flush()?;
+        rows += 1;
+
+        if count.is_some_and(|c| rows >= c) {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! `gpustat events`: stream XID errors and ECC events as timestamped lines
+//!
+//! NVML has no reset event, so resets show up as the XIDs the driver raises
+//! around them (e.g. 79 "fallen off the bus", 154 "recovery action changed").
+#![cfg_attr(not(target_os = "linux"), allow(dead_code, unused_imports))]
+
+use chrono::Local;
+use nvml_wrapper::bitmasks::event::EventTypes;
+use nvml_wrapper::enums::event::XidError;
+use nvml_wrapper::error::NvmlError;
+use nvml_wrapper::Nvml;
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// How long a single wait blocks, so a broken pipe is noticed promptly
+const WAIT_MS: u32 = 1000;
+
+#[derive(Debug, Serialize)]
+struct GpuEvent {
+    timestamp: String,
+    index: Option<u32>,
+    uuid: Option<String>,
+    /// "xid", "ecc_dbe" or "ecc_sbe"
+    kind: &'static str,
+    xid: Option<u64>,
+    description: String,
+}
+
+/// Meaning of the XIDs worth calling out; the full list is in NVIDIA's XID catalog
+fn xid_description(xid: u64) -> &'static str {
+    match xid {
+        13 => "Graphics engine exception",
+        31 => "GPU memory page fault",
+        43 => "GPU stopped processing",
+        45 => "Preemptive cleanup, due to previous errors",
+        48 => "Double-bit ECC error",
+        61 | 62 => "Internal micro-controller error",
+        63 | 64 => "ECC page retirement or row remapping event",
+        74 => "NVLink error",
+        79 => "GPU has fallen off the bus",
+        92 => "High single-bit ECC error rate",
+        94 => "Contained ECC error",
+        95 => "Uncontained ECC error",
+        119 | 120 => "GSP error",
+        154 => "GPU recovery action changed (reset or reboot required)",
+        _ => "",
+    }
+}
+
+/// Wait for events on the selected GPUs and print each as it arrives
+#[cfg(target_os = "linux")]
+pub fn run(gpu_ids: Option<&[u32]>, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let nvml = Nvml::init()?;
+    let indices: Vec<u32> = match gpu_ids {
+        Some(ids) => ids.to_vec(),
+        None => (0..nvml.device_count()?).collect(),
+    };
+    let wanted = EventTypes::CRITICAL_XID_ERROR | EventTypes::DOUBLE_BIT_ECC_ERROR | EventTypes::SINGLE_BIT_ECC_ERROR;
+
+    let mut set = nvml.create_event_set()?;
+    let mut registered = 0;
+    for &index in &indices {
+        let device = nvml.device_by_index(index)?;
+        let supported = device.supported_event_types().unwrap_or(EventTypes::empty()) & wanted;
+        if supported.is_empty() {
+            eprintln!("GPU {}: no supported error events, skipping", index);
+            continue;
+        }
+        set = device.register_events(supported, set).map_err(|e| e.error)?;
+        registered += 1;
+    }
+    if registered == 0 {
+        return Err("no GPU supports error events".into());
+    }
+
+    let mut stdout = io::stdout().lock();
+    loop {
+        let data = match set.wait(WAIT_MS) {
+            Ok(data) => data,
+            Err(NvmlError::Timeout) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let (kind, xid) = if data.event_type.contains(EventTypes::CRITICAL_XID_ERROR) {
+            let xid = match data.event_data {
+                Some(XidError::Value(v)) => Some(v),
+                _ => None,
+            };
+            ("xid", xid)
+        } else if data.event_type.contains(EventTypes::DOUBLE_BIT_ECC_ERROR) {
+            ("ecc_dbe", None)
+        } else {
+            ("ecc_sbe", None)
+        };
+        let description = match (kind, xid) {
+            ("xid", Some(x)) => xid_description(x).to_string(),
+            ("ecc_dbe", _) => "Double-bit ECC error".to_string(),
+            ("ecc_sbe", _) => "Single-bit ECC error".to_string(),
+            _ => String::new(),
+        };
+        let event = GpuEvent {
+            timestamp: Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string(),
+            index: data.device.index().ok(),
+            uuid: data.device.uuid().ok(),
+            kind,
+            xid,
+            description,
+        };
+        let line = if json {
+            serde_json::to_string(&event)?
+        } else {
+            format_event(&event)
+        };
+        writeln!(stdout, "{}", line)?;
+        stdout.flush()?;
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn run(_gpu_ids: Option<&[u32]>, _json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    Err("NVML event monitoring is only supported on Linux".into())
+}
+
+fn format_event(e: &GpuEvent) -> String {
+    let gpu = match e.index {
+        Some(i) => format!("[{}]", i),
+        None => "[?]".to_string(),
+    };
+    let what = match e.xid {
+        Some(x) => format!("XID {}", x),
+        None => e.kind.to_uppercase(),
+    };
+    let mut line = format!("{} {} {}", e.timestamp, gpu, what);
+    if !e.description.is_empty() {
+        line.push_str(&format!(": {}", e.description));
+    }
+    if let Some(uuid) = &e.uuid {
+        line.push_str(&format!(" ({})", uuid));
+    }
+    line
+}
@@ -0,0 +1,438 @@
+//! gRPC service (`gpustat serve --grpc`) and client (`--connect URL`), `grpc` feature
+//!
+//! The schema is `proto/gpustat.proto`. The message and service code below is
+//! what tonic-build would generate from it, kept by hand so building doesn't
+//! need protoc.
+
+use crate::serve::Auth;
+use gpustat_core::{DeviceError, GpuBackend, GpuProcessInfo, GpuStat, GpuStatCollection, GpuStatError, MemUnit, QueryOptions, TempUnit};
+use std::convert::Infallible;
+use rustls::pki_types::ServerName;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tonic::codegen::{http, BoxFuture, BoxStream, Context, Poll, Service, StdError};
+use tonic_prost::ProstCodec;
+
+/// Messages of `gpustat.v1`
+pub mod pb {
+    use std::collections::BTreeMap;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct QueryRequest {
+        #[prost(uint32, repeated, tag = "1")]
+        pub gpu_ids: Vec<u32>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct WatchRequest {
+        #[prost(uint32, repeated, tag = "1")]
+        pub gpu_ids: Vec<u32>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GpuStatCollection {
+        #[prost(string, tag = "1")]
+        pub hostname: String,
+        #[prost(string, tag = "2")]
+        pub query_time: String,
+        #[prost(string, optional, tag = "3")]
+        pub driver_version: Option<String>,
+        #[prost(string, optional, tag = "4")]
+        pub cuda_version: Option<String>,
+        #[prost(string, optional, tag = "5")]
+        pub nvml_version: Option<String>,
+        #[prost(double, optional, tag = "6")]
+        pub query_latency: Option<f64>,
+        #[prost(message, repeated, tag = "7")]
+        pub gpus: Vec<Gpu>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Gpu {
+        #[prost(uint32, tag = "1")]
+        pub index: u32,
+        #[prost(string, tag = "2")]
+        pub uuid: String,
+        #[prost(string, tag = "3")]
+        pub name: String,
+        #[prost(bool, tag = "4")]
+        pub available: bool,
+        #[prost(message, optional, tag = "5")]
+        pub error: Option<DeviceError>,
+        #[prost(uint32, optional, tag = "6")]
+        pub temperature: Option<u32>,
+        #[prost(uint32, optional, tag = "7")]
+        pub fan_speed: Option<u32>,
+        #[prost(uint32, optional, tag = "8")]
+        pub utilization: Option<u32>,
+        #[prost(uint32, optional, tag = "9")]
+        pub utilization_enc: Option<u32>,
+        #[prost(uint32, optional, tag = "10")]
+        pub utilization_dec: Option<u32>,
+        #[prost(uint32, optional, tag = "11")]
+        pub power_draw: Option<u32>,
+        #[prost(uint32, optional, tag = "12")]
+        pub power_limit: Option<u32>,
+        #[prost(uint64, tag = "13")]
+        pub memory_used_bytes: u64,
+        #[prost(uint64, tag = "14")]
+        pub memory_total_bytes: u64,
+        #[prost(message, repeated, tag = "15")]
+        pub processes: Vec<Process>,
+        #[prost(btree_map = "string, string", tag = "16")]
+        pub extra: BTreeMap<String, String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DeviceError {
+        #[prost(string, tag = "1")]
+        pub kind: String,
+        #[prost(string, tag = "2")]
+        pub message: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Process {
+        #[prost(uint32, tag = "1")]
+        pub pid: u32,
+        #[prost(string, optional, tag = "2")]
+        pub username: Option<String>,
+        #[prost(string, tag = "3")]
+        pub command: String,
+        #[prost(uint64, optional, tag = "4")]
+        pub gpu_memory_usage: Option<u64>,
+        #[prost(double, optional, tag = "5")]
+        pub cpu_percent: Option<f64>,
+        #[prost(uint64, optional, tag = "6")]
+        pub cpu_memory_usage: Option<u64>,
+        #[prost(btree_map = "string, string", tag = "7")]
+        pub extra: BTreeMap<String, String>,
+    }
+}
+
+const QUERY_PATH: &str = "/gpustat.v1.GpuStat/Query";
+const WATCH_PATH: &str = "/gpustat.v1.GpuStat/Watch";
+
+impl From<&GpuStatCollection> for pb::GpuStatCollection {
+    fn from(stats: &GpuStatCollection) -> Self {
+        pb::GpuStatCollection {
+            hostname: stats.hostname.clone(),
+            query_time: stats.query_time.to_rfc3339(),
+            driver_version: stats.driver_version.clone(),
+            cuda_version: stats.cuda_version.clone(),
+            nvml_version: stats.nvml_version.clone(),
+            query_latency: stats.query_latency,
+            gpus: stats.gpus.iter().map(pb::Gpu::from).collect(),
+        }
+    }
+}
+
+impl From<&GpuStat> for pb::Gpu {
+    fn from(g: &GpuStat) -> Self {
+        pb::Gpu {
+            index: g.index,
+            uuid: g.uuid.clone(),
+            name: g.name.clone(),
+            available: g.available,
+            error: g.error.as_ref().map(|e| pb::DeviceError {
+                kind: serde_json::to_value(e.kind).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default(),
+                message: e.message.clone(),
+            }),
+            temperature: g.temperature,
+            fan_speed: g.fan_speed,
+            utilization: g.utilization,
+            utilization_enc: g.utilization_enc,
+            utilization_dec: g.utilization_dec,
+            power_draw: g.power_draw,
+            power_limit: g.power_limit,
+            memory_used_bytes: g.memory_used_bytes,
+            memory_total_bytes: g.memory_total_bytes,
+            processes: g
+                .processes
+                .iter()
+                .flatten()
+                .map(|p| pb::Process {
+                    pid: p.pid,
+                    username: p.username.clone(),
+                    command: p.command.clone(),
+                    gpu_memory_usage: p.gpu_memory_usage,
+                    cpu_percent: p.cpu_percent,
+                    cpu_memory_usage: p.cpu_memory_usage,
+                    extra: p.extra.clone(),
+                })
+                .collect(),
+            extra: g.extra.clone(),
+        }
+    }
+}
+
+impl From<pb::GpuStatCollection> for GpuStatCollection {
+    fn from(stats: pb::GpuStatCollection) -> Self {
+        GpuStatCollection {
+            hostname: stats.hostname,
+            query_time: chrono::DateTime::parse_from_rfc3339(&stats.query_time)
+                .map(|t| t.with_timezone(&chrono::Utc))
+                .unwrap_or_else(|_| chrono::Utc::now()),
+            driver_version: stats.driver_version,
+            cuda_version: stats.cuda_version,
+            nvml_version: stats.nvml_version,
+            sample_period: None,
+            query_latency: stats.query_latency,
+            temperature_unit: TempUnit::Celsius,
+            memory_unit: MemUnit::MiB,
+            gpus: stats.gpus.into_iter().map(GpuStat::from).collect(),
+            users: Vec::new(),
+            by_process: Vec::new(),
+        }
+    }
+}
+
+impl From<pb::Gpu> for GpuStat {
+    fn from(g: pb::Gpu) -> Self {
+        const MB: u64 = 1024 * 1024;
+        let error = g.error.map(|e| DeviceError {
+            kind: serde_json::from_value(serde_json::Value::String(e.kind)).unwrap_or(gpustat_core::DeviceErrorKind::Driver),
+            message: e.message,
+        });
+        let processes = g
+            .processes
+            .into_iter()
+            .map(|p| GpuProcessInfo {
+                pid: p.pid,
+                username: p.username,
+                command: p.command,
+                gpu_memory_usage: p.gpu_memory_usage,
+                gpu_memory_percent: p
+                    .gpu_memory_usage
+                    .filter(|_| g.memory_total_bytes > 0)
+                    .map(|m| (m * MB) as f64 / g.memory_total_bytes as f64 * 100.0),
+                cpu_percent: p.cpu_percent,
+                cpu_memory_usage: p.cpu_memory_usage,
+                extra: p.extra,
+                ..Default::default()
+            })
+            .collect();
+        GpuStat {
+            index: g.index,
+            uuid: g.uuid,
+            name: g.name,
+            available: g.available,
+            error,
+            temperature: g.temperature,
+            fan_speed: g.fan_speed,
+            utilization: g.utilization,
+            utilization_enc: g.utilization_enc,
+            utilization_dec: g.utilization_dec,
+            power_draw: g.power_draw,
+            power_limit: g.power_limit,
+            memory_used: g.memory_used_bytes / MB,
+            memory_total: g.memory_total_bytes / MB,
+            memory_free: g.memory_total_bytes.saturating_sub(g.memory_used_bytes) / MB,
+            memory_used_bytes: g.memory_used_bytes,
+            memory_total_bytes: g.memory_total_bytes,
+            processes: g.available.then_some(processes),
+            extra: g.extra,
+            ..Default::default()
+        }
+    }
+}
+
+/// Latest snapshot, or why the last query failed; None before the first query
+type Latest = Option<Result<pb::GpuStatCollection, String>>;
+
+/// Hands each new snapshot to the server
+pub struct Publisher {
+    tx: watch::Sender<Latest>,
+    // Runs the server; lives as long as the publisher
+    _runtime: tokio::runtime::Runtime,
+}
+
+impl Publisher {
+    pub fn publish(&self, stats: Result<&GpuStatCollection, String>) {
+        self.tx.send_replace(Some(stats.map(pb::GpuStatCollection::from)));
+    }
+}
+
+/// Serve whatever is published from then on to callers passing `auth`. Also
+/// returns the listener's address, for port 0
+pub fn start(listener: std::net::TcpListener, auth: Auth) -> Result<(Publisher, SocketAddr), Box<dyn std::error::Error>> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    listener.set_nonblocking(true)?;
+    let listener = {
+        let _guard = runtime.enter();
+        tokio::net::TcpListener::from_std(listener)?
+    };
+    let local = listener.local_addr()?;
+    let (tx, rx) = watch::channel(None);
+    let service = GpuStatServer { latest: rx, auth: Arc::new(auth) };
+    runtime.spawn(async move {
+        let incoming = tokio_stream::wrappers::TcpListenerStream::new(listener);
+        if let Err(e) = tonic::transport::Server::builder().add_service(service).serve_with_incoming(incoming).await {
+            eprintln!("gRPC server error: {}", e);
+            std::process::exit(1);
+        }
+    });
+    Ok((Publisher { tx, _runtime: runtime }, local))
+}
+
+fn select(latest: &Latest, gpu_ids: &[u32]) -> Result<pb::GpuStatCollection, tonic::Status> {
+    match latest {
+        None => Err(tonic::Status::unavailable("no snapshot yet")),
+        Some(Err(e)) => Err(tonic::Status::unavailable(format!("query failed: {}", e))),
+        Some(Ok(stats)) => {
+            let mut stats = stats.clone();
+            if !gpu_ids.is_empty() {
+                stats.gpus.retain(|g| gpu_ids.contains(&g.index));
+            }
+            Ok(stats)
+        }
+    }
+}
+
+#[derive(Clone)]
+struct GpuStatServer {
+    latest: watch::Receiver<Latest>,
+    auth: Arc<Auth>,
+}
+
+impl tonic::server::NamedService for GpuStatServer {
+    const NAME: &'static str = "gpustat.v1.GpuStat";
+}
+
+struct QuerySvc(watch::Receiver<Latest>);
+
+impl tonic::server::UnaryService<pb::QueryRequest> for QuerySvc {
+    type Response = pb::GpuStatCollection;
+    type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+
+    fn call(&mut self, request: tonic::Request<pb::QueryRequest>) -> Self::Future {
+        let result = select(&self.0.borrow(), &request.get_ref().gpu_ids);
+        Box::pin(async move { result.map(tonic::Response::new) })
+    }
+}
+
+struct WatchSvc(watch::Receiver<Latest>);
+
+impl tonic::server::ServerStreamingService<pb::WatchRequest> for WatchSvc {
+    type Response = pb::GpuStatCollection;
+    type ResponseStream = BoxStream<pb::GpuStatCollection>;
+    type Future = BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+
+    fn call(&mut self, request: tonic::Request<pb::WatchRequest>) -> Self::Future {
+        let mut latest = self.0.clone();
+        let gpu_ids = request.into_inner().gpu_ids;
+        let (tx, rx) = mpsc::channel(4);
+        tokio::spawn(async move {
+            latest.mark_changed();
+            // Ends when the client goes away or the server shuts down
+            while latest.changed().await.is_ok() {
+                let item = select(&latest.borrow_and_update(), &gpu_ids);
+                if tx.send(item).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let stream: Self::ResponseStream = Box::pin(tokio_stream::wrappers::ReceiverStream::new(rx));
+        Box::pin(async move { Ok(tonic::Response::new(stream)) })
+    }
+}
+
+impl<B> Service<http::Request<B>> for GpuStatServer
+where
+    B: tonic::codegen::Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let latest = self.latest.clone();
+        if !self.auth.check(req.headers().get("authorization").map(|v| v.as_bytes())) {
+            return Box::pin(async move { Ok(tonic::Status::unauthenticated("missing or wrong credentials").into_http()) });
+        }
+        match req.uri().path() {
+            QUERY_PATH => Box::pin(async move {
+                let mut grpc = tonic::server::Grpc::new(ProstCodec::default());
+                Ok(grpc.unary(QuerySvc(latest), req).await)
+            }),
+            WATCH_PATH => Box::pin(async move {
+                let mut grpc = tonic::server::Grpc::new(ProstCodec::default());
+                Ok(grpc.server_streaming(WatchSvc(latest), req).await)
+            }),
+            _ => Box::pin(async move { Ok(tonic::Status::unimplemented("no such method").into_http()) }),
+        }
+    }
+}
+
+/// `--connect URL`: stats from a `gpustat serve --grpc` server
+pub struct GrpcBackend {
+    runtime: tokio::runtime::Runtime,
+    channel: tonic::transport::Channel,
+    /// `authorization` header sent with every call
+    authorization: Option<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>,
+}
+
+impl GrpcBackend {
+    /// `https://` URLs go through a TLS tunnel (see `tls`), trusting the public
+    /// CAs and `ca`; `auth` supplies the credentials the server asks for
+    pub fn new(url: &str, auth: &Auth, ca: Option<&Path>) -> Result<Self, GpuStatError> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let uri: http::Uri = url.parse().map_err(|e| format!("invalid URL {}: {}", url, e))?;
+        let url = match (uri.scheme_str(), uri.host()) {
+            (Some("https"), Some(host)) => {
+                let name = ServerName::try_from(host.trim_matches(['[', ']']).to_string())
+                    .map_err(|e| format!("invalid host {}: {}", host, e))?;
+                let server = format!("{}:{}", host, uri.port_u16().unwrap_or(443));
+                let local = crate::tls::tunnel(server, name, crate::tls::client_config(ca)?)?;
+                format!("http://{}", local)
+            }
+            _ => url.to_string(),
+        };
+        let authorization = auth
+            .header()
+            .map(|h| h.parse())
+            .transpose()
+            .map_err(|_| "credentials must be printable ASCII")?;
+        let endpoint = tonic::transport::Endpoint::from_shared(url.clone())
+            .map_err(|e| format!("invalid URL {}: {}", url, e))?
+            .connect_timeout(Duration::from_secs(5));
+        // The channel's worker needs a runtime to spawn on
+        let channel = {
+            let _guard = runtime.enter();
+            endpoint.connect_lazy()
+        };
+        Ok(GrpcBackend { runtime, channel, authorization })
+    }
+}
+
+impl GpuBackend for GrpcBackend {
+    fn query(&self, gpu_ids: Option<&[u32]>, _query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        let mut request = tonic::Request::new(pb::QueryRequest { gpu_ids: gpu_ids.map(<[u32]>::to_vec).unwrap_or_default() });
+        if let Some(authorization) = &self.authorization {
+            request.metadata_mut().insert("authorization", authorization.clone());
+        }
+        let response = self.runtime.block_on(async {
+            let mut client = tonic::client::Grpc::new(self.channel.clone());
+            client.ready().await.map_err(|e| tonic::Status::unavailable(e.to_string()))?;
+            client
+                .unary::<_, pb::GpuStatCollection, _>(
+                    request,
+                    http::uri::PathAndQuery::from_static(QUERY_PATH),
+                    ProstCodec::default(),
+                )
+                .await
+        });
+        match response {
+            Ok(stats) => Ok(stats.into_inner().into()),
+            Err(status) => Err(format!("gRPC: {}", status.message()).into()),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Per-GPU sample history kept across watch-mode refreshes
+
+use gpustat_core::{GpuStat, GpuStatCollection};
+use std::collections::{HashMap, VecDeque};
+
+const SPARK_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Ring buffers of recent samples for one GPU
+#[derive(Debug, Clone, Default)]
+pub struct GpuHistory {
+    /// GPU utilization in percent (None when not supported)
+    pub utilization: VecDeque<Option<u32>>,
+    /// Memory used as a fraction of total
+    pub memory: VecDeque<f64>,
+    /// Power draw as a fraction of the enforced limit (None when not supported)
+    pub power: VecDeque<Option<f64>>,
+    /// Energy consumed since recording started (J)
+    pub energy: f64,
+    /// The sample before the most recent one, for change highlighting
+    pub previous: Option<GpuStat>,
+    latest: Option<GpuStat>,
+}
+
+/// Recent samples for every GPU seen, bounded to `capacity` entries each
+#[derive(Debug, Clone)]
+pub struct History {
+    capacity: usize,
+    gpus: HashMap<u32, GpuHistory>,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            gpus: HashMap::new(),
+        }
+    }
+
+    /// Append the current sample of every GPU in `stats`
+    pub fn record(&mut self, stats: &GpuStatCollection) {
+        for gpu in &stats.gpus {
+            let h = self.gpus.entry(gpu.index).or_default();
+            push_bounded(&mut h.utilization, gpu.utilization, self.capacity);
+            let mem = if gpu.memory_total > 0 {
+                gpu.memory_used as f64 / gpu.memory_total as f64
+            } else {
+                0.0
+            };
+            push_bounded(&mut h.memory, mem, self.capacity);
+            let power = match (gpu.power_draw, gpu.power_limit) {
+                (Some(d), Some(l)) if l > 0 => Some(d as f64 / l as f64),
+                _ => None,
+            };
+            push_bounded(&mut h.power, power, self.capacity);
+            h.energy += gpu.energy_delta.unwrap_or(0.0);
+            h.previous = h.latest.replace(gpu.clone());
+        }
+    }
+
+    pub fn gpu(&self, index: u32) -> Option<&GpuHistory> {
+        self.gpus.get(&index)
+    }
+}
+
+fn push_bounded<T>(buf: &mut VecDeque<T>, value: T, capacity: usize) {
+    if buf.len() == capacity {
+        buf.pop_front();
+    }
+    buf.push_back(value);
+}
+
+/// Render values in `[0, max]` as a unicode sparkline; missing samples become spaces
+pub fn sparkline<I: IntoIterator<Item = Option<f64>>>(values: I, max: f64) -> String {
+    values
+        .into_iter()
+        .map(|v| match v {
+            Some(v) if max > 0.0 => {
+                let level = ((v / max).clamp(0.0, 1.0) * (SPARK_CHARS.len() - 1) as f64).round();
+                SPARK_CHARS[level as usize]
+            }
+            Some(_) => SPARK_CHARS[0],
+            None => ' ',
+        })
+        .collect()
+}
@@ -0,0 +1,128 @@
+//! `gpustat kill`: signal GPU processes by PID or owner, shared with the TUI's kill action
+
+use clap::ValueEnum;
+use gpustat_core::{procinfo, GpuStatCollection, QueryOptions};
+use std::io::{self, BufRead, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KillSignal {
+    Term,
+    Kill,
+}
+
+impl KillSignal {
+    pub fn name(self) -> &'static str {
+        match self {
+            KillSignal::Term => "SIGTERM",
+            KillSignal::Kill => "SIGKILL",
+        }
+    }
+
+    fn to_sysinfo(self) -> sysinfo::Signal {
+        match self {
+            KillSignal::Term => sysinfo::Signal::Term,
+            KillSignal::Kill => sysinfo::Signal::Kill,
+        }
+    }
+}
+
+/// A GPU process that may be signalled
+#[derive(Debug, Clone)]
+pub struct Target {
+    /// Host PID
+    pub pid: u32,
+    pub username: Option<String>,
+    pub command: String,
+    pub gpus: Vec<u32>,
+}
+
+/// Refuse to signal another user's process unless running as root; the OS has
+/// the final say, this just gives a clearer message up front
+pub fn check_permission(target: &Target) -> Result<(), String> {
+    let me = procinfo::current_username();
+    if me.as_deref() == Some("root") || (me.is_some() && target.username == me) {
+        Ok(())
+    } else {
+        Err(format!(
+            "process {} belongs to {}, not you",
+            target.pid,
+            target.username.as_deref().unwrap_or("an unknown user")
+        ))
+    }
+}
+
+pub fn send(target: &Target, signal: KillSignal) -> Result<(), String> {
+    check_permission(target)?;
+    procinfo::send_signal(target.pid, signal.to_sysinfo())
+}
+
+/// GPU processes matching any of `pids` or owned by `user`, merged across GPUs
+pub fn targets(stats: &GpuStatCollection, pids: &[u32], user: Option<&str>) -> Vec<Target> {
+    let mut out: Vec<Target> = Vec::new();
+    for gpu in &stats.gpus {
+        let lists = std::iter::once(&gpu.processes).chain(gpu.mig_instances.iter().map(|m| &m.processes));
+        for p in lists.flatten().flatten() {
+            let pid = p.real_pid.unwrap_or(p.pid);
+            let matches = pids.contains(&pid) || pids.contains(&p.pid) || (user.is_some() && p.username.as_deref() == user);
+            if !matches {
+                continue;
+            }
+            match out.iter_mut().find(|t| t.pid == pid) {
+                Some(t) => t.gpus.push(gpu.index),
+                None => out.push(Target { pid, username: p.username.clone(), command: p.command.clone(), gpus: vec![gpu.index] }),
+            }
+        }
+    }
+    out
+}
+
+/// List the matching processes, confirm on stdin (unless `yes`) and signal them
+pub fn run(
+    gpu_ids: Option<&[u32]>,
+    pids: &[u32],
+    user: Option<&str>,
+    signal: KillSignal,
+    yes: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if pids.is_empty() && user.is_none() {
+        return Err("give --pid and/or --user".into());
+    }
+    let nvml = nvml_wrapper::Nvml::init()?;
+    let stats = GpuStatCollection::new_query(&nvml, gpu_ids, &QueryOptions::default())?;
+    let targets = targets(&stats, pids, user);
+    if targets.is_empty() {
+        println!("No matching GPU processes");
+        return Ok(());
+    }
+    for t in &targets {
+        let gpus: Vec<String> = t.gpus.iter().map(|g| g.to_string()).collect();
+        println!(
+            "{:>7}  {:<12}  GPU {:<8}  {}",
+            t.pid,
+            t.username.as_deref().unwrap_or("--"),
+            gpus.join(","),
+            t.command
+        );
+    }
+    if !yes {
+        print!("Send {} to {} process(es)? [y/N] ", signal.name(), targets.len());
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().lock().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("Aborted");
+            return Ok(());
+        }
+    }
+    let mut failed = 0;
+    for t in &targets {
+        if let Err(e) = send(t, signal) {
+            eprintln!("{}", e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} process(es) not signalled", failed, targets.len()).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,184 @@
+//! Snapshot log file with size/time based rotation (`--log-file`, `--tee`)
+//! Rotated files are renamed `<path>.1`, `<path>.2`, ... (oldest has the highest suffix).
+
+use crate::display::{DisplayOptions, Render};
+use gpustat_core::GpuStatCollection;
+use clap::ValueEnum;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Format of snapshots written to the log file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Plain (uncolored) text table, blank line between snapshots
+    Text,
+    /// One compact JSON object per line
+    Json,
+}
+
+/// Rotation policy for the snapshot log
+#[derive(Debug, Clone)]
+pub struct RotationPolicy {
+    /// Rotate once the current file reaches this many bytes
+    pub max_bytes: Option<u64>,
+    /// Rotate once the current file has been open this long
+    pub max_age: Option<Duration>,
+    /// Number of rotated files to keep
+    pub keep: usize,
+}
+
+/// Append-only snapshot log that rotates according to a [`RotationPolicy`]
+pub struct RotatingLog {
+    path: PathBuf,
+    format: LogFormat,
+    policy: RotationPolicy,
+    file: File,
+    written: u64,
+    opened_at: Instant,
+}
+
+impl RotatingLog {
+    /// Open `path` for appending, keeping any existing content
+    pub fn open(path: &Path, format: LogFormat, policy: RotationPolicy) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Self::with_file(path, format, policy, file)
+    }
+
+    /// Create `path` (truncating it) with no rotation, like `tee`
+    pub fn create(path: &Path, format: LogFormat) -> io::Result<Self> {
+        let file = File::create(path)?;
+        let policy = RotationPolicy {
+            max_bytes: None,
+            max_age: None,
+            keep: 0,
+        };
+        Self::with_file(path, format, policy, file)
+    }
+
+    fn with_file(path: &Path, format: LogFormat, policy: RotationPolicy, file: File) -> io::Result<Self> {
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            format,
+            policy,
+            file,
+            written,
+            opened_at: Instant::now(),
+        })
+    }
+
+    /// Append one snapshot, rotating first if the policy says so
+    pub fn write_snapshot(&mut self, stats: &GpuStatCollection, opts: &DisplayOptions) -> io::Result<()> {
+        let entry = match self.format {
+            LogFormat::Text => format!("{}\n", stats.format_table(opts, false, None)),
+            LogFormat::Json => format!("{}\n", serde_json::to_string(stats)?),
+        };
+        self.write_entry(&entry)
+    }
+
+    /// Append `text` as it was shown instead of a snapshot (`--script`, `--select`);
+    /// a JSON log still gets the snapshot
+    pub fn write_shown(&mut self, text: &str, stats: &GpuStatCollection, opts: &DisplayOptions) -> io::Result<()> {
+        match self.format {
+            LogFormat::Text => self.write_entry(&format!("{}\n", text)),
+            LogFormat::Json => self.write_snapshot(stats, opts),
+        }
+    }
+
+    fn write_entry(&mut self, entry: &str) -> io::Result<()> {
+        if self.should_rotate(entry.len() as u64) {
+            self.rotate()?;
+        }
+
+        self.file.write_all(entry.as_bytes())?;
+        self.file.flush()?;
+        self.written += entry.len() as u64;
+        Ok(())
+    }
+
+    fn should_rotate(&self, incoming: u64) -> bool {
+        if self.written == 0 {
+            return false;
+        }
+        let too_big = self
+            .policy
+            .max_bytes
+            .is_some_and(|max| self.written + incoming > max);
+        let too_old = self
+            .policy
+            .max_age
+            .is_some_and(|age| self.opened_at.elapsed() >= age);
+        too_big || too_old
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.policy.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(self.rotated_path(self.policy.keep));
+            for n in (1..self.policy.keep).rev() {
+                let from = self.rotated_path(n);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+        self.opened_at = Instant::now();
+        Ok(())
+    }
+}
+
+/// Parse a human-readable size like `512K`, `10M` or `1G` into bytes
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (digits, mult) = match s.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&s[..s.len() - 1], 1024),
+        Some('M') => (&s[..s.len() - 1], 1024 * 1024),
+        Some('G') => (&s[..s.len() - 1], 1024 * 1024 * 1024),
+        _ => (s, 1),
+    };
+    digits
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(mult))
+        .ok_or_else(|| format!("invalid size '{}' (expected e.g. 512K, 10M, 1G)", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("512K"), Ok(512 * 1024));
+        assert_eq!(parse_size(" 10m "), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("1 G"), Ok(1 << 30));
+    }
+
+    #[test]
+    fn bad_sizes() {
+        for bad in ["", "K", "10X", "-1M", "1.5G"] {
+            assert!(parse_size(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        assert!(parse_size("99999999999G").is_err());
+        assert_eq!(parse_size(&u64::MAX.to_string()), Ok(u64::MAX));
+    }
+}
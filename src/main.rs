@@ -1,31 +1,106 @@
 //! gpustat-rs: A Rust reimplementation of gpustat
 //! Monitor your NVIDIA GPU status, like htop but for GPUs.
 
-mod core;
+mod cluster;
+mod config;
+mod daemon;
 mod display;
-
-use clap::Parser;
-use display::DisplayOptions;
+mod dmon;
+mod events;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod history;
+mod kill;
+mod logfile;
+mod manpage;
+mod mdns;
+mod plugin;
+mod push;
+mod script;
+mod select;
+mod serve;
+mod ssh;
+mod systemd;
+mod theme;
+mod tls;
+mod topo;
+mod tui;
+mod watch;
+
+use clap::builder::BoolishValueParser;
+use clap::{Parser, Subcommand};
+use gpustat_core::{AutoBackend, GpuBackend, GpuStatCollection, MockBackend, ProcSortKey, QueryOptions, SortKey};
+use display::{DisplayOptions, Render, Threshold, Thresholds};
+use history::History;
+use logfile::{LogFormat, RotatingLog, RotationPolicy};
+use select::Selector;
+use std::path::PathBuf;
 use std::process;
 use std::time::Duration;
-use std::thread;
 
 #[derive(Parser)]
 #[command(name = "gpustat")]
-#[command(about = "A monitoring tool for NVIDIA GPUs", long_about = None)]
+#[command(about = "A monitoring tool for NVIDIA GPUs", long_about = None, version = "0.1.0", disable_version_flag = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Comma-separated GPU indices to query (e.g. "0,1,2"). Default: all GPUs.
-    #[arg(long, value_name = "IDS")]
+    #[arg(long, value_name = "IDS", env = "GPUSTAT_ID")]
     id: Option<String>,
 
+    /// Show stats from a JSON snapshot (as printed by --json) instead of querying
+    /// NVML; without FILE, a built-in two-GPU demo
+    #[arg(long, value_name = "FILE")]
+    mock: Option<Option<PathBuf>>,
+
+    /// Query another machine over SSH (its gpustat, else nvidia-smi) and show it here
+    #[arg(long, value_name = "[USER@]HOST", env = "GPUSTAT_SSH", conflicts_with = "mock")]
+    ssh: Option<String>,
+
+    /// Print a systemd unit running this command line (e.g. `gpustat serve
+    /// --generate-systemd-unit`), or the .socket unit activating it
+    #[arg(long, value_enum, value_name = "KIND", num_args = 0..=1, default_missing_value = "service", global = true)]
+    generate_systemd_unit: Option<systemd::UnitKind>,
+
+    /// Read snapshots from a running `gpustat daemon` (default socket
+    /// $XDG_RUNTIME_DIR/gpustat.sock, else /tmp/gpustat-$UID.sock), skipping NVML startup
+    #[arg(long, value_name = "PATH", env = "GPUSTAT_SOCKET", conflicts_with_all = ["mock", "ssh"])]
+    socket: Option<Option<PathBuf>>,
+
+    /// Query a `gpustat serve --grpc` server, e.g. http://node7:9101, or `auto` for
+    /// the first one announced on the LAN
+    #[arg(long, value_name = "URL", env = "GPUSTAT_CONNECT", conflicts_with_all = ["mock", "ssh", "socket"])]
+    connect: Option<String>,
+
+    /// Bearer token for --connect
+    #[arg(long, value_name = "TOKEN", env = "GPUSTAT_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+
+    /// Basic auth credentials for --connect
+    #[arg(long, value_name = "USER:PASS", env = "GPUSTAT_BASIC_AUTH", hide_env_values = true)]
+    basic_auth: Option<String>,
+
+    /// Also trust this CA (PEM) for --connect https://..., e.g. a self-signed server
+    #[arg(long, value_name = "FILE", env = "GPUSTAT_CA_CERT")]
+    ca_cert: Option<PathBuf>,
+
     /// Print as JSON
-    #[arg(long)]
+    #[arg(long, env = "GPUSTAT_JSON", value_parser = BoolishValueParser::new())]
     json: bool,
 
+    /// Print only the selected JSON fields (e.g. "gpus[].memory_used")
+    #[arg(long, value_name = "PATH")]
+    select: Option<String>,
+
     /// Show all: cmd, user, pid, fan, codec, power
     #[arg(short = 'a', long)]
     show_all: bool,
 
+    /// Columns to show, comma-separated; the same as the matching --show-* flags
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "COLUMNS", env = "GPUSTAT_SHOW")]
+    show: Vec<ShowColumn>,
+
     /// Display command name of running process
     #[arg(short = 'c', long)]
     show_cmd: bool,
@@ -34,6 +109,38 @@ struct Args {
     #[arg(short = 'f', long)]
     show_full_cmd: bool,
 
+    /// Display host CPU% and resident memory of running process
+    #[arg(long)]
+    show_cpu: bool,
+
+    /// Display each process's CUDA_VISIBLE_DEVICES (needs permission to read its environment)
+    #[arg(long)]
+    show_visible_devices: bool,
+
+    /// Display how long each process has been running
+    #[arg(long)]
+    show_elapsed: bool,
+
+    /// Hide other users' usernames and commands (shown as ***), keeping their memory
+    #[arg(long)]
+    anonymize: bool,
+
+    /// List each process once with the GPUs it uses and its total memory, instead of per GPU
+    #[arg(long)]
+    by_process: bool,
+
+    /// Also show GPU processes and memory totalled per user (and per GPU)
+    #[arg(long)]
+    by_user: bool,
+
+    /// Only list processes of this user (repeatable); GPU stats are still shown
+    #[arg(long = "user", value_name = "NAME")]
+    users: Vec<String>,
+
+    /// Only list your own processes
+    #[arg(long)]
+    mine: bool,
+
     /// Display username of running process
     #[arg(short = 'u', long)]
     show_user: bool,
@@ -46,7 +153,7 @@ struct Args {
     #[arg(short = 'F', long)]
     show_fan_speed: bool,
 
-    /// Show encoder/decoder utilization [possible values: enc, dec, enc,dec]
+    /// Show encoder/decoder utilization [possible values: enc, dec, enc,dec]; add jpg and/or ofa for those engines
     #[arg(short = 'e', long, value_name = "CODEC")]
     show_codec: Option<Option<String>>,
 
@@ -54,53 +161,440 @@ struct Args {
     #[arg(short = 'P', long, value_name = "POWER")]
     show_power: Option<Option<String>>,
 
-    /// Do not display header
+    /// Show current SM, graphics and memory clocks
+    #[arg(short = 'C', long)]
+    show_clocks: bool,
+
+    /// Show the slowdown temperature threshold next to the temperature
+    #[arg(long)]
+    show_temp_limits: bool,
+
+    /// Show PCIe link generation/width (current vs max) and TX/RX throughput
+    #[arg(long)]
+    show_pcie: bool,
+
+    /// Show DCGM profiling metrics: SM active, SM occupancy, tensor and DRAM activity
+    /// (needs a build with the `dcgm` feature and a running nv-hostengine)
+    #[arg(long)]
+    show_profiling: bool,
+
+    /// Show BAR1 memory used/total
     #[arg(long)]
+    show_bar1: bool,
+
+    /// Show memory reserved by the driver (counted as used, but by no process)
+    #[arg(long)]
+    show_reserved: bool,
+
+    /// Show compute mode (Default/Exclusive/Prohibited), persistence mode and virtualization mode
+    #[arg(long)]
+    show_mode: bool,
+
+    /// Show the Windows driver model (WDDM/TCC/MCDM)
+    #[arg(long)]
+    show_driver_model: bool,
+
+    /// Do not display header
+    #[arg(long, env = "GPUSTAT_NO_HEADER", value_parser = BoolishValueParser::new())]
     no_header: bool,
 
     /// Width for GPU name column (0 to hide)
-    #[arg(long, value_name = "N")]
+    #[arg(long, value_name = "N", env = "GPUSTAT_GPUNAME_WIDTH")]
     gpuname_width: Option<usize>,
 
     /// Do not display running process information
-    #[arg(long)]
+    #[arg(long, env = "GPUSTAT_NO_PROCESSES", value_parser = BoolishValueParser::new())]
     no_processes: bool,
 
     /// Force colored output
-    #[arg(long, alias = "color")]
+    #[arg(long, alias = "color", env = "GPUSTAT_FORCE_COLOR", value_parser = BoolishValueParser::new())]
     force_color: bool,
 
     /// Suppress colored output
-    #[arg(long)]
+    #[arg(long, env = "GPUSTAT_NO_COLOR", value_parser = BoolishValueParser::new())]
     no_color: bool,
 
-    /// Use watch mode; seconds between updates (default: 1.0)
-    #[arg(short = 'i', long = "interval", value_name = "SECONDS")]
-    watch: Option<Option<f64>>,
+    /// Use watch mode; seconds between updates (default: 1.0), or "auto" to adapt to activity
+    #[arg(short = 'i', long = "interval", value_name = "SECONDS", value_parser = watch::parse_interval, env = "GPUSTAT_INTERVAL")]
+    watch: Option<Option<watch::IntervalArg>>,
+
+    /// Also write what is shown to this file (the table, or --script/--select output)
+    #[arg(long, value_name = "FILE")]
+    tee: Option<PathBuf>,
+
+    /// Format of --tee: json writes each snapshot as a JSON line (default: json with --json, else text)
+    #[arg(long, value_enum, value_name = "FORMAT", requires = "tee")]
+    tee_format: Option<LogFormat>,
+
+    /// Also append each snapshot to this file (rotated per --log-max-size/--log-rotate)
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// Format of snapshots written to --log-file
+    #[arg(long, value_enum, default_value = "text", value_name = "FORMAT")]
+    log_format: LogFormat,
+
+    /// Rotate the log file once it exceeds this size (e.g. 512K, 10M, 1G)
+    #[arg(long, value_name = "SIZE", value_parser = logfile::parse_size)]
+    log_max_size: Option<u64>,
+
+    /// Rotate the log file after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    log_rotate: Option<f64>,
+
+    /// Number of rotated log files to keep
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    log_keep: usize,
+
+    /// Draw utilization and memory as bar gauges
+    #[arg(long)]
+    bars: bool,
+
+    /// Add a column with used memory as a percentage gauge
+    #[arg(long)]
+    mem_bar: bool,
+
+    /// One short line per GPU: index, util %, memory %, temperature
+    #[arg(long)]
+    compact: bool,
+
+    /// In watch mode, show sparklines of the last N samples (default: 10)
+    #[arg(long, value_name = "N")]
+    sparkline: Option<Option<usize>>,
+
+    /// In watch mode, highlight values and processes that changed since the previous sample
+    #[arg(long)]
+    highlight_changes: bool,
+
+    /// In watch mode, warn about GPUs whose memory grows faster than this (MiB/s)
+    #[arg(long, value_name = "MIB_PER_S")]
+    alert_mem_growth: Option<f64>,
+
+    /// Show a footer line with totals across all GPUs
+    #[arg(long)]
+    summary: bool,
+
+    /// Collapse idle GPUs (0% util, no processes) into a single summary line
+    #[arg(long)]
+    collapse_idle: bool,
+
+    /// Sort GPUs by metric (busiest/hottest first)
+    #[arg(long, value_enum, value_name = "KEY", env = "GPUSTAT_SORT")]
+    sort: Option<SortKey>,
+
+    /// Sort processes on each GPU (largest memory first with `mem`)
+    #[arg(long, value_enum, value_name = "KEY")]
+    sort_procs: Option<ProcSortKey>,
+
+    /// Only show the N largest processes (by memory) on each GPU, with a "+k more" suffix
+    #[arg(long, value_name = "N")]
+    top_procs: Option<usize>,
+
+    /// Temperature color thresholds in °C: WARN[,CRIT] (default: 50)
+    #[arg(long, value_name = "WARN[,CRIT]")]
+    temp_thresholds: Option<Threshold>,
+
+    /// Utilization color thresholds in %: WARN[,CRIT] (default: 30)
+    #[arg(long, value_name = "WARN[,CRIT]")]
+    util_thresholds: Option<Threshold>,
+
+    /// Fan speed color thresholds in %: WARN[,CRIT] (default: 30)
+    #[arg(long, value_name = "WARN[,CRIT]")]
+    fan_thresholds: Option<Threshold>,
+
+    /// Power color thresholds in % of the power limit: WARN[,CRIT] (default: 40)
+    #[arg(long, value_name = "WARN[,CRIT]")]
+    power_thresholds: Option<Threshold>,
+
+    /// Encoder/decoder color thresholds in %: WARN[,CRIT] (default: 50)
+    #[arg(long, value_name = "WARN[,CRIT]")]
+    codec_thresholds: Option<Threshold>,
+
+    /// Memory (HBM) temperature color thresholds in °C: WARN[,CRIT] (default: 85,95)
+    #[arg(long, value_name = "WARN[,CRIT]")]
+    mem_temp_thresholds: Option<Threshold>,
+
+    /// Color theme
+    #[arg(long, value_enum, value_name = "THEME", env = "GPUSTAT_THEME")]
+    theme: Option<theme::ThemeName>,
+
+    /// Color temperature/utilization/memory on a green-yellow-red gradient (needs a truecolor terminal)
+    #[arg(long)]
+    gradient: bool,
+
+    /// Temperature unit for display and JSON [possible values: C, F]
+    #[arg(long, value_name = "UNIT", default_value = "C", env = "GPUSTAT_TEMP_UNIT")]
+    temp_unit: gpustat_core::TempUnit,
+
+    /// Fit output into this many columns (default: terminal width when stdout is a terminal)
+    #[arg(long, value_name = "COLS", env = "GPUSTAT_WIDTH")]
+    width: Option<usize>,
+
+    /// Memory unit for display and JSON [possible values: MiB, GiB, %]
+    #[arg(long, value_name = "UNIT", default_value = "MiB", env = "GPUSTAT_MEM_UNIT")]
+    mem_unit: gpustat_core::MemUnit,
+
+    /// Include serial, vBIOS, board part number and PCI IDs in JSON output
+    #[arg(long)]
+    inventory: bool,
+
+    /// Config file to read (default: /etc/gpustat.toml, then ~/.config/gpustat/config.toml)
+    #[arg(long, value_name = "PATH", env = "GPUSTAT_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Rhai script run on each snapshot: filter GPUs/processes, add columns to
+    /// `extra`, or print custom output instead of the table
+    #[arg(long, value_name = "FILE", env = "GPUSTAT_SCRIPT")]
+    script: Option<PathBuf>,
+
+    /// In watch mode, append each snapshot instead of clearing the screen
+    #[arg(long)]
+    no_clear: bool,
+
+    /// Exit watch mode after N samples (implies --interval)
+    #[arg(long, value_name = "N")]
+    count: Option<u64>,
+
+    /// Log failed NVML calls and /proc scans to stderr (RUST_LOG takes precedence)
+    #[arg(long, env = "GPUSTAT_DEBUG", value_parser = BoolishValueParser::new())]
+    debug: bool,
 
     /// Print version
     #[arg(short = 'v', long)]
     version: bool,
 }
 
+/// A column of `--show` / `GPUSTAT_SHOW`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ShowColumn {
+    All,
+    Cmd,
+    FullCmd,
+    User,
+    Pid,
+    Cpu,
+    Elapsed,
+    Fan,
+    Codec,
+    Power,
+    Clocks,
+    TempLimits,
+    Pcie,
+    Profiling,
+    Bar1,
+    Reserved,
+    Mode,
+}
+
+impl Args {
+    /// Turn on the --show-* flag of every `--show` column
+    fn apply_show(&mut self) {
+        for column in self.show.clone() {
+            match column {
+                ShowColumn::All => self.show_all = true,
+                ShowColumn::Cmd => self.show_cmd = true,
+                ShowColumn::FullCmd => self.show_full_cmd = true,
+                ShowColumn::User => self.show_user = true,
+                ShowColumn::Pid => self.show_pid = true,
+                ShowColumn::Cpu => self.show_cpu = true,
+                ShowColumn::Elapsed => self.show_elapsed = true,
+                ShowColumn::Fan => self.show_fan_speed = true,
+                ShowColumn::Codec => {
+                    self.show_codec.get_or_insert(None);
+                }
+                ShowColumn::Power => {
+                    self.show_power.get_or_insert(None);
+                }
+                ShowColumn::Clocks => self.show_clocks = true,
+                ShowColumn::TempLimits => self.show_temp_limits = true,
+                ShowColumn::Pcie => self.show_pcie = true,
+                ShowColumn::Profiling => self.show_profiling = true,
+                ShowColumn::Bar1 => self.show_bar1 = true,
+                ShowColumn::Reserved => self.show_reserved = true,
+                ShowColumn::Mode => self.show_mode = true,
+            }
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Interactive terminal UI with per-GPU panels and a process table
+    Tui {
+        /// Minutes of history kept for the chart view
+        #[arg(long, value_name = "MINUTES", default_value_t = 5.0)]
+        history: f64,
+    },
+    /// Compact rows of interval-averaged samples per GPU, like `nvidia-smi dmon`
+    Dmon,
+    /// GPU-to-GPU connection matrix and CPU affinity, like `nvidia-smi topo -m`
+    Topo {
+        /// Output the matrix as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Send SIGTERM (or SIGKILL) to GPU processes by PID or owner, after confirmation
+    Kill {
+        /// Process to signal (repeatable)
+        #[arg(long = "pid", value_name = "PID")]
+        pids: Vec<u32>,
+        /// Signal every GPU process of this user
+        #[arg(long, value_name = "NAME")]
+        user: Option<String>,
+        #[arg(long, value_enum, default_value = "term")]
+        signal: kill::KillSignal,
+        /// Don't ask for confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+    /// Stream XID errors and ECC events as timestamped lines (Linux only)
+    Events {
+        /// Print each event as a JSON object, one per line
+        #[arg(long)]
+        json: bool,
+    },
+    /// Dashboard of many `gpustat serve` nodes: per-GPU heat cells, Enter to open a node
+    Cluster {
+        /// Nodes as HOST, HOST:PORT or URL (default port 9101)
+        #[arg(value_name = "NODE")]
+        nodes: Vec<String>,
+        /// Read more nodes from FILE, one per line
+        #[arg(long, value_name = "FILE")]
+        hosts: Option<PathBuf>,
+        /// Add the `gpustat serve` agents announced on the LAN (mDNS)
+        #[arg(long)]
+        discover: bool,
+        /// Send `Authorization: Bearer TOKEN` to every node
+        #[arg(long, value_name = "TOKEN", env = "GPUSTAT_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+    /// Print the gpustat(1) man page (roff) to stdout, e.g. `gpustat man > gpustat.1`
+    Man,
+    /// Serve the latest snapshot over HTTP: /json, /metrics (Prometheus) and /healthz,
+    /// refreshed every --interval (default 1s)
+    Serve {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = serve::DEFAULT_LISTEN)]
+        listen: String,
+        /// Serve the gpustat.v1 gRPC service (proto/gpustat.proto) instead of HTTP
+        #[arg(long)]
+        grpc: bool,
+        /// Don't announce this agent on the LAN (mDNS `_gpustat._tcp`)
+        #[arg(long)]
+        no_mdns: bool,
+        /// Serve over TLS with this certificate chain (PEM)
+        #[arg(long, value_name = "FILE", requires = "tls_key")]
+        tls_cert: Option<PathBuf>,
+        /// Private key for --tls-cert (PEM)
+        #[arg(long, value_name = "FILE", requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+        /// Require `Authorization: Bearer TOKEN` (prefer the environment variable;
+        /// arguments are visible to other users)
+        #[arg(long, value_name = "TOKEN", env = "GPUSTAT_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+        /// Require HTTP basic auth with these credentials
+        #[arg(long, value_name = "USER:PASS", env = "GPUSTAT_BASIC_AUTH", hide_env_values = true)]
+        basic_auth: Option<String>,
+    },
+    /// Keep NVML initialized and answer `gpustat --socket` clients on a unix socket
+    Daemon {
+        /// Socket to listen on [default: $XDG_RUNTIME_DIR/gpustat.sock, else /tmp/gpustat-$UID.sock]
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+    /// POST snapshots as JSON to a central collector every --interval (default 10s),
+    /// retrying with backoff while it is unreachable
+    Push {
+        /// Collector endpoint
+        #[arg(long, value_name = "URL")]
+        to: String,
+        /// Snapshots per request
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        batch: usize,
+    },
+}
+
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    args.apply_show();
+    init_tracing(args.debug);
 
     if args.version {
         println!("gpustat-rs 0.1.0");
         return;
     }
 
+    if let Some(kind) = args.generate_systemd_unit {
+        let (command, listen) = match &args.command {
+            Some(Command::Serve { listen, .. }) => ("serve", Some(listen.clone())),
+            // A system daemon has no XDG_RUNTIME_DIR; clients then need --socket /run/gpustat.sock
+            Some(Command::Daemon { socket }) => {
+                ("daemon", Some(socket.as_ref().map_or("/run/gpustat.sock".to_string(), |p| p.display().to_string())))
+            }
+            Some(Command::Push { .. }) => ("push", None),
+            _ => ("agent", None),
+        };
+        match systemd::unit(kind, command, listen.as_deref()) {
+            Ok(unit) => print!("{}", unit),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(Command::Man) = args.command {
+        if let Err(e) = manpage::run() {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // An explicit --config replaces both the system and the user file
+    let config_paths = match args.config.clone() {
+        Some(path) => vec![path],
+        None => std::iter::once(PathBuf::from(config::SYSTEM_PATH)).chain(config::default_path()).collect(),
+    };
+    let config = match config::load_layered(&config_paths) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            eprintln!("Error: invalid config file {}", e);
+            process::exit(1);
+        }
+    };
+    config.apply(&mut args);
+
     if args.force_color && args.no_color {
         eprintln!("Error: --force-color and --no-color cannot be used together");
         process::exit(1);
     }
 
-    if args.json && args.watch.is_some() {
+    // --count alone implies watch mode with the default interval
+    let watch_arg = match (args.watch, args.count) {
+        (None, Some(_)) => Some(None),
+        (w, _) => w,
+    };
+
+    if args.json && watch_arg.is_some() {
         eprintln!("Error: --json and --interval cannot be used together");
         process::exit(1);
     }
 
+    if args.select.is_some() && watch_arg.is_some() {
+        eprintln!("Error: --select and --interval cannot be used together");
+        process::exit(1);
+    }
+
+    let selector = match args.select.as_deref().map(Selector::parse).transpose() {
+        Ok(sel) => sel,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+
     // Parse GPU IDs
     let gpu_ids: Option<Vec<u32>> = args.id.as_ref().map(|s| {
         s.split(',')
@@ -108,22 +602,99 @@ fn main() {
             .collect()
     });
 
+    // Thresholds: defaults, then config file, then command line
+    let mut thresholds = Thresholds::default();
+    config.thresholds.apply(&mut thresholds);
+    let cli_thresholds = [
+        (&mut thresholds.temp, args.temp_thresholds),
+        (&mut thresholds.util, args.util_thresholds),
+        (&mut thresholds.fan, args.fan_thresholds),
+        (&mut thresholds.power, args.power_thresholds),
+        (&mut thresholds.codec, args.codec_thresholds),
+        (&mut thresholds.mem_temp, args.mem_temp_thresholds),
+    ];
+    for (slot, value) in cli_thresholds {
+        if let Some(v) = value {
+            *slot = v;
+        }
+    }
+
+    if args.gradient && !theme::supports_truecolor() {
+        eprintln!("Warning: --gradient ignored, terminal does not advertise truecolor (COLORTERM=truecolor)");
+    }
+
+    let query = QueryOptions {
+        inventory: args.inventory,
+        // Only when shown or handed on as JSON: the throughput counters are slow to sample
+        pcie: args.show_pcie
+            || args.json
+            || args.select.is_some()
+            || args.script.is_some()
+            || (args.log_file.is_some() && args.log_format == LogFormat::Json)
+            || (args.tee.is_some() && args.tee_format == Some(LogFormat::Json))
+            || matches!(args.command, Some(Command::Serve { .. } | Command::Push { .. })),
+    };
+
     // Build display options
     let mut opts = DisplayOptions {
         show_cmd: args.show_cmd || args.show_all,
         show_user: args.show_user || args.show_all,
+        anonymize: args.anonymize,
+        by_user: args.by_user,
+        by_process: args.by_process,
+        users: {
+            let mut users = args.users.clone();
+            if args.mine {
+                users.extend(gpustat_core::procinfo::current_username());
+            }
+            users
+        },
+        show_cpu: args.show_cpu,
+        show_elapsed: args.show_elapsed,
+        show_visible_devices: args.show_visible_devices,
+        show_full_cmd: args.show_full_cmd,
         show_pid: args.show_pid || args.show_all,
         show_fan_speed: args.show_fan_speed || args.show_all,
         show_codec: args.show_codec.is_some() || args.show_all,
+        show_jpg_ofa: args
+            .show_codec
+            .as_ref()
+            .and_then(|c| c.as_deref())
+            .is_some_and(|c| c.split(',').any(|e| e == "jpg" || e == "ofa")),
         show_power: args.show_power.is_some() || args.show_all,
+        show_clocks: args.show_clocks,
+        show_temp_limits: args.show_temp_limits,
+        show_pcie: args.show_pcie,
+        show_profiling: args.show_profiling,
+        show_bar1: args.show_bar1,
+        show_reserved: args.show_reserved,
+        show_mode: args.show_mode,
+        show_driver_model: args.show_driver_model,
         show_power_limit: args.show_power.as_ref()
             .map(|o| o.as_ref().map(|s| s.contains("limit")).unwrap_or(true))
             .unwrap_or(args.show_all),
-        no_processes: args.no_processes,
+        // --by-process lists processes below the table instead of on each GPU line
+        no_processes: args.no_processes || args.by_process,
         no_header: args.no_header,
         gpuname_width: args.gpuname_width,
         force_color: args.force_color,
         no_color: args.no_color,
+        sparkline: args.sparkline.is_some(),
+        bars: args.bars,
+        mem_bar: args.mem_bar,
+        compact: args.compact,
+        highlight_changes: args.highlight_changes,
+        summary: args.summary,
+        collapse_idle: args.collapse_idle,
+        sort: args.sort,
+        sort_procs: args.sort_procs,
+        top_procs: args.top_procs,
+        thresholds,
+        theme: config.theme.resolve(args.theme),
+        gradient: args.gradient && theme::supports_truecolor(),
+        temp_unit: args.temp_unit,
+        mem_unit: args.mem_unit,
+        width: args.width,
     };
 
     // Handle show_power: "draw", "limit", "draw,limit"
@@ -135,52 +706,309 @@ fn main() {
             .unwrap_or(true);
     }
 
-    let interval = match args.watch {
-        Some(Some(secs)) => Duration::from_secs_f64(secs.max(0.1)),
+    let interval = match watch_arg {
+        Some(Some(watch::IntervalArg::Seconds(secs))) => Duration::from_secs_f64(secs.max(0.1)),
+        Some(Some(watch::IntervalArg::Auto)) => watch::AUTO_MIN_INTERVAL,
         Some(None) => Duration::from_secs_f64(1.0),
         None => Duration::ZERO,
     };
 
     let run_once = interval == Duration::ZERO;
 
-    loop {
-        match run_gpustat(&gpu_ids, &opts, args.json) {
-            Ok(()) => {}
+    let mut backend: Box<dyn GpuBackend> = match &args.mock {
+        None => match (&args.ssh, &args.socket, &args.connect) {
+            (Some(target), _, _) => Box::new(ssh::SshBackend::new(target)),
+            (None, Some(path), _) => {
+                Box::new(daemon::SocketBackend::new(&path.clone().unwrap_or_else(daemon::default_socket)))
+            }
+            (None, None, Some(url)) => connect(url, &args),
+            (None, None, None) => Box::new(AutoBackend::detect()),
+        },
+        Some(None) => Box::new(MockBackend::default()),
+        Some(Some(path)) => match MockBackend::load(path) {
+            Ok(mock) => Box::new(mock),
             Err(e) => {
-                eprintln!("Error querying NVIDIA devices: {}", e);
+                eprintln!("Error loading mock snapshot {}: {}", path.display(), e);
+                process::exit(1);
+            }
+        },
+    };
+    if !config.plugins.is_empty() {
+        match plugin::load_all(&config.plugins) {
+            Ok(plugins) => backend = Box::new(plugin::PluginBackend { inner: backend, plugins }),
+            Err(e) => {
+                eprintln!("Error loading {}", e);
                 process::exit(1);
             }
         }
+    }
 
-        if run_once {
-            break;
+    if let Some(Command::Topo { json }) = args.command {
+        if let Err(e) = run_topo(gpu_ids.as_deref(), json) {
+            eprintln!("Error querying NVIDIA devices: {}", e);
+            process::exit(1);
         }
+        return;
+    }
+
+    if let Some(Command::Kill { pids, user, signal, yes }) = &args.command {
+        if let Err(e) = kill::run(gpu_ids.as_deref(), pids, user.as_deref(), *signal, *yes) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(Command::Events { json }) = args.command {
+        if let Err(e) = events::run(gpu_ids.as_deref(), json) {
+            eprintln!("Error querying NVIDIA devices: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(Command::Dmon) = args.command {
+        let delay = if run_once { Duration::from_secs_f64(1.0) } else { interval };
+        if let Err(e) = dmon::run(gpu_ids.as_deref(), delay, args.count) {
+            eprintln!("Error querying NVIDIA devices: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
 
-        thread::sleep(interval);
+    if let Some(Command::Tui { history }) = args.command {
+        let refresh = if run_once { Duration::from_secs_f64(1.0) } else { interval };
+        let window = Duration::from_secs_f64(history.max(0.0) * 60.0);
+        if let Err(e) = tui::run(backend.as_ref(), gpu_ids.as_deref(), refresh, window) {
+            eprintln!("Error querying NVIDIA devices: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(Command::Serve { listen, grpc, no_mdns, tls_cert, tls_key, token, basic_auth }) = &args.command {
+        // Serving works without it, e.g. on hosts without multicast
+        let _mdns = (!no_mdns)
+            .then(|| mdns::advertise(listen, *grpc, tls_cert.is_some()))
+            .and_then(|r| r.map_err(|e| eprintln!("Warning: not announced on the LAN: {}", e)).ok());
+        let refresh = if run_once { Duration::from_secs_f64(1.0) } else { interval };
+        let listen = serve::Listen {
+            addr: listen.clone(),
+            grpc: *grpc,
+            tls: tls_cert.clone().zip(tls_key.clone()),
+            auth: serve::Auth::new(token.as_deref(), basic_auth.as_deref()),
+        };
+        if let Err(e) = serve::run(backend.as_ref(), gpu_ids.as_deref(), &query, &opts, listen, refresh) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
 
-        // Clear screen for watch mode (cursor to 0,0 and clear)
-        if !args.json {
-            print!("\x1b[H\x1b[J");
+    if let Some(Command::Daemon { socket }) = &args.command {
+        // Reused for clients asking within this long of the last query
+        let max_age = if run_once { Duration::from_secs_f64(0.5) } else { interval };
+        let path = socket.clone().unwrap_or_else(daemon::default_socket);
+        if let Err(e) = daemon::run(backend.as_ref(), &path, max_age) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
         }
+        return;
     }
+
+    if let Some(Command::Push { to, batch }) = &args.command {
+        let refresh = if run_once { Duration::from_secs_f64(10.0) } else { interval };
+        if let Err(e) = push::run(backend.as_ref(), gpu_ids.as_deref(), &query, &opts, to, refresh, *batch) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(Command::Cluster { nodes, hosts, discover, token }) = &args.command {
+        let mut nodes = nodes.clone();
+        if let Some(path) = hosts {
+            match cluster::read_hosts(path) {
+                Ok(more) => nodes.extend(more),
+                Err(e) => {
+                    eprintln!("Error reading {}: {}", path.display(), e);
+                    process::exit(1);
+                }
+            }
+        }
+        if *discover {
+            match mdns::discover(false, mdns::DISCOVER_TIMEOUT) {
+                Ok(found) => nodes.extend(found.into_iter().filter(|n| !nodes.contains(n)).collect::<Vec<_>>()),
+                Err(e) => eprintln!("Warning: discovery failed: {}", e),
+            }
+        }
+        if nodes.is_empty() {
+            eprintln!("Error: no nodes given (NODE arguments, --hosts FILE or --discover)");
+            process::exit(1);
+        }
+        let refresh = if run_once { Duration::from_secs_f64(2.0) } else { interval };
+        if let Err(e) = cluster::run(&nodes, token.as_deref(), refresh) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let mut logs = Vec::new();
+    if let Some(path) = &args.log_file {
+        let policy = RotationPolicy {
+            max_bytes: args.log_max_size,
+            max_age: args.log_rotate.map(|s| Duration::from_secs_f64(s.max(1.0))),
+            keep: args.log_keep,
+        };
+        match RotatingLog::open(path, args.log_format, policy) {
+            Ok(log) => logs.push(log),
+            Err(e) => {
+                eprintln!("Error opening log file {}: {}", path.display(), e);
+                process::exit(1);
+            }
+        }
+    }
+    let mut tee = args.tee.as_ref().map(|path| {
+        let format = args.tee_format.unwrap_or(if args.json { LogFormat::Json } else { LogFormat::Text });
+        RotatingLog::create(path, format).unwrap_or_else(|e| {
+            eprintln!("Error opening tee file {}: {}", path.display(), e);
+            process::exit(1);
+        })
+    });
+
+    let script = match args.script.as_deref().map(script::Script::load).transpose() {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!("Error loading script {}", e);
+            process::exit(1);
+        }
+    };
+
+    if !run_once {
+        let cfg = watch::WatchConfig {
+            gpu_ids,
+            query,
+            opts,
+            interval,
+            adaptive: matches!(watch_arg, Some(Some(watch::IntervalArg::Auto))),
+            count: args.count,
+            no_clear: args.no_clear,
+            logs,
+            tee,
+            history: History::new(args.sparkline.flatten().unwrap_or(10)),
+            alert_mem_growth: args.alert_mem_growth.map(|mib| mib * 1024.0 * 1024.0),
+            script,
+        };
+        if let Err(e) = watch::run(backend.as_ref(), cfg) {
+            eprintln!("Error querying NVIDIA devices: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let result = backend
+        .query(gpu_ids.as_deref(), &query)
+        .map_err(Into::into)
+        .and_then(|stats| run_gpustat(stats, &opts, args.json, selector.as_ref(), script.as_ref(), &mut logs, tee.as_mut()));
+    if let Err(e) = result {
+        eprintln!("Error querying NVIDIA devices: {}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(feature = "grpc")]
+fn connect(url: &str, args: &Args) -> Box<dyn GpuBackend> {
+    let url = if url == "auto" {
+        match mdns::discover(true, mdns::DISCOVER_TIMEOUT).map(|nodes| nodes.into_iter().next()) {
+            Ok(Some(node)) => node,
+            Ok(None) => {
+                eprintln!("Error: no `gpustat serve --grpc` agent announced on the LAN");
+                process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            }
+        }
+    } else {
+        url.to_string()
+    };
+    let auth = serve::Auth::new(args.token.as_deref(), args.basic_auth.as_deref());
+    match grpc::GrpcBackend::new(&url, &auth, args.ca_cert.as_deref()) {
+        Ok(backend) => Box::new(backend),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(feature = "grpc"))]
+fn connect(_url: &str, _args: &Args) -> Box<dyn GpuBackend> {
+    eprintln!("Error: --connect needs gRPC support (build with --features grpc)");
+    process::exit(1);
+}
+
+/// Send `tracing` events to stderr when asked for with --debug or RUST_LOG
+fn init_tracing(debug: bool) {
+    use tracing_subscriber::EnvFilter;
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) if debug => EnvFilter::new("gpustat_core=debug,gpustat=debug"),
+        Err(_) => return,
+    };
+    tracing_subscriber::fmt().with_env_filter(filter).with_writer(std::io::stderr).init();
+}
+
+fn run_topo(gpu_ids: Option<&[u32]>, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let nvml = nvml_wrapper::Nvml::init()?;
+    let topology = topo::Topology::query(&nvml, gpu_ids)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&topology)?);
+    } else {
+        print!("{}", topology.format_matrix());
+    }
+    Ok(())
 }
 
 fn run_gpustat(
-    gpu_ids: &Option<Vec<u32>>,
+    mut stats: GpuStatCollection,
     opts: &DisplayOptions,
     json: bool,
+    selector: Option<&Selector>,
+    script: Option<&script::Script>,
+    logs: &mut [RotatingLog],
+    tee: Option<&mut RotatingLog>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let nvml = nvml_wrapper::Nvml::init()?;
-    let stats = core::GpuStatCollection::new_query(
-        &nvml,
-        gpu_ids.as_deref(),
-    )?;
+    stats.apply_options(opts);
+    let mut printed = match script {
+        Some(script) => script.run(&mut stats)?,
+        None => None,
+    };
+    if let (None, Some(selector)) = (&printed, selector) {
+        let value = serde_json::to_value(&stats)?;
+        let lines: String = selector.apply(&value).iter().map(|v| format!("{}\n", select::format_value(v))).collect();
+        printed = Some(lines);
+    }
 
-    if json {
+    if let Some(text) = &printed {
+        print!("{}", text);
+    } else if json {
         println!("{}", serde_json::to_string_pretty(&stats)?);
     } else {
         stats.print_formatted(opts)?;
     }
 
+    for log in logs {
+        log.write_snapshot(&stats, opts)?;
+    }
+    if let Some(tee) = tee {
+        match &printed {
+            Some(text) => tee.write_shown(text, &stats, opts)?,
+            None => tee.write_snapshot(&stats, opts)?,
+        }
+    }
+
     Ok(())
 }
@@ -0,0 +1,84 @@
+//! `gpustat man`: the gpustat(1) manual page, generated from the CLI definition
+//! so it lists exactly the flags this build accepts, plus hand-written sections
+//! on the output format, the JSON fields, the environment and config files
+
+use clap::CommandFactory;
+use clap_mangen::Man;
+use std::io::{self, Write};
+
+/// Everything clap_mangen can't know: what the columns and JSON keys mean
+const OUTPUT_SECTIONS: &str = r#".SH OUTPUT
+The first line shows the host name, the query time, and the driver, CUDA and NVML versions.
+Each GPU then gets one line:
+.PP
+.RS
+[index] name | temp, util % | used / total MB | user:command(memory) ...
+.RE
+.PP
+Optional columns (fan speed, codec and power, clocks, PCIe, profiling, BAR1, modes) are
+inserted before the memory column when their \fB\-\-show\-*\fR flag is given.
+A GPU that could not be queried is listed as \fB((Error: \fIreason\fB))\fR.
+.SH JSON OUTPUT
+\fB\-\-json\fR prints one object per snapshot:
+.TP
+\fBhostname\fR, \fBquery_time\fR, \fBdriver_version\fR, \fBcuda_version\fR, \fBnvml_version\fR
+Host and driver identification; \fBquery_time\fR is RFC 3339.
+.TP
+\fBquery_latency\fR
+Seconds the query took.
+.TP
+\fBtemperature_unit\fR, \fBmemory_unit\fR
+Units of the per-GPU temperatures ("C" or "F") and of the optional memory fields ("MiB", "GiB" or "%").
+.TP
+\fBgpus\fR
+One object per GPU: \fBindex\fR, \fBuuid\fR, \fBname\fR, \fBtemperature\fR, \fBfan_speed\fR (%),
+\fButilization\fR, \fButilization_enc\fR, \fButilization_dec\fR (%), \fBpower_draw\fR and
+\fBpower_limit\fR (W), \fBenergy_total\fR (mJ), \fBclock_sm\fR, \fBclock_mem\fR, \fBclock_graphics\fR (MHz),
+\fBpcie_gen\fR, \fBpcie_width\fR, \fBpcie_tx\fR, \fBpcie_rx\fR (KB/s), \fBmemory_used\fR,
+\fBmemory_total\fR, \fBmemory_free\fR (MiB), \fBmemory_used_bytes\fR, \fBmemory_total_bytes\fR,
+\fBprocesses\fR and \fBavailable\fR.
+Columns added by plugins are in \fBextra\fR, keyed by plugin name.
+Unavailable GPUs carry \fBerror\fR with a \fBkind\fR (gpu_lost, reset_required, no_permission,
+not_supported, timeout or driver) and a \fBmessage\fR.
+Fields the GPU doesn't report are null or left out; \fBunsupported\fR lists the NVML calls the GPU
+or driver doesn't support (e.g. \fBfan_speed\fR), telling those apart from calls that failed.
+.TP
+\fBgpus[].processes\fR
+One object per process: \fBpid\fR, \fBusername\fR, \fBcommand\fR, \fBgpu_memory_usage\fR (MiB),
+\fBgpu_memory_percent\fR, \fBsm_util\fR, \fBmem_util\fR (%), and with \fB\-\-show\-full\-cmd\fR or
+\fB\-\-show\-cpu\fR \fBfull_command\fR, \fBcpu_percent\fR and \fBcpu_memory_usage\fR (bytes).
+\fBslurm_job\fR and \fBcontainer\fR are set for processes of a Slurm job or a container.
+.TP
+\fBusers\fR, \fBby_process\fR
+Per-user and per-process totals, with \fB\-\-by\-user\fR and \fB\-\-by\-process\fR only.
+.SH ENVIRONMENT
+Most options can be set through \fBGPUSTAT_*\fR variables, listed with each option above;
+command-line flags take precedence.
+\fBGPUSTAT_SHOW\fR takes the same column list as \fB\-\-show\fR, e.g. \fBGPUSTAT_SHOW=cmd,user,power\fR.
+.SH FILES
+.TP
+\fI/etc/gpustat.toml\fR
+System-wide defaults.
+.TP
+\fI$XDG_CONFIG_HOME/gpustat/config.toml\fR (\fI~/.config/gpustat/config.toml\fR)
+Per-user defaults, overriding the system file key by key.
+Sections: \fB[display]\fR (long option names without dashes, e.g. \fBshow_cmd = true\fR),
+\fB[watch]\fR (\fBinterval\fR, \fBsparkline\fR, \fBhighlight_changes\fR, \fBno_clear\fR,
+\fBalert_mem_growth\fR), \fB[thresholds]\fR, \fB[theme]\fR and \fB[[plugins]]\fR
+(\fBname\fR and \fBpath\fR of a column plugin library).
+"#;
+
+/// Write gpustat(1) in roff to stdout
+pub fn run() -> io::Result<()> {
+    let man = Man::new(crate::Args::command());
+    let mut out = io::stdout().lock();
+    man.render_title(&mut out)?;
+    man.render_name_section(&mut out)?;
+    man.render_synopsis_section(&mut out)?;
+    man.render_description_section(&mut out)?;
+    man.render_options_section(&mut out)?;
+    man.render_subcommands_section(&mut out)?;
+    out.write_all(OUTPUT_SECTIONS.as_bytes())?;
+    man.render_version_section(&mut out)?;
+    out.flush()
+}
@@ -0,0 +1,73 @@
+//! Zeroconf: `gpustat serve` announces itself as `_gpustat._tcp` on the LAN so
+//! `gpustat cluster --discover` and `--connect auto` find agents without a host list
+//!
+//! The TXT record says which protocol the agent speaks (`proto=http` or
+//! `proto=grpc`) and whether over TLS (`tls=1`); discovery only returns agents
+//! of the protocol asked for.
+
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+const SERVICE_TYPE: &str = "_gpustat._tcp.local.";
+
+/// How long `--discover` listens for answers
+pub const DISCOVER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Announce an agent listening on `listen`; it stays announced while the
+/// returned daemon lives
+pub fn advertise(listen: &str, grpc: bool, tls: bool) -> Result<ServiceDaemon, String> {
+    let addr: SocketAddr = listen.parse().map_err(|e| format!("mDNS: bad address {}: {}", listen, e))?;
+    let host = hostname::get().map(|h| h.to_string_lossy().into_owned()).unwrap_or_else(|_| "gpustat".into());
+    let daemon = ServiceDaemon::new().map_err(|e| format!("mDNS: {}", e))?;
+    let proto = if grpc { "grpc" } else { "http" };
+    let properties = [("proto", proto), ("tls", if tls { "1" } else { "0" }), ("version", env!("CARGO_PKG_VERSION"))];
+    // Announce every interface address when bound to 0.0.0.0 / ::
+    let ip = if addr.ip().is_unspecified() { String::new() } else { addr.ip().to_string() };
+    let mut info = ServiceInfo::new(
+        SERVICE_TYPE,
+        &format!("{}-{}", host, addr.port()),
+        &format!("{}.local.", host),
+        ip.as_str(),
+        addr.port(),
+        &properties[..],
+    )
+    .map_err(|e| format!("mDNS: {}", e))?;
+    if addr.ip().is_unspecified() {
+        info = info.enable_addr_auto();
+    }
+    daemon.register(info).map_err(|e| format!("mDNS: {}", e))?;
+    Ok(daemon)
+}
+
+/// Agents answering within `timeout` as URLs, in the order they answered
+pub fn discover(grpc: bool, timeout: Duration) -> Result<Vec<String>, String> {
+    let daemon = ServiceDaemon::new().map_err(|e| format!("mDNS: {}", e))?;
+    let events = daemon.browse(SERVICE_TYPE).map_err(|e| format!("mDNS: {}", e))?;
+    let proto = if grpc { "grpc" } else { "http" };
+    let deadline = Instant::now() + timeout;
+    let mut nodes = Vec::new();
+    // An agent on several interfaces answers once per interface
+    let mut seen = Vec::new();
+    while let Ok(event) = events.recv_deadline(deadline) {
+        let ServiceEvent::ServiceResolved(service) = event else {
+            continue;
+        };
+        if service.get_property_val_str("proto").unwrap_or("http") != proto || seen.contains(&service.fullname) {
+            continue;
+        }
+        // Prefer IPv4; link-local IPv6 needs a scope id the URL can't carry
+        let mut addrs: Vec<IpAddr> = service.get_addresses().iter().map(|a| a.to_ip_addr()).collect();
+        addrs.sort_by_key(|a| !a.is_ipv4());
+        let Some(ip) = addrs.first() else {
+            continue;
+        };
+        let node = SocketAddr::new(*ip, service.get_port()).to_string();
+        let scheme = if service.get_property_val_str("tls") == Some("1") { "https" } else { "http" };
+        let node = format!("{}://{}", scheme, node);
+        seen.push(service.fullname.clone());
+        nodes.push(node);
+    }
+    let _ = daemon.shutdown();
+    Ok(nodes)
+}
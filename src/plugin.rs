@@ -0,0 +1,117 @@
+//! Site-specific columns from dynamic-library plugins, listed in the config file
+//!
+//! ```toml
+//! [[plugins]]
+//! name = "account"
+//! path = "/usr/lib/gpustat/libaccount.so"
+//! ```
+//!
+//! A plugin exports C functions taking JSON (as printed by `--json`) and
+//! returning the column text, or NULL to leave the cell empty:
+//!
+//! ```c
+//! char *gpustat_plugin_gpu(const char *gpu_json);          /* optional */
+//! char *gpustat_plugin_process(const char *process_json);  /* optional */
+//! void gpustat_plugin_free(char *value);  /* frees what the above return */
+//! ```
+//!
+//! Values land in the `extra` map of each GPU or process, under the plugin's name.
+
+use gpustat_core::{GpuBackend, GpuStatCollection, GpuStatError, QueryOptions};
+use libloading::Library;
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_char, CStr, CString};
+use std::path::PathBuf;
+
+type ColumnFn = unsafe extern "C" fn(*const c_char) -> *mut c_char;
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+/// A `[[plugins]]` entry of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    /// Column label and key in `extra`
+    pub name: String,
+    pub path: PathBuf,
+}
+
+pub struct Plugin {
+    name: String,
+    gpu: Option<ColumnFn>,
+    process: Option<ColumnFn>,
+    free: FreeFn,
+    // Keeps the fn pointers above valid
+    _lib: Library,
+}
+
+impl Plugin {
+    pub fn load(config: &PluginConfig) -> Result<Self, String> {
+        let error = |e: libloading::Error| format!("plugin {} ({}): {}", config.name, config.path.display(), e);
+        unsafe {
+            let lib = Library::new(&config.path).map_err(error)?;
+            let gpu = lib.get::<ColumnFn>(b"gpustat_plugin_gpu\0").ok().map(|f| *f);
+            let process = lib.get::<ColumnFn>(b"gpustat_plugin_process\0").ok().map(|f| *f);
+            let free = *lib.get::<FreeFn>(b"gpustat_plugin_free\0").map_err(error)?;
+            if gpu.is_none() && process.is_none() {
+                return Err(format!(
+                    "plugin {} ({}): exports neither gpustat_plugin_gpu nor gpustat_plugin_process",
+                    config.name,
+                    config.path.display()
+                ));
+            }
+            Ok(Plugin { name: config.name.clone(), gpu, process, free, _lib: lib })
+        }
+    }
+
+    /// Hand `value` to `column` as JSON and take back its text
+    fn call<T: Serialize>(&self, column: ColumnFn, value: &T) -> Option<String> {
+        let json = CString::new(serde_json::to_string(value).ok()?).ok()?;
+        unsafe {
+            let out = column(json.as_ptr());
+            if out.is_null() {
+                return None;
+            }
+            let text = CStr::from_ptr(out).to_string_lossy().into_owned();
+            (self.free)(out);
+            Some(text)
+        }
+    }
+
+    fn apply(&self, stats: &mut GpuStatCollection) {
+        for gpu in stats.gpus.iter_mut() {
+            if let Some(column) = self.process {
+                for p in gpu.processes.iter_mut().flatten() {
+                    if let Some(text) = self.call(column, &*p) {
+                        p.extra.insert(self.name.clone(), text);
+                    }
+                }
+            }
+            if let Some(column) = self.gpu {
+                if let Some(text) = self.call(column, &*gpu) {
+                    gpu.extra.insert(self.name.clone(), text);
+                }
+            }
+        }
+    }
+}
+
+/// Load every configured plugin; the first one that fails stops the lot
+pub fn load_all(configs: &[PluginConfig]) -> Result<Vec<Plugin>, String> {
+    configs.iter().map(Plugin::load).collect()
+}
+
+/// Another backend's stats with the plugins' columns filled in
+pub struct PluginBackend {
+    pub inner: Box<dyn GpuBackend>,
+    pub plugins: Vec<Plugin>,
+}
+
+impl GpuBackend for PluginBackend {
+    fn query(&self, gpu_ids: Option<&[u32]>, query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        let mut stats = self.inner.query(gpu_ids, query)?;
+        for plugin in &self.plugins {
+            plugin.apply(&mut stats);
+        }
+        Ok(stats)
+    }
+}
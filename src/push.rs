@@ -0,0 +1,79 @@
+//! `gpustat push --to URL`: POST snapshots to a central collector, for nodes
+//! that can't be scraped (behind NAT, no inbound ports)
+//!
+//! Every request body is a JSON array of snapshots, each shaped like the
+//! `--json` output, oldest first. Snapshots are sent `--batch` at a time;
+//! while the collector is unreachable they are queued (up to `MAX_QUEUED`, the
+//! oldest dropped first) and retried with exponential backoff.
+
+use crate::display::{DisplayOptions, Render};
+use gpustat_core::{GpuBackend, QueryOptions};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Snapshots kept while the collector is down
+const MAX_QUEUED: usize = 1000;
+/// Longest wait between retries
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn run(
+    backend: &dyn GpuBackend,
+    gpu_ids: Option<&[u32]>,
+    query: &QueryOptions,
+    opts: &DisplayOptions,
+    to: &str,
+    interval: Duration,
+    batch: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let agent: ureq::Agent = ureq::Agent::config_builder().timeout_global(Some(POST_TIMEOUT)).build().into();
+    let batch = batch.max(1);
+    crate::systemd::notify_ready();
+    eprintln!("Pushing GPU stats to {} every {:.1}s", to, interval.as_secs_f64());
+
+    let mut queue: VecDeque<Value> = VecDeque::new();
+    let mut backoff = Duration::ZERO;
+    let mut next_attempt = Instant::now();
+    loop {
+        let started = Instant::now();
+        match backend.query(gpu_ids, query) {
+            Ok(mut stats) => {
+                stats.apply_options(opts);
+                if queue.len() == MAX_QUEUED {
+                    queue.pop_front();
+                }
+                queue.push_back(serde_json::to_value(&stats)?);
+            }
+            Err(e) => eprintln!("Error querying NVIDIA devices: {}", e),
+        }
+
+        while queue.len() >= batch && Instant::now() >= next_attempt {
+            let count = batch.min(queue.len());
+            let body = serde_json::to_string(&queue.range(..count).collect::<Vec<_>>())?;
+            match agent.post(to).header("Content-Type", "application/json").send(body.as_str()) {
+                Ok(_) => {
+                    queue.drain(..count);
+                    backoff = Duration::ZERO;
+                }
+                Err(e) => {
+                    // Never retry sooner than the next snapshot, nor later than MAX_BACKOFF past it
+                    let min = interval.max(Duration::from_secs(1));
+                    backoff = (backoff * 2).max(min).min(MAX_BACKOFF.max(min));
+                    // Spread retries so a restarted collector isn't hit by every node at once
+                    let nanos = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+                    let jitter = backoff.mul_f64(f64::from(nanos % 1000) / 4000.0);
+                    next_attempt = Instant::now() + backoff + jitter;
+                    eprintln!(
+                        "Error pushing to {}: {} ({} snapshots queued, retrying in {:.0}s)",
+                        to,
+                        e,
+                        queue.len(),
+                        (backoff + jitter).as_secs_f64()
+                    );
+                }
+            }
+        }
+        std::thread::sleep(interval.saturating_sub(started.elapsed()));
+    }
+}
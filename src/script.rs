@@ -0,0 +1,98 @@
+//! `--script FILE`: a Rhai script run on every snapshot before it is shown
+//!
+//! The snapshot is in scope as `stats`, shaped like the `--json` output, and
+//! whatever the script leaves there is what gets displayed:
+//!
+//! ```rhai
+//! // Hide idle GPUs
+//! stats.gpus.retain(|g| g.utilization > 0);
+//! // Derived column, shown like a plugin column
+//! for i in 0..stats.gpus.len() {
+//!     let gpu = stats.gpus[i];
+//!     stats.gpus[i].extra.free = `${gpu.memory_total - gpu.memory_used}M`;
+//! }
+//! ```
+//!
+//! Anything the script `print`s replaces the normal output for that snapshot.
+
+use gpustat_core::GpuStatCollection;
+use rhai::{Dynamic, Engine, Scope, AST};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    printed: Rc<RefCell<String>>,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut engine = Engine::new();
+        let printed = Rc::new(RefCell::new(String::new()));
+        let sink = printed.clone();
+        engine.on_print(move |line| {
+            let mut out = sink.borrow_mut();
+            out.push_str(line);
+            out.push('\n');
+        });
+        engine.on_debug(|line, _, pos| eprintln!("{}: {}", pos, line));
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Script { engine, ast, printed })
+    }
+
+    /// Run the script on `stats`, replacing it with what the script left in
+    /// `stats`. Returns the printed text, if the script printed anything
+    pub fn run(&self, stats: &mut GpuStatCollection) -> Result<Option<String>, String> {
+        let mut value = serde_json::to_value(&*stats).map_err(|e| e.to_string())?;
+        for_each_item(&mut value, |item| {
+            if let Some(obj) = item.as_object_mut() {
+                obj.entry("extra").or_insert_with(|| Value::Object(Default::default()));
+            }
+        });
+        let dynamic = rhai::serde::to_dynamic(&value).map_err(|e| e.to_string())?;
+        let mut scope = Scope::new();
+        scope.push("stats", dynamic);
+        self.printed.borrow_mut().clear();
+        self.engine
+            .run_ast_with_scope(&mut scope, &self.ast)
+            .map_err(|e| format!("script: {}", e))?;
+
+        let result = scope.get_value::<Dynamic>("stats").ok_or("script: `stats` is gone")?;
+        let mut value: Value = rhai::serde::from_dynamic(&result).map_err(|e| format!("script: {}", e))?;
+        for_each_item(&mut value, stringify_extra);
+        *stats = serde_json::from_value(value).map_err(|e| format!("script: invalid stats: {}", e))?;
+
+        let printed = self.printed.take();
+        Ok((!printed.is_empty()).then_some(printed))
+    }
+}
+
+/// Call `f` on every GPU and process of `stats`
+fn for_each_item(stats: &mut Value, f: impl Fn(&mut Value)) {
+    let Some(gpus) = stats.get_mut("gpus").and_then(Value::as_array_mut) else {
+        return;
+    };
+    for gpu in gpus {
+        for p in gpu.get_mut("processes").and_then(Value::as_array_mut).into_iter().flatten() {
+            f(p);
+        }
+        f(gpu);
+    }
+}
+
+/// Let scripts put numbers and the like into `extra`, which only holds text
+fn stringify_extra(item: &mut Value) {
+    if let Some(extra) = item.get_mut("extra").and_then(Value::as_object_mut) {
+        for v in extra.values_mut() {
+            if !v.is_string() {
+                let text = if v.is_null() { String::new() } else { v.to_string() };
+                *v = Value::String(text);
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Minimal JSON field selector for `--select`
+//! Supports jq-like paths such as `gpus[].memory_used`, `.gpus[0].processes[].pid`
+//! or `hostname`, so simple extractions don't require jq.
+
+use serde_json::Value;
+use std::fmt;
+
+/// A single step of a selector path
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+    Iterate,
+}
+
+/// Parsed `--select` expression
+#[derive(Debug, Clone)]
+pub struct Selector {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug)]
+pub struct SelectorError(String);
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid selector: {}", self.0)
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+impl Selector {
+    /// Parse a path expression like `gpus[].memory_used`
+    pub fn parse(expr: &str) -> Result<Self, SelectorError> {
+        let expr = expr.trim();
+        let expr = expr.strip_prefix('.').unwrap_or(expr);
+        let mut segments = Vec::new();
+        if expr.is_empty() {
+            return Ok(Self { segments });
+        }
+
+        for part in expr.split('.') {
+            let (name, mut rest) = match part.find('[') {
+                Some(pos) => (&part[..pos], &part[pos..]),
+                None => (part, ""),
+            };
+            if name.is_empty() && rest.is_empty() {
+                return Err(SelectorError(format!("empty path segment in '{}'", expr)));
+            }
+            if !name.is_empty() {
+                segments.push(Segment::Field(name.to_string()));
+            }
+            while !rest.is_empty() {
+                let Some(end) = rest.find(']') else {
+                    return Err(SelectorError(format!("unclosed '[' in '{}'", part)));
+                };
+                if !rest.starts_with('[') {
+                    return Err(SelectorError(format!("unexpected '{}' in '{}'", rest, part)));
+                }
+                let inner = rest[1..end].trim();
+                if inner.is_empty() {
+                    segments.push(Segment::Iterate);
+                } else {
+                    let index = inner
+                        .parse()
+                        .map_err(|_| SelectorError(format!("bad index '{}' in '{}'", inner, part)))?;
+                    segments.push(Segment::Index(index));
+                }
+                rest = &rest[end + 1..];
+            }
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Apply the selector; `[]` fans out so multiple values may be returned.
+    /// Missing fields yield `null`, like jq.
+    pub fn apply(&self, root: &Value) -> Vec<Value> {
+        let mut current = vec![root.clone()];
+        for segment in &self.segments {
+            let mut next = Vec::new();
+            for value in current {
+                match segment {
+                    Segment::Field(name) => {
+                        next.push(value.get(name.as_str()).cloned().unwrap_or(Value::Null));
+                    }
+                    Segment::Index(i) => {
+                        next.push(value.get(*i).cloned().unwrap_or(Value::Null));
+                    }
+                    Segment::Iterate => match value {
+                        Value::Array(items) => next.extend(items),
+                        Value::Object(map) => next.extend(map.into_iter().map(|(_, v)| v)),
+                        _ => {}
+                    },
+                }
+            }
+            current = next;
+        }
+        current
+    }
+}
+
+/// Render a selected value: strings are printed raw (like `jq -r`),
+/// scalars compactly and objects/arrays as pretty JSON.
+pub fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(_) | Value::Object(_) => {
+            serde_json::to_string_pretty(value).unwrap_or_default()
+        }
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn select(expr: &str, value: &Value) -> Vec<Value> {
+        Selector::parse(expr).unwrap().apply(value)
+    }
+
+    #[test]
+    fn parse() {
+        let selector = Selector::parse(".gpus[0].processes[].pid").unwrap();
+        assert_eq!(
+            selector.segments,
+            vec![
+                Segment::Field("gpus".into()),
+                Segment::Index(0),
+                Segment::Field("processes".into()),
+                Segment::Iterate,
+                Segment::Field("pid".into()),
+            ]
+        );
+        assert!(Selector::parse("").unwrap().segments.is_empty());
+        assert!(Selector::parse(".").unwrap().segments.is_empty());
+    }
+
+    #[test]
+    fn parse_errors() {
+        for bad in ["gpus[", "gpus[x]", "gpus[0]x", "gpus..name", "gpus[-1]"] {
+            assert!(Selector::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn apply() {
+        let value = json!({
+            "hostname": "node1",
+            "gpus": [
+                {"index": 0, "processes": [{"pid": 1}, {"pid": 2}]},
+                {"index": 1, "processes": []},
+            ],
+        });
+        assert_eq!(select("hostname", &value), vec![json!("node1")]);
+        assert_eq!(select("gpus[].index", &value), vec![json!(0), json!(1)]);
+        assert_eq!(select("gpus[0].processes[].pid", &value), vec![json!(1), json!(2)]);
+        assert_eq!(select("gpus[1].processes[].pid", &value), Vec::<Value>::new());
+        // Missing fields and indices are null, like jq
+        assert_eq!(select("gpus[5].index", &value), vec![Value::Null]);
+        assert_eq!(select("uptime", &value), vec![Value::Null]);
+        assert_eq!(select("", &value), vec![value.clone()]);
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(format_value(&json!("node1")), "node1");
+        assert_eq!(format_value(&json!(42)), "42");
+        assert_eq!(format_value(&Value::Null), "null");
+        assert_eq!(format_value(&json!([1])), "[\n  1\n]");
+    }
+}
@@ -0,0 +1,287 @@
+//! `gpustat serve`: the latest snapshot over HTTP, for polling from other machines
+//!
+//! - `/json`: the snapshot as printed by `--json`
+//! - `/metrics`: per-GPU gauges in the Prometheus text format
+//! - `/healthz`: 200 while queries succeed and are up to date, 503 otherwise
+//!
+//! With `--grpc`, the `gpustat.v1.GpuStat` service of `proto/gpustat.proto` is
+//! served instead (`grpc` feature).
+//!
+//! `--tls-cert`/`--tls-key` put TLS in front of either (see `tls`), and
+//! `--token`/`--basic-auth` require an `Authorization` header on everything but
+//! `/healthz`, which stays open for load balancers and says nothing about the GPUs.
+//!
+//! The main thread queries the backend every interval; requests are answered
+//! from the last snapshot on a separate thread, so a slow NVML never blocks them.
+
+use crate::display::{DisplayOptions, Render};
+use base64::Engine as _;
+use gpustat_core::{GpuBackend, GpuStatCollection, QueryOptions};
+use std::fmt::Write as _;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Response, Server};
+
+/// Default `--listen` address
+pub const DEFAULT_LISTEN: &str = "0.0.0.0:9101";
+
+/// Snapshots older than this many intervals make /healthz fail
+const STALE_INTERVALS: u32 = 3;
+
+/// Where and how to serve
+pub struct Listen {
+    pub addr: String,
+    pub grpc: bool,
+    /// Certificate chain and private key (PEM)
+    pub tls: Option<(PathBuf, PathBuf)>,
+    pub auth: Auth,
+}
+
+/// Accepted `Authorization` header values; anything goes when empty
+#[derive(Default)]
+pub struct Auth {
+    accepted: Vec<String>,
+    basic: bool,
+}
+
+impl Auth {
+    pub fn new(token: Option<&str>, basic: Option<&str>) -> Self {
+        let mut accepted = Vec::new();
+        if let Some(token) = token {
+            accepted.push(format!("Bearer {}", token));
+        }
+        if let Some(credentials) = basic {
+            accepted.push(format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials)));
+        }
+        Auth { accepted, basic: basic.is_some() }
+    }
+
+    pub fn check(&self, header: Option<&[u8]>) -> bool {
+        self.accepted.is_empty()
+            || header.is_some_and(|h| self.accepted.iter().any(|a| constant_time_eq(a.as_bytes(), h.trim_ascii())))
+    }
+
+    /// What a client sends: the bearer token if there is one, else basic auth
+    #[cfg(feature = "grpc")]
+    pub fn header(&self) -> Option<&str> {
+        self.accepted.first().map(String::as_str)
+    }
+
+    /// `WWW-Authenticate` challenge for a 401
+    fn challenge(&self) -> &'static str {
+        if self.basic {
+            "Basic realm=\"gpustat\""
+        } else {
+            "Bearer"
+        }
+    }
+}
+
+/// Compare without an early exit, so timing doesn't reveal how much of a token matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Last query outcome, rendered once for every request that follows
+struct Snapshot {
+    taken: Instant,
+    /// JSON and Prometheus text, or why the query failed
+    body: Result<(String, String), String>,
+}
+
+pub fn run(
+    backend: &dyn GpuBackend,
+    gpu_ids: Option<&[u32]>,
+    query: &QueryOptions,
+    opts: &DisplayOptions,
+    listen: Listen,
+    interval: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if listen.grpc {
+        return run_grpc(backend, gpu_ids, query, opts, listen, interval);
+    }
+    let public = bind(&listen.addr)?;
+    let shown = public.local_addr().map_or(listen.addr.clone(), |a| a.to_string());
+    let (server, scheme) = match &listen.tls {
+        Some((cert, key)) => {
+            let server = Server::http("127.0.0.1:0").map_err(|e| e.to_string())?;
+            let upstream = server.server_addr().to_ip().ok_or("TLS: no upstream address")?;
+            crate::tls::terminate(public, crate::tls::load(cert, key, b"http/1.1")?, upstream);
+            (server, "https")
+        }
+        None => (Server::from_listener(public, None).map_err(|e| e.to_string())?, "http"),
+    };
+    crate::systemd::notify_ready();
+    eprintln!("Serving GPU stats on {}://{}/ (refresh every {:.1}s)", scheme, shown, interval.as_secs_f64());
+
+    let latest: Arc<Mutex<Option<Snapshot>>> = Arc::default();
+    let shared = latest.clone();
+    let auth = listen.auth;
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let authorization = request.headers().iter().find(|h| h.field.equiv("Authorization"));
+            let path = request.url().split('?').next().unwrap_or_default();
+            let response = if path == "/healthz" || auth.check(authorization.map(|h| h.value.as_bytes())) {
+                let snapshot = shared.lock().unwrap_or_else(|e| e.into_inner());
+                respond(request.url(), snapshot.as_ref(), interval)
+            } else {
+                let response = text(401, "unauthorized\n".to_string());
+                match Header::from_bytes("WWW-Authenticate", auth.challenge()) {
+                    Ok(header) => response.with_header(header),
+                    Err(()) => response,
+                }
+            };
+            let _ = request.respond(response);
+        }
+    });
+
+    loop {
+        let started = Instant::now();
+        let body = backend
+            .query(gpu_ids, query)
+            .map_err(|e| e.to_string())
+            .and_then(|mut stats| {
+                // Gauges use fixed units; the JSON follows --temp-unit, --user, ...
+                let metrics = format_metrics(&stats);
+                stats.apply_options(opts);
+                let json = serde_json::to_string_pretty(&stats).map_err(|e| e.to_string())?;
+                Ok((json, metrics))
+            });
+        *latest.lock().unwrap_or_else(|e| e.into_inner()) = Some(Snapshot { taken: started, body });
+        std::thread::sleep(interval.saturating_sub(started.elapsed()));
+    }
+}
+
+#[cfg(feature = "grpc")]
+fn run_grpc(
+    backend: &dyn GpuBackend,
+    gpu_ids: Option<&[u32]>,
+    query: &QueryOptions,
+    opts: &DisplayOptions,
+    listen: Listen,
+    interval: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Listen { addr, tls, auth, .. } = listen;
+    let public = bind(&addr)?;
+    let shown = public.local_addr().map_or(addr, |a| a.to_string());
+    let publisher = match &tls {
+        Some((cert, key)) => {
+            let (publisher, upstream) = crate::grpc::start(TcpListener::bind("127.0.0.1:0")?, auth)?;
+            crate::tls::terminate(public, crate::tls::load(cert, key, b"h2")?, upstream);
+            publisher
+        }
+        None => crate::grpc::start(public, auth)?.0,
+    };
+    crate::systemd::notify_ready();
+    let tls_note = if tls.is_some() { " with TLS" } else { "" };
+    eprintln!("Serving gRPC on {}{} (refresh every {:.1}s)", shown, tls_note, interval.as_secs_f64());
+    loop {
+        let started = Instant::now();
+        // Clients convert units and sort themselves, but --user/--anonymize hold for them too
+        let mut stats = backend.query(gpu_ids, query);
+        if let Ok(stats) = &mut stats {
+            stats.apply_privacy(opts);
+        }
+        publisher.publish(stats.as_ref().map_err(|e| e.to_string()));
+        std::thread::sleep(interval.saturating_sub(started.elapsed()));
+    }
+}
+
+#[cfg(not(feature = "grpc"))]
+fn run_grpc(
+    _backend: &dyn GpuBackend,
+    _gpu_ids: Option<&[u32]>,
+    _query: &QueryOptions,
+    _opts: &DisplayOptions,
+    _listen: Listen,
+    _interval: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err("gRPC support is not built in (build with --features grpc)".into())
+}
+
+/// The socket systemd passed us, else `addr` bound
+fn bind(addr: &str) -> Result<TcpListener, String> {
+    match crate::systemd::tcp_listener() {
+        Some(listener) => Ok(listener),
+        None => TcpListener::bind(addr).map_err(|e| format!("cannot listen on {}: {}", addr, e)),
+    }
+}
+
+fn respond(url: &str, snapshot: Option<&Snapshot>, interval: Duration) -> Response<std::io::Cursor<Vec<u8>>> {
+    let path = url.split('?').next().unwrap_or(url);
+    let body = snapshot.map(|s| s.body.as_ref());
+    match (path, body) {
+        ("/healthz", Some(Ok(_))) if snapshot.is_some_and(|s| s.taken.elapsed() <= interval * STALE_INTERVALS) => {
+            text(200, "ok\n".to_string())
+        }
+        ("/healthz", Some(Ok(_))) => text(503, "stale: no successful query recently\n".to_string()),
+        ("/json" | "/metrics" | "/healthz", None) => text(503, "no snapshot yet\n".to_string()),
+        ("/json" | "/metrics" | "/healthz", Some(Err(e))) => text(503, format!("query failed: {}\n", e)),
+        ("/json", Some(Ok((json, _)))) => with_type(text(200, json.clone()), "application/json"),
+        ("/metrics", Some(Ok((_, metrics)))) => with_type(text(200, metrics.clone()), "text/plain; version=0.0.4"),
+        _ => text(404, "not found; try /json, /metrics or /healthz\n".to_string()),
+    }
+}
+
+fn text(status: u16, body: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    with_type(Response::from_string(body).with_status_code(status), "text/plain; charset=utf-8")
+}
+
+fn with_type(response: Response<std::io::Cursor<Vec<u8>>>, content_type: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    match Header::from_bytes("Content-Type", content_type) {
+        Ok(header) => response.with_header(header),
+        Err(()) => response,
+    }
+}
+
+/// One gauge family: name, help text, and how to read it off a GPU
+type Gauge = (&'static str, &'static str, fn(&gpustat_core::GpuStat) -> Option<f64>);
+
+const GAUGES: [Gauge; 10] = [
+    ("gpustat_up", "1 if the GPU could be queried", |g| Some(if g.available { 1.0 } else { 0.0 })),
+    ("gpustat_utilization_percent", "GPU utilization", |g| g.utilization.map(f64::from)),
+    ("gpustat_memory_used_bytes", "Used GPU memory", |g| g.available.then_some(g.memory_used_bytes as f64)),
+    ("gpustat_memory_total_bytes", "Total GPU memory", |g| g.available.then_some(g.memory_total_bytes as f64)),
+    ("gpustat_temperature_celsius", "GPU core temperature", |g| g.temperature.map(f64::from)),
+    ("gpustat_fan_speed_percent", "Fan speed", |g| g.fan_speed.map(f64::from)),
+    ("gpustat_power_draw_watts", "Power draw", |g| g.power_draw.map(f64::from)),
+    ("gpustat_power_limit_watts", "Enforced power limit", |g| g.power_limit.map(f64::from)),
+    ("gpustat_energy_joules", "Energy used since the driver loaded", |g| g.energy_total.map(|mj| mj as f64 / 1000.0)),
+    ("gpustat_processes", "Processes using the GPU", |g| g.processes.as_ref().map(|p| p.len() as f64)),
+];
+
+/// Prometheus text exposition of `stats`
+fn format_metrics(stats: &GpuStatCollection) -> String {
+    let mut out = String::new();
+    for (name, help, value) in GAUGES {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge", name, help, name);
+        for gpu in &stats.gpus {
+            if let Some(v) = value(gpu) {
+                let _ = writeln!(
+                    out,
+                    "{}{{gpu=\"{}\",uuid=\"{}\",name=\"{}\"}} {}",
+                    name,
+                    gpu.index,
+                    escape(&gpu.uuid),
+                    escape(&gpu.name),
+                    v
+                );
+            }
+        }
+    }
+    if let Some(latency) = stats.query_latency {
+        let _ = writeln!(
+            out,
+            "# HELP gpustat_query_seconds Time the last query took\n# TYPE gpustat_query_seconds gauge\ngpustat_query_seconds {}",
+            latency
+        );
+    }
+    out
+}
+
+/// Label value escaping of the text format
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
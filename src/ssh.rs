@@ -0,0 +1,196 @@
+//! `--ssh [USER@]HOST`: query another machine over SSH and render locally
+//!
+//! The remote `gpustat --json` is used when it is this gpustat; otherwise (not
+//! installed, or the Python gpustat with its different schema) `nvidia-smi`'s
+//! CSV output is parsed instead, which lacks process owners. One SSH master
+//! connection is kept open so watch mode doesn't pay for a handshake per sample.
+
+use gpustat_core::{GpuBackend, GpuProcessInfo, GpuStat, GpuStatCollection, GpuStatError, MemUnit, QueryOptions, TempUnit};
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const MB: u64 = 1024 * 1024;
+
+const GPU_FIELDS: &str = "index,uuid,name,temperature.gpu,fan.speed,utilization.gpu,utilization.encoder,\
+utilization.decoder,power.draw,enforced.power.limit,memory.used,memory.total,memory.free,driver_version";
+const APP_FIELDS: &str = "gpu_uuid,pid,process_name,used_memory";
+
+pub struct SshBackend {
+    target: String,
+    /// Set once the remote gpustat turned out to be unusable
+    nvidia_smi: AtomicBool,
+}
+
+impl SshBackend {
+    pub fn new(target: &str) -> Self {
+        SshBackend { target: target.to_string(), nvidia_smi: AtomicBool::new(false) }
+    }
+
+    /// Run `command` on the remote host; Err carries ssh's (or the command's) stderr
+    fn run(&self, command: &str) -> Result<String, GpuStatError> {
+        let output = Command::new("ssh")
+            .args(["-o", "BatchMode=yes", "-o", "ControlMaster=auto", "-o", "ControlPersist=60"])
+            .arg("-o")
+            .arg(format!("ControlPath={}", std::env::temp_dir().join("gpustat-ssh-%C").display()))
+            .arg(&self.target)
+            .arg(command)
+            .output()
+            .map_err(|e| format!("ssh: {}", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("ssh {}: {} ({})", self.target, stderr.trim(), output.status).into());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn query_gpustat(&self, query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        let command = if query.inventory { "gpustat --json --inventory" } else { "gpustat --json" };
+        Ok(serde_json::from_str(&self.run(command)?)?)
+    }
+
+    fn query_nvidia_smi(&self) -> Result<GpuStatCollection, GpuStatError> {
+        let gpus = self.run(&format!("nvidia-smi --query-gpu={} --format=csv,noheader,nounits", GPU_FIELDS))?;
+        // Process listing is best-effort (e.g. not supported on some GeForce cards)
+        let apps = self
+            .run(&format!("nvidia-smi --query-compute-apps={} --format=csv,noheader,nounits", APP_FIELDS))
+            .unwrap_or_default();
+        let host = self.target.rsplit('@').next().unwrap_or(&self.target);
+        parse_nvidia_smi(host, &gpus, &apps)
+    }
+}
+
+impl GpuBackend for SshBackend {
+    fn query(&self, gpu_ids: Option<&[u32]>, query: &QueryOptions) -> Result<GpuStatCollection, GpuStatError> {
+        let started = std::time::Instant::now();
+        let mut stats = if self.nvidia_smi.load(Ordering::Relaxed) {
+            self.query_nvidia_smi()?
+        } else {
+            match self.query_gpustat(query) {
+                Ok(stats) => stats,
+                // An unreachable host fails the same way for nvidia-smi, with a clearer message
+                Err(_) => {
+                    let stats = self.query_nvidia_smi()?;
+                    self.nvidia_smi.store(true, Ordering::Relaxed);
+                    stats
+                }
+            }
+        };
+        if let Some(ids) = gpu_ids {
+            stats.gpus.retain(|g| ids.contains(&g.index));
+        }
+        stats.query_latency = Some(started.elapsed().as_secs_f64());
+        Ok(stats)
+    }
+}
+
+/// A CSV cell, None for "[N/A]", "[Not Supported]" and the like
+fn cell<T: std::str::FromStr>(s: Option<&str>) -> Option<T> {
+    s?.trim().parse().ok()
+}
+
+fn parse_nvidia_smi(host: &str, gpus: &str, apps: &str) -> Result<GpuStatCollection, GpuStatError> {
+    let mut driver_version = None;
+    let mut stats = Vec::new();
+    for line in gpus.lines().filter(|l| !l.trim().is_empty()) {
+        let f: Vec<&str> = line.split(',').map(str::trim).collect();
+        let get = |i: usize| f.get(i).copied();
+        let index = cell(get(0)).ok_or_else(|| format!("unexpected nvidia-smi output: {}", line))?;
+        let mib = |i| cell::<f64>(get(i)).map(|m| m as u64).unwrap_or(0);
+        driver_version = driver_version.or(get(13).map(str::to_string));
+        stats.push(GpuStat {
+            index,
+            uuid: get(1).unwrap_or_default().to_string(),
+            name: get(2).unwrap_or_default().to_string(),
+            temperature: cell(get(3)),
+            fan_speed: cell(get(4)),
+            utilization: cell(get(5)),
+            utilization_enc: cell(get(6)),
+            utilization_dec: cell(get(7)),
+            power_draw: cell::<f64>(get(8)).map(|w| w.round() as u32),
+            power_limit: cell::<f64>(get(9)).map(|w| w.round() as u32),
+            memory_used: mib(10),
+            memory_total: mib(11),
+            memory_free: mib(12),
+            memory_used_bytes: mib(10) * MB,
+            memory_total_bytes: mib(11) * MB,
+            processes: Some(Vec::new()),
+            available: true,
+            ..Default::default()
+        });
+    }
+    for line in apps.lines() {
+        let f: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [uuid, pid, name, memory] = f[..] else {
+            continue;
+        };
+        let Some(gpu) = stats.iter_mut().find(|g| g.uuid == uuid) else {
+            continue;
+        };
+        let Some(pid) = cell(Some(pid)) else {
+            continue;
+        };
+        let gpu_memory_usage: Option<u64> = cell(Some(memory));
+        let total = gpu.memory_total;
+        gpu.processes.get_or_insert_with(Vec::new).push(GpuProcessInfo {
+            pid,
+            command: Path::new(name).file_name().map_or(name.to_string(), |n| n.to_string_lossy().into_owned()),
+            gpu_memory_usage,
+            gpu_memory_percent: gpu_memory_usage.filter(|_| total > 0).map(|m| m as f64 / total as f64 * 100.0),
+            ..Default::default()
+        });
+    }
+    Ok(GpuStatCollection {
+        hostname: host.to_string(),
+        query_time: chrono::Utc::now(),
+        driver_version,
+        cuda_version: None,
+        nvml_version: None,
+        sample_period: None,
+        query_latency: None,
+        temperature_unit: TempUnit::Celsius,
+        memory_unit: MemUnit::MiB,
+        gpus: stats,
+        users: Vec::new(),
+        by_process: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GPUS: &str = "\
+0, GPU-aaaa, NVIDIA A100-SXM4-80GB, 61, [N/A], 87, 0, 0, 312.45, 400.00, 61903, 81920, 19338, 550.54.15
+1, GPU-bbbb, NVIDIA A100-SXM4-80GB, 34, [N/A], 0, 0, 0, 61.20, 400.00, 587, 81920, 80654, 550.54.15
+";
+    const APPS: &str = "\
+GPU-aaaa, 41235, /usr/bin/python3, 40960
+GPU-aaaa, 41862, python, [N/A]
+GPU-cccc, 1, gone, 1
+";
+
+    #[test]
+    fn nvidia_smi_csv() {
+        let stats = parse_nvidia_smi("node1", GPUS, APPS).unwrap();
+        assert_eq!(stats.driver_version.as_deref(), Some("550.54.15"));
+        assert_eq!(stats.gpus.len(), 2);
+        let gpu = &stats.gpus[0];
+        assert_eq!((gpu.index, gpu.uuid.as_str()), (0, "GPU-aaaa"));
+        assert_eq!((gpu.temperature, gpu.fan_speed, gpu.utilization), (Some(61), None, Some(87)));
+        assert_eq!((gpu.power_draw, gpu.power_limit), (Some(312), Some(400)));
+        assert_eq!((gpu.memory_used, gpu.memory_total), (61903, 81920));
+        assert_eq!(gpu.memory_used_bytes, 61903 * MB);
+
+        let procs = gpu.processes.as_ref().unwrap();
+        assert_eq!(procs.len(), 2);
+        assert_eq!((procs[0].pid, procs[0].command.as_str(), procs[0].gpu_memory_usage), (41235, "python3", Some(40960)));
+        assert_eq!(procs[1].gpu_memory_usage, None);
+        assert_eq!(stats.gpus[1].processes.as_ref().map(Vec::len), Some(0));
+    }
+
+    #[test]
+    fn nvidia_smi_garbage() {
+        assert!(parse_nvidia_smi("node1", "NVIDIA-SMI has failed\n", "").is_err());
+    }
+}
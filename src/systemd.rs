@@ -0,0 +1,147 @@
+//! Running under systemd: socket activation, `sd_notify` readiness and
+//! `--generate-systemd-unit`
+//!
+//! Both protocols are small enough to speak directly: an activated socket is
+//! fd 3 when `LISTEN_PID` is us, and readiness is a datagram to `$NOTIFY_SOCKET`.
+//! Outside systemd (or off unix) everything here is a no-op.
+
+use clap::ValueEnum;
+use std::fmt::Write as _;
+use std::net::TcpListener;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UnitKind {
+    Service,
+    Socket,
+}
+
+/// First socket passed by systemd, taken at most once
+#[cfg(unix)]
+fn listen_fd() -> Option<std::os::fd::OwnedFd> {
+    use std::os::fd::FromRawFd;
+
+    const SD_LISTEN_FDS_START: i32 = 3;
+    let for_us = std::env::var("LISTEN_PID").ok()?.parse::<u32>().ok()? == std::process::id();
+    let count: i32 = std::env::var("LISTEN_FDS").ok()?.parse().ok()?;
+    // Not for children, and not twice
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+    // SAFETY: systemd hands fds from 3 on to this process, and the variables
+    // are gone now so nothing else wraps the same fd
+    (for_us && count >= 1).then(|| unsafe { std::os::fd::OwnedFd::from_raw_fd(SD_LISTEN_FDS_START) })
+}
+
+/// The socket-activated TCP listener, if started by a `.socket` unit
+#[cfg(unix)]
+pub fn tcp_listener() -> Option<TcpListener> {
+    listen_fd().map(TcpListener::from)
+}
+
+#[cfg(not(unix))]
+pub fn tcp_listener() -> Option<TcpListener> {
+    None
+}
+
+/// The socket-activated unix listener, if started by a `.socket` unit
+#[cfg(unix)]
+pub fn unix_listener() -> Option<std::os::unix::net::UnixListener> {
+    listen_fd().map(std::os::unix::net::UnixListener::from)
+}
+
+/// Tell systemd the service is up (`Type=notify`)
+pub fn notify_ready() {
+    notify("READY=1");
+}
+
+#[cfg(unix)]
+fn notify(state: &str) {
+    use std::os::unix::net::UnixDatagram;
+
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let Ok(socket) = UnixDatagram::unbound() else {
+        return;
+    };
+    let path = path.to_string_lossy();
+    let sent = match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            std::os::unix::net::SocketAddr::from_abstract_name(name).and_then(|addr| socket.send_to_addr(state.as_bytes(), &addr))
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => return,
+        None => socket.send_to(state.as_bytes(), &*path),
+    };
+    if let Err(e) = sent {
+        eprintln!("Warning: sd_notify: {}", e);
+    }
+}
+
+#[cfg(not(unix))]
+fn notify(_state: &str) {}
+
+/// Unit of `kind` running this very command line, minus `--generate-systemd-unit`.
+/// `listen` is what a `.socket` unit should listen on: an address or a path
+pub fn unit(kind: UnitKind, command: &str, listen: Option<&str>) -> Result<String, String> {
+    let mut out = String::new();
+    match kind {
+        UnitKind::Service => {
+            let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+            let mut exec = vec![exe.to_string_lossy().into_owned()];
+            let mut args = std::env::args().skip(1).peekable();
+            while let Some(arg) = args.next() {
+                if arg == "--generate-systemd-unit" {
+                    args.next_if(|v| UnitKind::from_str(v, false).is_ok());
+                } else if !arg.starts_with("--generate-systemd-unit=") {
+                    exec.push(arg);
+                }
+            }
+            // The long-running modes say when they're ready; anything else is up once started
+            let ty = if matches!(command, "serve" | "daemon" | "push") { "notify" } else { "simple" };
+            let _ = writeln!(out, "[Unit]\nDescription=gpustat {}", command);
+            if command != "daemon" {
+                out.push_str("Wants=network-online.target\nAfter=network-online.target\n");
+            }
+            let _ = writeln!(out, "\n[Service]\nType={}\nExecStart={}", ty, exec.iter().map(|a| quote(a)).collect::<Vec<_>>().join(" "));
+            out.push_str("Restart=on-failure\nRestartSec=5\n\n[Install]\nWantedBy=multi-user.target\n");
+        }
+        UnitKind::Socket => {
+            let listen = listen.ok_or("only `gpustat serve` and `gpustat daemon` can be socket-activated")?;
+            let _ = writeln!(out, "[Unit]\nDescription=gpustat {} socket\n\n[Socket]\nListenStream={}", command, listen);
+            if command == "daemon" {
+                out.push_str("SocketMode=0666\n");
+            }
+            out.push_str("\n[Install]\nWantedBy=sockets.target\n");
+        }
+    }
+    Ok(out)
+}
+
+/// An `ExecStart=` word: quoted when it has spaces, quotes, `$` or `%`
+fn quote(arg: &str) -> String {
+    let escaped = arg.replace('%', "%%").replace('$', "$$");
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == '\\') {
+        return escaped;
+    }
+    format!("\"{}\"", escaped.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote("/usr/bin/gpustat"), "/usr/bin/gpustat");
+        assert_eq!(quote("--interval=2"), "--interval=2");
+        assert_eq!(quote(""), "\"\"");
+        assert_eq!(quote("my gpus"), "\"my gpus\"");
+        assert_eq!(quote("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(quote("a\\b"), "\"a\\\\b\"");
+        assert_eq!(quote("100%"), "100%%");
+        assert_eq!(quote("$HOME"), "$$HOME");
+    }
+}
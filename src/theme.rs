@@ -0,0 +1,228 @@
+//! Named color themes (`--theme`) mapping each display field to a color
+
+use clap::ValueEnum;
+use colored::{Color, ColoredString, Colorize};
+use serde::Deserialize;
+
+/// Built-in theme names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    /// Colors of Python gpustat
+    #[default]
+    Default,
+    /// Solarized accent palette (truecolor)
+    Solarized,
+    /// No colors, only bold emphasis
+    Mono,
+    /// Greens and yellows in the style of nvtop
+    Nvtop,
+}
+
+/// Color per display field; `None` leaves the terminal's default color
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub hostname: Option<Color>,
+    pub index: Option<Color>,
+    pub name: Option<Color>,
+    /// GPU name when the device could not be queried
+    pub name_error: Option<Color>,
+    pub temp: Option<Color>,
+    pub fan: Option<Color>,
+    pub util: Option<Color>,
+    pub codec: Option<Color>,
+    pub power: Option<Color>,
+    pub clock: Option<Color>,
+    pub pcie: Option<Color>,
+    pub mem_used: Option<Color>,
+    pub mem_total: Option<Color>,
+    pub user: Option<Color>,
+    /// Username resolved via Ngid-to-PID mapping
+    pub user_ngid: Option<Color>,
+    pub cmd: Option<Color>,
+    pub proc_mem: Option<Color>,
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Option<Color> {
+    Some(Color::TrueColor { r, g, b })
+}
+
+impl Theme {
+    pub fn named(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Default => Self {
+                hostname: Some(Color::White),
+                index: Some(Color::Cyan),
+                name: Some(Color::Blue),
+                name_error: Some(Color::Red),
+                temp: Some(Color::Red),
+                fan: Some(Color::Cyan),
+                util: Some(Color::Green),
+                codec: Some(Color::Green),
+                power: Some(Color::Magenta),
+                clock: Some(Color::Blue),
+                pcie: Some(Color::Cyan),
+                mem_used: Some(Color::Yellow),
+                mem_total: Some(Color::Yellow),
+                user: Some(Color::BrightBlack),
+                user_ngid: Some(Color::Green),
+                cmd: Some(Color::Cyan),
+                proc_mem: Some(Color::Yellow),
+            },
+            ThemeName::Solarized => Self {
+                hostname: rgb(0x93, 0xa1, 0xa1),
+                index: rgb(0x2a, 0xa1, 0x98),
+                name: rgb(0x26, 0x8b, 0xd2),
+                name_error: rgb(0xdc, 0x32, 0x2f),
+                temp: rgb(0xcb, 0x4b, 0x16),
+                fan: rgb(0x2a, 0xa1, 0x98),
+                util: rgb(0x85, 0x99, 0x00),
+                codec: rgb(0x85, 0x99, 0x00),
+                power: rgb(0xd3, 0x36, 0x82),
+                clock: rgb(0x26, 0x8b, 0xd2),
+                pcie: rgb(0x2a, 0xa1, 0x98),
+                mem_used: rgb(0xb5, 0x89, 0x00),
+                mem_total: rgb(0xb5, 0x89, 0x00),
+                user: rgb(0x58, 0x6e, 0x75),
+                user_ngid: rgb(0x85, 0x99, 0x00),
+                cmd: rgb(0x6c, 0x71, 0xc4),
+                proc_mem: rgb(0xb5, 0x89, 0x00),
+            },
+            ThemeName::Mono => Self {
+                hostname: None,
+                index: None,
+                name: None,
+                name_error: None,
+                temp: None,
+                fan: None,
+                util: None,
+                codec: None,
+                power: None,
+                clock: None,
+                pcie: None,
+                mem_used: None,
+                mem_total: None,
+                user: None,
+                user_ngid: None,
+                cmd: None,
+                proc_mem: None,
+            },
+            ThemeName::Nvtop => Self {
+                hostname: Some(Color::BrightWhite),
+                index: Some(Color::Green),
+                name: Some(Color::BrightWhite),
+                name_error: Some(Color::Red),
+                temp: Some(Color::BrightRed),
+                fan: Some(Color::BrightCyan),
+                util: Some(Color::BrightGreen),
+                codec: Some(Color::Green),
+                power: Some(Color::BrightYellow),
+                clock: Some(Color::BrightBlue),
+                pcie: Some(Color::BrightCyan),
+                mem_used: Some(Color::Yellow),
+                mem_total: Some(Color::BrightBlack),
+                user: Some(Color::BrightBlue),
+                user_ngid: Some(Color::BrightGreen),
+                cmd: Some(Color::White),
+                proc_mem: Some(Color::Yellow),
+            },
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::named(ThemeName::Default)
+    }
+}
+
+/// A color as written in the config file: a name (`red`, `bright blue`),
+/// `#rrggbb`, or `none` for the terminal default
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ColorValue(pub Option<Color>);
+
+impl TryFrom<String> for ColorValue {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("none") || s.eq_ignore_ascii_case("default") {
+            return Ok(Self(None));
+        }
+        if let Some(hex) = s.strip_prefix('#') {
+            let channel = |i: usize| {
+                hex.get(i..i + 2)
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or_else(|| format!("invalid hex color '{}'", s))
+            };
+            if hex.len() != 6 {
+                return Err(format!("invalid hex color '{}'", s));
+            }
+            return Ok(Self(Some(Color::TrueColor {
+                r: channel(0)?,
+                g: channel(2)?,
+                b: channel(4)?,
+            })));
+        }
+        s.parse::<Color>()
+            .map(|c| Self(Some(c)))
+            .map_err(|_| format!("unknown color '{}'", s))
+    }
+}
+
+/// Apply an optional foreground color
+pub fn fg(text: &str, color: Option<Color>) -> ColoredString {
+    match color {
+        Some(c) => text.color(c),
+        None => text.normal(),
+    }
+}
+
+/// Whether the terminal advertises 24-bit color (`COLORTERM=truecolor|24bit`)
+pub fn supports_truecolor() -> bool {
+    std::env::var("COLORTERM")
+        .map(|v| matches!(v.to_ascii_lowercase().as_str(), "truecolor" | "24bit"))
+        .unwrap_or(false)
+}
+
+/// Continuous green -> yellow -> red color for a ratio in [0, 1]
+pub fn gradient(ratio: f64) -> Color {
+    const GREEN: (f64, f64, f64) = (40.0, 200.0, 60.0);
+    const YELLOW: (f64, f64, f64) = (230.0, 200.0, 20.0);
+    const RED: (f64, f64, f64) = (220.0, 40.0, 40.0);
+    let t = ratio.clamp(0.0, 1.0);
+    let (from, to, t) = if t < 0.5 { (GREEN, YELLOW, t * 2.0) } else { (YELLOW, RED, (t - 0.5) * 2.0) };
+    let mix = |a: f64, b: f64| (a + (b - a) * t).round() as u8;
+    Color::TrueColor {
+        r: mix(from.0, to.0),
+        g: mix(from.1, to.1),
+        b: mix(from.2, to.2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(s: &str) -> Result<ColorValue, String> {
+        ColorValue::try_from(s.to_string())
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(color("red"), Ok(ColorValue(Some(Color::Red))));
+        assert_eq!(color(" bright blue "), Ok(ColorValue(Some(Color::BrightBlue))));
+        assert_eq!(color("#dc322f"), Ok(ColorValue(Some(Color::TrueColor { r: 0xdc, g: 0x32, b: 0x2f }))));
+        assert_eq!(color("none"), Ok(ColorValue(None)));
+        assert_eq!(color("Default"), Ok(ColorValue(None)));
+    }
+
+    #[test]
+    fn bad_colors() {
+        assert_eq!(color("#12345"), Err("invalid hex color '#12345'".to_string()));
+        assert_eq!(color("#12345g"), Err("invalid hex color '#12345g'".to_string()));
+        assert_eq!(color("#ééé"), Err("invalid hex color '#ééé'".to_string()));
+        assert_eq!(color("chartreuse"), Err("unknown color 'chartreuse'".to_string()));
+    }
+}
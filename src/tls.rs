@@ -0,0 +1,197 @@
+//! TLS for `gpustat serve --tls-cert/--tls-key` and `--connect https://...`
+//!
+//! Neither tiny_http nor our tonic build speak TLS, so both ends relay through
+//! loopback: the server listens on a loopback port behind a terminator that
+//! accepts TLS on `--listen`, and the client talks plaintext to a loopback port
+//! whose connections are carried over TLS to the server. At most
+//! `MAX_CONNECTIONS` are relayed at once; ones stuck in the handshake or idle
+//! both ways are dropped.
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{Connection, ServerConfig, ServerConnection};
+#[cfg(feature = "grpc")]
+use rustls::{pki_types::ServerName, ClientConfig, ClientConnection, RootCertStore};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const MAX_CONNECTIONS: usize = 64;
+/// Limit for the handshake, and how often an open connection checks for idleness
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Close connections with no traffic either way for this long
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Server config from PEM files: the certificate chain and its private key.
+/// `alpn` is "h2" for gRPC, "http/1.1" for HTTP
+pub fn load(cert: &Path, key: &Path, alpn: &[u8]) -> Result<Arc<ServerConfig>, String> {
+    let certs = read_certs(cert)?;
+    let private_key = PrivateKeyDer::from_pem_file(key).map_err(|e| format!("{}: {}", key.display(), e))?;
+    let mut config = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_no_client_auth()
+        .with_single_cert(certs, private_key)
+        .map_err(|e| format!("{}: {}", cert.display(), e))?;
+    config.alpn_protocols = vec![alpn.to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// gRPC client config trusting the public CAs, plus `ca` (PEM) for self-signed servers
+#[cfg(feature = "grpc")]
+pub fn client_config(ca: Option<&Path>) -> Result<Arc<ClientConfig>, String> {
+    let mut roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+    if let Some(ca) = ca {
+        for cert in read_certs(ca)? {
+            roots.add(cert).map_err(|e| format!("{}: {}", ca.display(), e))?;
+        }
+    }
+    let mut config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    Ok(Arc::new(config))
+}
+
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Accept TLS on `listener` and relay every connection to `upstream`
+pub fn terminate(listener: TcpListener, config: Arc<ServerConfig>, upstream: SocketAddr) {
+    spawn_relays(listener, move |client| {
+        let tls = ServerConnection::new(config.clone()).map_err(std::io::Error::other)?;
+        Ok((client, tls.into(), TcpStream::connect(upstream)?))
+    });
+}
+
+/// Loopback address whose connections are carried over TLS to `server` (host:port),
+/// checking its certificate against `name`
+#[cfg(feature = "grpc")]
+pub fn tunnel(server: String, name: ServerName<'static>, config: Arc<ClientConfig>) -> std::io::Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let local = listener.local_addr()?;
+    spawn_relays(listener, move |plain| {
+        let tls = ClientConnection::new(config.clone(), name.clone()).map_err(std::io::Error::other)?;
+        Ok((TcpStream::connect(&server)?, tls.into(), plain))
+    });
+    Ok(local)
+}
+
+/// The TLS side of a relayed connection, its session, and the plaintext side
+type Ends = (TcpStream, Connection, TcpStream);
+
+fn spawn_relays<F>(listener: TcpListener, open: F)
+where
+    F: Fn(TcpStream) -> std::io::Result<Ends> + Send + Sync + 'static,
+{
+    let open = Arc::new(open);
+    let active = Arc::new(AtomicUsize::new(0));
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if active.load(Ordering::Relaxed) >= MAX_CONNECTIONS {
+                // Dropping the stream closes it
+                tracing::debug!("TLS: too many connections, refusing one");
+                continue;
+            }
+            active.fetch_add(1, Ordering::Relaxed);
+            let (open, active) = (open.clone(), active.clone());
+            std::thread::spawn(move || {
+                // Scanners and dropped clients are routine; only --debug tells
+                if let Err(e) = open(stream).and_then(relay) {
+                    tracing::debug!(error = %e, "TLS connection closed");
+                }
+                active.fetch_sub(1, Ordering::Relaxed);
+            });
+        }
+    });
+}
+
+/// TLS side to plaintext side on this thread, the other way on another; both
+/// lock the session only while not waiting for the network
+fn relay((tls_side, tls, mut plain): Ends) -> std::io::Result<()> {
+    tls_side.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let tls = Arc::new(Mutex::new(tls));
+    let last_traffic = Arc::new(Mutex::new(Instant::now()));
+
+    let (tls_out, last_out, mut plain_in, mut tls_writer) =
+        (tls.clone(), last_traffic.clone(), plain.try_clone()?, tls_side.try_clone()?);
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 16 * 1024];
+        loop {
+            let n = plain_in.read(&mut buf).unwrap_or(0);
+            *last_out.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+            let mut tls = tls_out.lock().unwrap_or_else(|e| e.into_inner());
+            if n == 0 {
+                tls.send_close_notify();
+                let _ = flush(&mut tls, &mut tls_writer);
+                let _ = tls_writer.shutdown(Shutdown::Write);
+                return;
+            }
+            if tls.writer().write_all(&buf[..n]).and_then(|_| flush(&mut tls, &mut tls_writer)).is_err() {
+                return;
+            }
+        }
+    });
+
+    let mut tls_in = tls_side.try_clone()?;
+    let mut tls_writer = tls_side;
+    let mut buf = [0u8; 16 * 1024];
+    let result = (|| {
+        // A client speaks first
+        flush(&mut tls.lock().unwrap_or_else(|e| e.into_inner()), &mut tls_writer)?;
+        loop {
+            let n = match tls_in.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(n) => n,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if tls.lock().unwrap_or_else(|e| e.into_inner()).is_handshaking() {
+                        return Err(std::io::Error::new(ErrorKind::TimedOut, "handshake timed out"));
+                    }
+                    if last_traffic.lock().unwrap_or_else(|e| e.into_inner()).elapsed() > IDLE_TIMEOUT {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            *last_traffic.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+            let mut decrypted = Vec::new();
+            let mut tls = tls.lock().unwrap_or_else(|e| e.into_inner());
+            let mut rest = &buf[..n];
+            while !rest.is_empty() {
+                tls.read_tls(&mut rest)?;
+                if let Err(e) = tls.process_new_packets() {
+                    // Send the alert before hanging up
+                    let _ = flush(&mut tls, &mut tls_writer);
+                    return Err(std::io::Error::other(e));
+                }
+                match tls.reader().read_to_end(&mut decrypted) {
+                    Ok(_) => {}
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            // Handshake messages
+            flush(&mut tls, &mut tls_writer)?;
+            drop(tls);
+            plain.write_all(&decrypted)?;
+        }
+    })();
+    let _ = plain.shutdown(Shutdown::Both);
+    result
+}
+
+fn flush(tls: &mut Connection, out: &mut TcpStream) -> std::io::Result<()> {
+    while tls.wants_write() {
+        tls.write_tls(out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! `gpustat topo`: GPU-to-GPU connection matrix and CPU affinity, like `nvidia-smi topo -m`
+
+use nvml_wrapper::enum_wrappers::device::TopologyLevel;
+use nvml_wrapper::{Device, Nvml};
+use nvml_wrapper_sys::bindings::NVML_NVLINK_MAX_LINKS;
+use serde::Serialize;
+use std::os::raw::c_ulong;
+
+/// Words of the CPU affinity bitmask to request (64 CPUs each)
+const AFFINITY_WORDS: usize = 16;
+
+const LEGEND: &str = "\
+Legend:
+
+  X    = Self
+  SYS  = Connection traversing PCIe as well as the SMP interconnect between NUMA nodes
+  NODE = Connection traversing PCIe as well as the interconnect between PCIe Host Bridges within a NUMA node
+  PHB  = Connection traversing PCIe as well as a PCIe Host Bridge (typically the CPU)
+  PXB  = Connection traversing multiple PCIe bridges (without traversing the PCIe Host Bridge)
+  PIX  = Connection traversing at most a single PCIe bridge
+  NV#  = Connection traversing a bonded set of # NVLinks
+";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Topology {
+    pub gpus: Vec<TopoGpu>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TopoGpu {
+    pub index: u32,
+    pub pci_bus_id: Option<String>,
+    /// CPUs local to this GPU, e.g. "0-15,32-47"
+    pub cpu_affinity: Option<String>,
+    /// Connection to every GPU in `gpus` order ("X", "NV4", "PIX", "PHB", "SYS", ...)
+    pub links: Vec<String>,
+}
+
+/// Number of active NVLinks from `device` to the GPU at `bus_id`
+fn nvlink_count(device: &Device, bus_id: &str) -> u32 {
+    (0..NVML_NVLINK_MAX_LINKS)
+        .map(|l| device.link_wrapper_for(l))
+        .filter(|link| link.is_active().unwrap_or(false))
+        .filter(|link| link.remote_pci_info().is_ok_and(|p| p.bus_id.eq_ignore_ascii_case(bus_id)))
+        .count() as u32
+}
+
+fn level_name(level: TopologyLevel) -> &'static str {
+    match level {
+        TopologyLevel::Internal => "X",
+        TopologyLevel::Single => "PIX",
+        TopologyLevel::Multiple => "PXB",
+        TopologyLevel::HostBridge => "PHB",
+        TopologyLevel::Node => "NODE",
+        TopologyLevel::System => "SYS",
+    }
+}
+
+/// Compress a CPU bitmask into ranges, e.g. "0-15,32-47"
+fn cpu_ranges(mask: &[c_ulong]) -> String {
+    let bits = c_ulong::BITS as usize;
+    let cpus: Vec<usize> = (0..mask.len() * bits)
+        .filter(|&cpu| mask[cpu / bits] & (1 << (cpu % bits)) != 0)
+        .collect();
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < cpus.len() {
+        let start = cpus[i];
+        while i + 1 < cpus.len() && cpus[i + 1] == cpus[i] + 1 {
+            i += 1;
+        }
+        parts.push(if start == cpus[i] {
+            start.to_string()
+        } else {
+            format!("{}-{}", start, cpus[i])
+        });
+        i += 1;
+    }
+    parts.join(",")
+}
+
+impl Topology {
+    pub fn query(nvml: &Nvml, gpu_ids: Option<&[u32]>) -> Result<Self, nvml_wrapper::error::NvmlError> {
+        let indices: Vec<u32> = match gpu_ids {
+            Some(ids) => ids.to_vec(),
+            None => (0..nvml.device_count()?).collect(),
+        };
+        let bus_ids: Vec<Option<String>> = indices
+            .iter()
+            .map(|&i| nvml.device_by_index(i).and_then(|d| d.pci_info()).ok().map(|p| p.bus_id))
+            .collect();
+
+        let mut gpus = Vec::new();
+        for (row, &index) in indices.iter().enumerate() {
+            let device = nvml.device_by_index(index)?;
+            let mut links = Vec::new();
+            for (col, &other) in indices.iter().enumerate() {
+                if row == col {
+                    links.push("X".to_string());
+                    continue;
+                }
+                let nvlinks = bus_ids[col].as_deref().map_or(0, |b| nvlink_count(&device, b));
+                let link = if nvlinks > 0 {
+                    format!("NV{}", nvlinks)
+                } else {
+                    nvml.device_by_index(other)
+                        .and_then(|o| device.topology_common_ancestor(o))
+                        .map_or("?".to_string(), |l| level_name(l).to_string())
+                };
+                links.push(link);
+            }
+            gpus.push(TopoGpu {
+                index,
+                pci_bus_id: bus_ids[row].clone(),
+                cpu_affinity: device.cpu_affinity(AFFINITY_WORDS).ok().map(|m| cpu_ranges(&m)),
+                links,
+            });
+        }
+        Ok(Topology { gpus })
+    }
+
+    /// The matrix as printed by `nvidia-smi topo -m`, followed by the legend
+    pub fn format_matrix(&self) -> String {
+        let mut out = String::from("      ");
+        for gpu in &self.gpus {
+            out.push_str(&format!("{:<7}", format!("GPU{}", gpu.index)));
+        }
+        out.push_str("CPU Affinity\n");
+        for gpu in &self.gpus {
+            out.push_str(&format!("{:<6}", format!("GPU{}", gpu.index)));
+            for link in &gpu.links {
+                out.push_str(&format!("{:<7}", link));
+            }
+            out.push_str(gpu.cpu_affinity.as_deref().unwrap_or("N/A"));
+            out.push('\n');
+        }
+        out.push('\n');
+        out.push_str(LEGEND);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(cpu_ranges(&[0]), "");
+        assert_eq!(cpu_ranges(&[0b1]), "0");
+        assert_eq!(cpu_ranges(&[0b1011_0111]), "0-2,4-5,7");
+        // 0-15 and 32-47 of the first word, then 64-127 from the second
+        assert_eq!(cpu_ranges(&[0x0000_ffff_0000_ffff, c_ulong::MAX]), "0-15,32-47,64-127");
+    }
+}
@@ -0,0 +1,441 @@
+//! Interactive terminal UI (`gpustat tui`) built on ratatui
+//! Per-GPU panels with live gauges plus a process table, refreshed in place.
+
+use crate::history::{GpuHistory, History};
+use crate::kill::{self, KillSignal, Target};
+use gpustat_core::{GpuBackend, GpuStat, GpuStatCollection, QueryOptions};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{
+    Axis, Block, Borders, Chart, Dataset, Gauge, GraphType, Paragraph, Row, Table, TableState,
+};
+use ratatui::Frame;
+use std::time::{Duration, Instant};
+
+/// Height of one GPU panel (borders + util gauge + memory gauge)
+pub(crate) const PANEL_HEIGHT: u16 = 4;
+
+struct App {
+    stats: Option<GpuStatCollection>,
+    error: Option<String>,
+    table: TableState,
+    /// Number of process rows visible in the last frame, for PgUp/PgDn
+    page_size: usize,
+    history: History,
+    interval: Duration,
+    /// Chart view toggled with `c`; shows the GPU at `chart_gpu` (position in `gpus`)
+    show_chart: bool,
+    chart_gpu: usize,
+    /// Kill requested with `x`/`X`, waiting for y/n
+    confirm: Option<(Target, KillSignal)>,
+    /// Outcome of the last action, shown in the footer until the next key
+    message: Option<String>,
+}
+
+impl App {
+    fn process_count(&self) -> usize {
+        self.stats
+            .as_ref()
+            .map(|s| s.gpus.iter().map(|g| g.processes.as_ref().map_or(0, |p| p.len())).sum())
+            .unwrap_or(0)
+    }
+
+    fn select_next(&mut self, delta: isize) {
+        let count = self.process_count();
+        if count == 0 {
+            self.table.select(None);
+            return;
+        }
+        let current = self.table.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, count as isize - 1);
+        self.table.select(Some(next as usize));
+    }
+
+    /// The process under the cursor, in process table order
+    fn selected_target(&self) -> Option<Target> {
+        let index = self.table.selected()?;
+        let (gpu, p) = self
+            .stats
+            .as_ref()?
+            .gpus
+            .iter()
+            .flat_map(|g| g.processes.iter().flatten().map(move |p| (g, p)))
+            .nth(index)?;
+        Some(Target {
+            pid: p.real_pid.unwrap_or(p.pid),
+            username: p.username.clone(),
+            command: p.command.clone(),
+            gpus: vec![gpu.index],
+        })
+    }
+
+    fn request_kill(&mut self, signal: KillSignal) {
+        match self.selected_target() {
+            Some(target) => match kill::check_permission(&target) {
+                Ok(()) => self.confirm = Some((target, signal)),
+                Err(e) => self.message = Some(e),
+            },
+            None => self.message = Some("select a process first".to_string()),
+        }
+    }
+
+    fn page(&self) -> isize {
+        self.page_size.max(1) as isize
+    }
+
+    fn cycle_chart_gpu(&mut self, delta: isize) {
+        let count = self.stats.as_ref().map_or(0, |s| s.gpus.len()) as isize;
+        if count > 0 {
+            self.chart_gpu = (self.chart_gpu as isize + delta).rem_euclid(count) as usize;
+        }
+    }
+}
+
+/// Run the TUI until the user quits with `q` or Esc.
+/// `window` is how much history the chart view keeps.
+pub fn run(
+    backend: &dyn GpuBackend,
+    gpu_ids: Option<&[u32]>,
+    interval: Duration,
+    window: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let capacity = (window.as_secs_f64() / interval.as_secs_f64()).ceil() as usize;
+    let mut app = App {
+        stats: None,
+        error: None,
+        table: TableState::default(),
+        page_size: 0,
+        history: History::new(capacity),
+        interval,
+        show_chart: false,
+        chart_gpu: 0,
+        confirm: None,
+        message: None,
+    };
+
+    let mut terminal = ratatui::init();
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        let mut next_refresh = Instant::now();
+        loop {
+            if Instant::now() >= next_refresh {
+                match backend.query(gpu_ids, &QueryOptions::default()) {
+                    Ok(stats) => {
+                        app.history.record(&stats);
+                        app.stats = Some(stats);
+                        app.error = None;
+                    }
+                    Err(e) => app.error = Some(e.to_string()),
+                }
+                next_refresh = Instant::now() + interval;
+            }
+
+            terminal.draw(|f| draw(f, &mut app))?;
+
+            let timeout = next_refresh.saturating_duration_since(Instant::now());
+            if event::poll(timeout)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    app.message = None;
+                    if let Some((target, signal)) = app.confirm.take() {
+                        if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                            app.message = Some(match kill::send(&target, signal) {
+                                Ok(()) => format!("sent {} to {}", signal.name(), target.pid),
+                                Err(e) => e,
+                            });
+                            next_refresh = Instant::now();
+                        }
+                        continue;
+                    }
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        KeyCode::Char('r') => next_refresh = Instant::now(),
+                        KeyCode::Char('c') => app.show_chart = !app.show_chart,
+                        KeyCode::Right | KeyCode::Tab if app.show_chart => app.cycle_chart_gpu(1),
+                        KeyCode::Left | KeyCode::BackTab if app.show_chart => app.cycle_chart_gpu(-1),
+                        KeyCode::Down | KeyCode::Char('j') => app.select_next(1),
+                        KeyCode::Up | KeyCode::Char('k') => app.select_next(-1),
+                        KeyCode::PageDown => app.select_next(app.page()),
+                        KeyCode::PageUp => app.select_next(-app.page()),
+                        KeyCode::Home | KeyCode::Char('g') => app.select_next(isize::MIN / 2),
+                        KeyCode::End | KeyCode::Char('G') => app.select_next(isize::MAX / 2),
+                        KeyCode::Char('x') if !app.show_chart => app.request_kill(KillSignal::Term),
+                        KeyCode::Char('X') if !app.show_chart => app.request_kill(KillSignal::Kill),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    })();
+    ratatui::restore();
+    result
+}
+
+fn draw(f: &mut Frame, app: &mut App) {
+    let gpu_count = app.stats.as_ref().map_or(0, |s| s.gpus.len()) as u16;
+    let columns = if gpu_count > 4 { 2 } else { 1 };
+    let panel_rows = gpu_count.div_ceil(columns).max(1);
+
+    let [header_area, gpu_area, proc_area, footer_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(panel_rows * PANEL_HEIGHT),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(f.area());
+
+    f.render_widget(Paragraph::new(header_line(app)), header_area);
+    let footer = if let Some((target, signal)) = &app.confirm {
+        Paragraph::new(format!("Send {} to {} ({})? y/n", signal.name(), target.pid, target.command))
+            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+    } else if let Some(message) = &app.message {
+        Paragraph::new(message.clone()).style(Style::default().fg(Color::Yellow))
+    } else if app.show_chart {
+        Paragraph::new("q: quit  r: refresh  c: back to panels  ←/→: previous/next GPU")
+            .style(Style::default().fg(Color::DarkGray))
+    } else {
+        Paragraph::new("q: quit  r: refresh  c: charts  ↑/↓: select process  PgUp/PgDn: scroll  Home/End: first/last  x/X: term/kill")
+            .style(Style::default().fg(Color::DarkGray))
+    };
+    f.render_widget(footer, footer_area);
+
+    let Some(stats) = &app.stats else {
+        return;
+    };
+
+    if app.show_chart {
+        let chart_area = gpu_area.union(proc_area);
+        match stats.gpus.get(app.chart_gpu) {
+            Some(gpu) => draw_chart(f, gpu, app.history.gpu(gpu.index), app.interval, chart_area),
+            None => f.render_widget(
+                Paragraph::new("(No GPUs are available)").style(Style::default().fg(Color::Yellow)),
+                chart_area,
+            ),
+        }
+        return;
+    }
+
+    if stats.gpus.is_empty() {
+        f.render_widget(
+            Paragraph::new("(No GPUs are available)").style(Style::default().fg(Color::Yellow)),
+            gpu_area,
+        );
+    } else {
+        let rows = Layout::vertical(vec![Constraint::Length(PANEL_HEIGHT); panel_rows as usize]).split(gpu_area);
+        for (i, gpu) in stats.gpus.iter().enumerate() {
+            let row = rows[i / columns as usize];
+            let cells = Layout::horizontal(vec![Constraint::Ratio(1, columns as u32); columns as usize]).split(row);
+            draw_gpu_panel(f, gpu, cells[i % columns as usize]);
+        }
+    }
+
+    // Borders (2) and the header row (1) are not available for processes
+    app.page_size = proc_area.height.saturating_sub(3) as usize;
+    draw_process_table(f, stats, &mut app.table, app.page_size, proc_area);
+}
+
+fn header_line(app: &App) -> Line<'static> {
+    if let Some(err) = &app.error {
+        return Line::from(Span::styled(
+            format!("Error querying NVIDIA devices: {}", err),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    match &app.stats {
+        Some(stats) => Line::from(vec![
+            Span::styled(stats.hostname.clone(), Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("  {}  ", stats.query_time.format("%Y-%m-%d %H:%M:%S"))),
+            Span::styled(
+                stats.driver_version.clone().unwrap_or_else(|| "N/A".to_string()),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(
+                stats.cuda_version.as_ref().map(|v| format!("  CUDA {}", v)).unwrap_or_default(),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]),
+        None => Line::from("Querying NVIDIA devices..."),
+    }
+}
+
+pub(crate) fn draw_gpu_panel(f: &mut Frame, gpu: &GpuStat, area: Rect) {
+    let mut title = vec![
+        Span::styled(format!("[{}] ", gpu.index), Style::default().fg(Color::Cyan)),
+        Span::styled(
+            gpu.display_name(),
+            Style::default().fg(if gpu.available { Color::Blue } else { Color::Red }),
+        ),
+    ];
+    if let Some(t) = gpu.temperature {
+        title.push(Span::styled(format!("  {}°C", t), Style::default().fg(Color::Red)));
+    }
+    if let Some(fan) = gpu.fan_speed {
+        title.push(Span::styled(format!("  fan {} %", fan), Style::default().fg(Color::Cyan)));
+    }
+    if let Some(p) = gpu.power_draw {
+        let limit = gpu.power_limit.map(|l| format!(" / {}", l)).unwrap_or_default();
+        title.push(Span::styled(format!("  {}{} W", p, limit), Style::default().fg(Color::Magenta)));
+    }
+
+    let block = Block::default().borders(Borders::ALL).title(Line::from(title));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let [util_area, mem_area] = Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).areas(inner);
+
+    let util = gpu.utilization.unwrap_or(0).min(100);
+    let util_label = match gpu.utilization {
+        Some(u) => format!("util {} %", u),
+        None => "util ?? %".to_string(),
+    };
+    f.render_widget(
+        Gauge::default()
+            .gauge_style(Style::default().fg(Color::Green))
+            .percent(util as u16)
+            .label(util_label),
+        util_area,
+    );
+
+    let mem_ratio = if gpu.memory_total > 0 {
+        (gpu.memory_used as f64 / gpu.memory_total as f64).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    f.render_widget(
+        Gauge::default()
+            .gauge_style(Style::default().fg(Color::Yellow))
+            .ratio(mem_ratio)
+            .label(format!("mem {} / {} MB", gpu.memory_used, gpu.memory_total)),
+        mem_area,
+    );
+}
+
+pub(crate) fn draw_process_table(
+    f: &mut Frame,
+    stats: &GpuStatCollection,
+    state: &mut TableState,
+    page_size: usize,
+    area: Rect,
+) {
+    let rows: Vec<Row> = stats
+        .gpus
+        .iter()
+        .flat_map(|gpu| {
+            gpu.processes.iter().flatten().map(move |p| {
+                let pid = match p.real_pid {
+                    Some(rp) => format!("{}->{}", p.pid, rp),
+                    None => p.pid.to_string(),
+                };
+                let mut mem = p
+                    .gpu_memory_usage
+                    .map(|m| format!("{}M", m))
+                    .unwrap_or_else(|| "?".to_string());
+                if let Some(pct) = p.gpu_memory_percent {
+                    mem.push_str(&format!(" {:.0}%", pct));
+                }
+                Row::new(vec![
+                    gpu.index.to_string(),
+                    pid,
+                    p.username.clone().unwrap_or_else(|| "--".to_string()),
+                    mem,
+                    p.command.clone(),
+                ])
+            })
+        })
+        .collect();
+
+    let total = rows.len();
+    let title = if total > page_size && page_size > 0 {
+        let first = state.offset().min(total.saturating_sub(page_size));
+        format!("Processes ({}-{} of {})", first + 1, (first + page_size).min(total), total)
+    } else {
+        format!("Processes ({})", total)
+    };
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(4),
+            Constraint::Length(16),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Min(10),
+        ],
+    )
+    .header(
+        Row::new(vec!["GPU", "PID", "USER", "GPU MEM", "COMMAND"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().borders(Borders::ALL).title(title))
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    f.render_stateful_widget(table, area, state);
+}
+
+/// Braille line chart of utilization, memory and power (all in % of max) over time
+fn draw_chart(f: &mut Frame, gpu: &GpuStat, history: Option<&GpuHistory>, interval: Duration, area: Rect) {
+    let step = interval.as_secs_f64();
+    // x is "seconds ago" (negative), so the newest sample sits at 0 on the right edge
+    let series = |values: Vec<Option<f64>>| -> Vec<(f64, f64)> {
+        let n = values.len();
+        values
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, v)| v.map(|v| (-((n - 1 - i) as f64) * step, v * 100.0)))
+            .collect()
+    };
+
+    let (util, mem, power) = match history {
+        Some(h) => (
+            series(h.utilization.iter().map(|u| u.map(|u| u as f64 / 100.0)).collect()),
+            series(h.memory.iter().map(|&m| Some(m)).collect()),
+            series(h.power.iter().copied().collect()),
+        ),
+        None => Default::default(),
+    };
+    let span = util.len().max(mem.len()).max(2) as f64 * step;
+
+    let datasets = vec![
+        Dataset::default()
+            .name("util %")
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Green))
+            .data(&util),
+        Dataset::default()
+            .name("mem %")
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Yellow))
+            .data(&mem),
+        Dataset::default()
+            .name("power %")
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Magenta))
+            .data(&power),
+    ];
+
+    let title = Line::from(vec![
+        Span::styled(format!("[{}] ", gpu.index), Style::default().fg(Color::Cyan)),
+        Span::styled(gpu.display_name(), Style::default().fg(Color::Blue)),
+    ]);
+    let chart = Chart::new(datasets)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .x_axis(
+            Axis::default()
+                .title("seconds ago")
+                .bounds([-span, 0.0])
+                .labels([format!("-{:.0}", span), "0".to_string()]),
+        )
+        .y_axis(
+            Axis::default()
+                .bounds([0.0, 100.0])
+                .labels(["0", "50", "100"]),
+        );
+    f.render_widget(chart, area);
+}
@@ -0,0 +1,368 @@
+//! Watch mode: periodic refresh loop with keyboard controls
+//! Keys (when stdin is a terminal): q quit, space pause/resume, +/- interval,
+//! f/p/e toggle fan/power/codec columns, s cycle sort order, r refresh now.
+
+use crate::display::{self, DisplayOptions, Render};
+use crate::history::History;
+use crate::logfile::RotatingLog;
+use crate::script::Script;
+use gpustat_core::{GpuBackend, GpuStatCollection, GpuStatDelta, QueryOptions, SortKey};
+use colored::Colorize;
+use crossterm::cursor;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use serde::Deserialize;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+const MIN_INTERVAL: f64 = 0.1;
+const INTERVAL_STEP: f64 = 0.5;
+
+/// `--interval auto`: fastest rate while the node is busy changing
+pub const AUTO_MIN_INTERVAL: Duration = Duration::from_millis(500);
+/// `--interval auto`: slowest rate once the node has been idle for a while
+const AUTO_MAX_INTERVAL: Duration = Duration::from_secs(5);
+const AUTO_BACKOFF: f64 = 1.5;
+/// Utilization change (percentage points) that counts as activity
+const AUTO_UTIL_DELTA: u32 = 5;
+/// Memory change (bytes) that counts as activity
+const AUTO_MEM_DELTA: u64 = 64 * 1024 * 1024;
+
+/// Value of `--interval`: a fixed number of seconds or `auto`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "toml::Value")]
+pub enum IntervalArg {
+    Seconds(f64),
+    Auto,
+}
+
+impl TryFrom<toml::Value> for IntervalArg {
+    type Error = String;
+
+    /// `interval = 2.5` or `interval = "auto"` in the config file
+    fn try_from(v: toml::Value) -> Result<Self, String> {
+        match v {
+            toml::Value::Float(secs) => Ok(IntervalArg::Seconds(secs)),
+            toml::Value::Integer(secs) => Ok(IntervalArg::Seconds(secs as f64)),
+            toml::Value::String(s) => parse_interval(&s),
+            other => Err(format!("invalid interval {} (expected seconds or 'auto')", other)),
+        }
+    }
+}
+
+pub fn parse_interval(s: &str) -> Result<IntervalArg, String> {
+    if s.eq_ignore_ascii_case("auto") {
+        return Ok(IntervalArg::Auto);
+    }
+    s.parse::<f64>()
+        .map(IntervalArg::Seconds)
+        .map_err(|_| format!("invalid interval '{}' (expected seconds or 'auto')", s))
+}
+
+/// Everything the watch loop needs besides the stats backend
+pub struct WatchConfig {
+    pub gpu_ids: Option<Vec<u32>>,
+    pub query: QueryOptions,
+    pub opts: DisplayOptions,
+    pub interval: Duration,
+    /// Adapt `interval` to activity (`--interval auto`)
+    pub adaptive: bool,
+    /// Stop after this many samples (`--count`)
+    pub count: Option<u64>,
+    /// Append each snapshot instead of redrawing in place (`--no-clear`)
+    pub no_clear: bool,
+    /// Snapshot sinks (`--log-file`)
+    pub logs: Vec<RotatingLog>,
+    /// Copy of what is shown (`--tee`)
+    pub tee: Option<RotatingLog>,
+    pub history: History,
+    /// Warn when a GPU's memory grows faster than this many bytes/s (`--alert-mem-growth`)
+    pub alert_mem_growth: Option<f64>,
+    /// Run on each snapshot before it is shown (`--script`)
+    pub script: Option<Script>,
+}
+
+/// What the loop should do after waiting for input
+enum Action {
+    Quit,
+    /// Query NVML again and redraw
+    Refresh,
+    /// Redraw the last snapshot (e.g. after toggling a column)
+    Redraw,
+}
+
+/// Puts the terminal into raw mode and restores it on drop
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        Ok(Self)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Switches to the alternate screen with a hidden cursor, restoring both on drop
+struct AltScreen;
+
+impl AltScreen {
+    fn enter() -> io::Result<Self> {
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for AltScreen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, LeaveAlternateScreen);
+    }
+}
+
+/// Overwrite the previous frame in place: home the cursor, clear the tail of
+/// every line and everything below the last one, so nothing flickers or lingers
+fn overwrite_frame(text: &str) -> String {
+    let mut frame = String::from("\x1b[H");
+    for line in text.lines() {
+        frame.push_str(line);
+        frame.push_str("\x1b[K\n");
+    }
+    frame.push_str("\x1b[J");
+    frame
+}
+
+pub fn run(backend: &dyn GpuBackend, mut cfg: WatchConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let tty = io::stdout().is_terminal();
+    // A bounded run should leave its output on the normal screen
+    let alt = if tty && cfg.count.is_none() && !cfg.no_clear {
+        Some(AltScreen::enter()?)
+    } else {
+        None
+    };
+    // Raw mode also turns off "\n" -> "\r\n" on output, which would end up in a pipe
+    let raw = if io::stdin().is_terminal() && tty {
+        Some(RawMode::enable()?)
+    } else {
+        None
+    };
+    let keyboard = raw.is_some();
+
+    let mut paused = false;
+    let mut last: Option<GpuStatCollection> = None;
+    // What the script printed for `last`, shown instead of the table
+    let mut printed: Option<String> = None;
+    let mut delta: Option<GpuStatDelta> = None;
+    let mut action = Action::Refresh;
+    let mut first_frame = true;
+    let mut samples = 0u64;
+    // Ticks are scheduled against a monotonic deadline so slow NVML queries don't accumulate drift
+    let mut next_tick = Instant::now();
+    let mut last_sample_at: Option<Instant> = None;
+
+    loop {
+        if matches!(action, Action::Refresh) && !paused {
+            let started = Instant::now();
+            let mut stats = backend.query(cfg.gpu_ids.as_deref(), &cfg.query)?;
+            stats.sample_period = last_sample_at.map(|t| started.duration_since(t).as_secs_f64());
+            last_sample_at = Some(started);
+            stats.apply_options(&cfg.opts);
+            if let Some(script) = &cfg.script {
+                printed = script.run(&mut stats)?;
+            }
+            if let Some(prev) = &last {
+                stats.set_deltas(prev);
+            }
+            delta = last.as_ref().map(|prev| stats.diff(prev));
+            cfg.history.record(&stats);
+            for log in &mut cfg.logs {
+                log.write_snapshot(&stats, &cfg.opts)?;
+            }
+            if let Some(tee) = &mut cfg.tee {
+                match &printed {
+                    Some(text) => tee.write_shown(text, &stats, &cfg.opts)?,
+                    None => tee.write_snapshot(&stats, &cfg.opts)?,
+                }
+            }
+            if cfg.adaptive {
+                cfg.interval = match &delta {
+                    Some(d) if !d.is_active(AUTO_UTIL_DELTA, AUTO_MEM_DELTA) => {
+                        cfg.interval.mul_f64(AUTO_BACKOFF).min(AUTO_MAX_INTERVAL)
+                    }
+                    _ => AUTO_MIN_INTERVAL,
+                };
+            }
+            last = Some(stats);
+            samples += 1;
+
+            next_tick += cfg.interval;
+            let now = Instant::now();
+            if next_tick < now {
+                // Fell behind (e.g. a query slower than the interval): skip missed ticks
+                next_tick = now;
+            }
+        } else if matches!(action, Action::Refresh) {
+            // Paused: keep ticking so the status line stays live without querying
+            next_tick = Instant::now() + cfg.interval;
+        }
+
+        if let Some(stats) = last.as_mut() {
+            // Re-sort in case the order was changed from the keyboard
+            if let Some(key) = cfg.opts.sort {
+                stats.sort_gpus(key);
+            }
+            // Re-measured every frame so the layout follows terminal resizes
+            let mut opts = cfg.opts.clone();
+            opts.width = opts.width.or_else(display::terminal_width);
+            let mut text = match &printed {
+                Some(text) => text.clone(),
+                None => stats.format_table(&opts, opts.use_color(), Some(&cfg.history)),
+            };
+            if let (Some(limit), Some(d)) = (cfg.alert_mem_growth, &delta) {
+                text.push_str(&memory_alerts(d, limit, opts.use_color()));
+            }
+            if keyboard {
+                text.push_str(&status_line(&cfg, paused));
+            }
+            let mut frame = if alt.is_some() {
+                overwrite_frame(&text)
+            } else if first_frame {
+                text
+            } else if cfg.no_clear {
+                // Scrolling mode: separate snapshots with a blank line
+                format!("\n{}", text)
+            } else if tty {
+                // A bounded run on the normal screen redraws in place too
+                overwrite_frame(&text)
+            } else {
+                // Not a terminal: fall back to clearing (cursor to 0,0 and clear)
+                format!("\x1b[H\x1b[J{}", text)
+            };
+            first_frame = false;
+            if keyboard {
+                // Raw mode disables output post-processing, so newlines need an explicit CR
+                frame = frame.replace('\n', "\r\n");
+            }
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(frame.as_bytes())?;
+            stdout.flush()?;
+        }
+
+        if cfg.count.is_some_and(|n| samples >= n) {
+            return Ok(());
+        }
+
+        action = if keyboard {
+            wait_for_key(&mut cfg, &mut paused, next_tick)?
+        } else {
+            std::thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+            Action::Refresh
+        };
+        if matches!(action, Action::Quit) {
+            return Ok(());
+        }
+    }
+}
+
+/// One line per GPU whose memory grew faster than `limit` bytes/s (e.g. a leak)
+fn memory_alerts(delta: &GpuStatDelta, limit: f64, use_color: bool) -> String {
+    let mut lines = String::new();
+    for g in delta.gpus.iter().filter(|g| g.memory_rate.is_some_and(|r| r > limit)) {
+        let line = format!(
+            "[{}] memory growing {:.0} MiB/s (+{}M in {:.1}s)",
+            g.index,
+            g.memory_rate.unwrap_or(0.0) / (1024.0 * 1024.0),
+            g.memory_growth / (1024 * 1024),
+            delta.period
+        );
+        let line = if use_color { line.red().bold().to_string() } else { line };
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+    lines
+}
+
+fn status_line(cfg: &WatchConfig, paused: bool) -> String {
+    let toggle = |on: bool, label: &str| {
+        if on {
+            label.to_uppercase()
+        } else {
+            label.to_string()
+        }
+    };
+    let mut line = format!(
+        "q: quit  space: {}  +/-: interval {}{:.1}s  {} {} {}  s: sort {}",
+        if paused { "resume" } else { "pause" },
+        if cfg.adaptive { "auto " } else { "" },
+        cfg.interval.as_secs_f64(),
+        toggle(cfg.opts.show_fan_speed, "f:fan"),
+        toggle(cfg.opts.show_power, "p:power"),
+        toggle(cfg.opts.show_codec, "e:codec"),
+        cfg.opts.sort.unwrap_or(SortKey::Index).name(),
+    );
+    if paused {
+        line.push_str("  [PAUSED]");
+    }
+    if cfg.opts.use_color() {
+        format!("{}\n", line.dimmed())
+    } else {
+        format!("{}\n", line)
+    }
+}
+
+/// Wait until the refresh deadline, handling keystrokes in the meantime
+fn wait_for_key(cfg: &mut WatchConfig, paused: &mut bool, deadline: Instant) -> io::Result<Action> {
+    loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        if timeout.is_zero() || !event::poll(timeout)? {
+            return Ok(Action::Refresh);
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let secs = cfg.interval.as_secs_f64();
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(Action::Quit),
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(Action::Quit),
+            KeyCode::Char(' ') => {
+                *paused = !*paused;
+                return Ok(Action::Redraw);
+            }
+            KeyCode::Char('r') => return Ok(Action::Refresh),
+            KeyCode::Char('+') | KeyCode::Char('=') => {
+                cfg.adaptive = false;
+                cfg.interval = Duration::from_secs_f64(secs + INTERVAL_STEP);
+                return Ok(Action::Redraw);
+            }
+            KeyCode::Char('-') => {
+                cfg.adaptive = false;
+                cfg.interval = Duration::from_secs_f64((secs - INTERVAL_STEP).max(MIN_INTERVAL));
+                return Ok(Action::Redraw);
+            }
+            KeyCode::Char('f') => {
+                cfg.opts.show_fan_speed = !cfg.opts.show_fan_speed;
+                return Ok(Action::Redraw);
+            }
+            KeyCode::Char('p') => {
+                cfg.opts.show_power = !cfg.opts.show_power;
+                return Ok(Action::Redraw);
+            }
+            KeyCode::Char('s') => {
+                cfg.opts.sort = Some(cfg.opts.sort.unwrap_or(SortKey::Index).next());
+                return Ok(Action::Redraw);
+            }
+            KeyCode::Char('e') => {
+                cfg.opts.show_codec = !cfg.opts.show_codec;
+                return Ok(Action::Redraw);
+            }
+            _ => {}
+        }
+    }
+}